@@ -0,0 +1,438 @@
+//! Addresses of interfaces and conversions between `std::net` addresses
+//! and the kernel's socket addresses
+use anyhow::{bail, Result};
+use bitflags::bitflags;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+/// Get `IpAddr` from sockaddr
+///
+/// A `sockaddr` is only large enough to hold an IPv4 address, IPv6
+/// addresses are converted from a `sockaddr_in6` with `ip_from_sockaddr_in6`.
+pub fn ip_from_sockaddr(sock_addr: &libc::sockaddr) -> Result<IpAddr> {
+    match sock_addr.sa_family as i32 {
+        // IPV4
+        libc::AF_INET => {
+            let mut arr = [0u8; 4];
+            for (byte, data) in arr.iter_mut().zip(&sock_addr.sa_data[2..]) {
+                *byte = *data as u8;
+            }
+            Ok(IpAddr::from(Ipv4Addr::from(arr)))
+        }
+        // IPV6
+        libc::AF_INET6 => bail!("IPv6 address does not fit in a sockaddr, use sockaddr_in6"),
+        _ => bail!("Received unknown sa_family"),
+    }
+}
+
+/// Get `sockaddr` from IpAddr
+pub fn sockaddr_from_ip(ip_addr: &IpAddr) -> Result<libc::sockaddr> {
+    let sa_family: libc::sa_family_t;
+    let mut sa_data = [0; 14];
+
+    match ip_addr {
+        IpAddr::V4(ip) => {
+            sa_family = libc::AF_INET as libc::sa_family_t;
+            for (data, byte) in sa_data[2..].iter_mut().zip(&ip.octets()) {
+                *data = *byte as _;
+            }
+        }
+        IpAddr::V6(_) => bail!("IPv6 address does not fit in a sockaddr, use sockaddr_in6"),
+    };
+
+    Ok(libc::sockaddr {
+        sa_family,
+        sa_data
+    })
+}
+
+/// Get `IpAddr` from sockaddr_in6
+pub fn ip_from_sockaddr_in6(sock_addr: &libc::sockaddr_in6) -> Result<IpAddr> {
+    match sock_addr.sin6_family as i32 {
+        libc::AF_INET6 => Ok(IpAddr::from(Ipv6Addr::from(sock_addr.sin6_addr.s6_addr))),
+        _ => bail!("Received unknown sin6_family"),
+    }
+}
+
+/// Get `sockaddr_in6` from IpAddr
+pub fn sockaddr_in6_from_ip(ip_addr: &IpAddr) -> Result<libc::sockaddr_in6> {
+    match ip_addr {
+        IpAddr::V6(ip) => {
+            // sockaddr_in6 has private padding on some targets, start from zeroes
+            let mut sock_addr: libc::sockaddr_in6 = unsafe { std::mem::zeroed() };
+            sock_addr.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sock_addr.sin6_addr.s6_addr = ip.octets();
+            Ok(sock_addr)
+        }
+        IpAddr::V4(_) => bail!("Expected an IPv6 address"),
+    }
+}
+
+/// Get the IPv4 netmask of a prefix length, e.g. `255.255.255.0` for 24
+pub fn netmask_from_prefix_len(prefix_len: u8) -> Result<Ipv4Addr> {
+    match prefix_len {
+        0 => Ok(Ipv4Addr::UNSPECIFIED),
+        1..=32 => Ok(Ipv4Addr::from(u32::MAX << (32 - prefix_len))),
+        _ => bail!("Invalid IPv4 prefix length '{}'", prefix_len),
+    }
+}
+
+/// Get the prefix length of an IPv4 netmask, e.g. 24 for `255.255.255.0`
+pub fn prefix_len_from_netmask(netmask: &Ipv4Addr) -> Result<u8> {
+    let mask = u32::from(*netmask);
+    let prefix_len = mask.leading_ones();
+    if mask.checked_shl(prefix_len).unwrap_or(0) != 0 {
+        bail!("Netmask '{}' is not contiguous", netmask);
+    }
+    Ok(prefix_len as u8)
+}
+
+/// Get the broadcast address of the IPv4 network `addr/prefix_len`
+///
+/// /31 and /32 networks have no broadcast address (RFC 3021).
+pub fn broadcast_from_prefix_len(addr: &Ipv4Addr, prefix_len: u8) -> Result<Option<Ipv4Addr>> {
+    let netmask = u32::from(netmask_from_prefix_len(prefix_len)?);
+    match prefix_len {
+        31 | 32 => Ok(None),
+        _ => Ok(Some(Ipv4Addr::from(u32::from(*addr) | !netmask))),
+    }
+}
+
+/// Address family of routing entries which may have no address to tell it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Family {
+    Inet,
+    Inet6,
+}
+
+impl Family {
+    /// Family of `addr`
+    pub fn of(addr: &IpAddr) -> Family {
+        match addr {
+            IpAddr::V4(_) => Family::Inet,
+            IpAddr::V6(_) => Family::Inet6,
+        }
+    }
+}
+
+impl fmt::Display for Family {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Family::Inet => write!(f, "inet"),
+            Family::Inet6 => write!(f, "inet6"),
+        }
+    }
+}
+
+/// Serializes as `"inet"` or `"inet6"`
+impl Serialize for Family {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// IP address with an optional prefix length, e.g. `10.1.2.3` or `2001:db8::5/64`
+///
+/// Serializes as `{"addr": "10.1.2.3", "prefix_len": 24}`, `prefix_len` is null when unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Cidr {
+    pub addr: IpAddr,
+    pub prefix_len: Option<u8>,
+}
+
+impl FromStr for Cidr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (s, None),
+        };
+        let addr = IpAddr::from_str(addr)?;
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(len) => match len.parse::<u8>() {
+                Ok(len) if len <= max_len => Some(len),
+                _ => bail!("Invalid prefix length '{}' for '{}'", len, addr),
+            },
+            None => None,
+        };
+        Ok(Cidr { addr, prefix_len })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.prefix_len {
+            Some(len) => write!(f, "{}/{}", self.addr, len),
+            None => write!(f, "{}", self.addr),
+        }
+    }
+}
+
+/// Scope of an address or route, how far from the host it is valid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Universe,
+    Site,
+    Link,
+    Host,
+    Nowhere,
+    Other(u8),
+}
+
+impl From<u8> for Scope {
+    fn from(scope: u8) -> Scope {
+        match scope {
+            0 => Scope::Universe,
+            200 => Scope::Site,
+            253 => Scope::Link,
+            254 => Scope::Host,
+            255 => Scope::Nowhere,
+            _ => Scope::Other(scope),
+        }
+    }
+}
+
+impl From<Scope> for u8 {
+    fn from(scope: Scope) -> u8 {
+        match scope {
+            Scope::Universe => 0,
+            Scope::Site => 200,
+            Scope::Link => 253,
+            Scope::Host => 254,
+            Scope::Nowhere => 255,
+            Scope::Other(scope) => scope,
+        }
+    }
+}
+
+/// Formats with the names iproute2 uses, `global` for the universe scope
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Universe => write!(f, "global"),
+            Scope::Site => write!(f, "site"),
+            Scope::Link => write!(f, "link"),
+            Scope::Host => write!(f, "host"),
+            Scope::Nowhere => write!(f, "nowhere"),
+            Scope::Other(scope) => write!(f, "{}", scope),
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "global" | "universe" => Ok(Scope::Universe),
+            "site" => Ok(Scope::Site),
+            "link" => Ok(Scope::Link),
+            "host" => Ok(Scope::Host),
+            "nowhere" => Ok(Scope::Nowhere),
+            _ => match s.parse::<u8>() {
+                Ok(scope) => Ok(Scope::from(scope)),
+                Err(_) => bail!("Unknown scope '{}'", s),
+            },
+        }
+    }
+}
+
+/// Serializes as its iproute2 name, e.g. `"global"`
+impl Serialize for Scope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+bitflags! {
+    /// Flags of an address, IFA_F_* in linux/if_addr.h
+    pub struct AddressFlags: u32 {
+        /// Secondary IPv4 address, or temporary IPv6 address
+        const SECONDARY = 0x01;
+        /// Skip duplicate address detection
+        const NODAD = 0x02;
+        const OPTIMISTIC = 0x04;
+        const DADFAILED = 0x08;
+        const HOMEADDRESS = 0x10;
+        const DEPRECATED = 0x20;
+        const TENTATIVE = 0x40;
+        /// Configured rather than learned through autoconfiguration
+        const PERMANENT = 0x80;
+        const MANAGETEMPADDR = 0x100;
+        /// Don't add the prefix route of the address
+        const NOPREFIXROUTE = 0x200;
+        const MCAUTOJOIN = 0x400;
+        const STABLE_PRIVACY = 0x800;
+    }
+}
+
+const ADDRESS_FLAG_NAMES: &[(AddressFlags, &str)] = &[
+    (AddressFlags::SECONDARY, "secondary"),
+    (AddressFlags::NODAD, "nodad"),
+    (AddressFlags::OPTIMISTIC, "optimistic"),
+    (AddressFlags::DADFAILED, "dadfailed"),
+    (AddressFlags::HOMEADDRESS, "home"),
+    (AddressFlags::DEPRECATED, "deprecated"),
+    (AddressFlags::TENTATIVE, "tentative"),
+    (AddressFlags::PERMANENT, "permanent"),
+    (AddressFlags::MANAGETEMPADDR, "mngtmpaddr"),
+    (AddressFlags::NOPREFIXROUTE, "noprefixroute"),
+    (AddressFlags::MCAUTOJOIN, "autojoin"),
+    (AddressFlags::STABLE_PRIVACY, "stable-privacy"),
+];
+
+impl AddressFlags {
+    /// iproute2 names of the flags set, e.g. `["secondary", "nodad"]`
+    pub fn names(&self) -> Vec<&'static str> {
+        ADDRESS_FLAG_NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+/// Formats as space separated iproute2 names, e.g. `secondary nodad`
+impl fmt::Display for AddressFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.names().join(" "))
+    }
+}
+
+/// Parses comma separated iproute2 names
+impl FromStr for AddressFlags {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut flags = AddressFlags::empty();
+        for name in s.split(',').filter(|name| !name.is_empty()) {
+            match ADDRESS_FLAG_NAMES.iter().find(|(_, known)| *known == name) {
+                Some((flag, _)) => flags |= *flag,
+                None => bail!("Unknown address flag '{}'", name),
+            }
+        }
+        Ok(flags)
+    }
+}
+
+/// Serializes as the list of iproute2 names, e.g. `["secondary"]`
+impl Serialize for AddressFlags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.names())
+    }
+}
+
+/// Address assigned to an interface, along with its properties
+///
+/// Serializes with the fields of `cidr` inlined.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Address {
+    #[serde(flatten)]
+    pub cidr: Cidr,
+    /// Remote end of a point-to-point link
+    pub peer: Option<IpAddr>,
+    pub broadcast: Option<Ipv4Addr>,
+    /// Label of an IPv4 address, `<interface>` or `<interface>:<alias>`
+    pub label: Option<String>,
+    pub scope: Scope,
+    pub flags: AddressFlags,
+    /// Seconds the address stays valid, unset for forever
+    pub valid_lifetime: Option<u32>,
+    /// Seconds the address stays preferred, unset for forever
+    pub preferred_lifetime: Option<u32>,
+}
+
+impl Address {
+    /// Permanent address of global scope without broadcast, peer or label
+    pub fn new(cidr: Cidr) -> Address {
+        Address {
+            cidr,
+            peer: None,
+            broadcast: None,
+            label: None,
+            scope: Scope::Universe,
+            flags: AddressFlags::empty(),
+            valid_lifetime: None,
+            preferred_lifetime: None,
+        }
+    }
+
+    /// Prefix length of the address, /32 or /128 when unset
+    pub fn prefix_len(&self) -> u8 {
+        match (self.cidr.prefix_len, self.cidr.addr) {
+            (Some(len), _) => len,
+            (None, IpAddr::V4(_)) => 32,
+            (None, IpAddr::V6(_)) => 128,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cidr() {
+        let cidr: Cidr = "10.1.2.3/24".parse().unwrap();
+        assert_eq!(cidr.addr, IpAddr::from([10, 1, 2, 3]));
+        assert_eq!(cidr.prefix_len, Some(24));
+        assert_eq!(cidr.to_string(), "10.1.2.3/24");
+
+        let cidr: Cidr = "2001:db8::5/128".parse().unwrap();
+        assert_eq!(cidr.addr, "2001:db8::5".parse::<IpAddr>().unwrap());
+        assert_eq!(cidr.prefix_len, Some(128));
+
+        let cidr: Cidr = "10.1.2.3".parse().unwrap();
+        assert_eq!(cidr.prefix_len, None);
+        assert_eq!(cidr.to_string(), "10.1.2.3");
+    }
+
+    #[test]
+    fn parse_invalid_cidr() {
+        for s in ["", "10.1.2.3/33", "2001:db8::5/129", "10.1.2.3/", "10.1.2.3/-1", "10.1.2/24", "host/24"] {
+            assert!(s.parse::<Cidr>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn netmask() {
+        assert_eq!(netmask_from_prefix_len(24).unwrap(), Ipv4Addr::new(255, 255, 255, 0));
+        assert_eq!(netmask_from_prefix_len(0).unwrap(), Ipv4Addr::UNSPECIFIED);
+        assert_eq!(netmask_from_prefix_len(32).unwrap(), Ipv4Addr::BROADCAST);
+        assert!(netmask_from_prefix_len(33).is_err());
+        assert_eq!(prefix_len_from_netmask(&Ipv4Addr::new(255, 255, 240, 0)).unwrap(), 20);
+        assert_eq!(prefix_len_from_netmask(&Ipv4Addr::UNSPECIFIED).unwrap(), 0);
+        assert!(prefix_len_from_netmask(&Ipv4Addr::new(255, 0, 255, 0)).is_err());
+    }
+
+    #[test]
+    fn serialize_address() {
+        let mut address = Address::new("192.0.2.2/24".parse().unwrap());
+        address.broadcast = Some(Ipv4Addr::new(192, 0, 2, 255));
+        address.label = Some("eth0".to_string());
+        address.flags = AddressFlags::PERMANENT;
+        // The fields of the Cidr are next to the others, as docs/output.md describes
+        assert_eq!(
+            serde_json::to_value(&address).unwrap(),
+            serde_json::json!({
+                "addr": "192.0.2.2",
+                "prefix_len": 24,
+                "peer": null,
+                "broadcast": "192.0.2.255",
+                "label": "eth0",
+                "scope": "global",
+                "flags": ["permanent"],
+                "valid_lifetime": null,
+                "preferred_lifetime": null
+            })
+        );
+
+        let cidr: Cidr = "2001:db8::5".parse().unwrap();
+        let expected = serde_json::json!({"addr": "2001:db8::5", "prefix_len": null});
+        assert_eq!(serde_json::to_value(cidr).unwrap(), expected);
+    }
+}
@@ -0,0 +1,772 @@
+//! Backends managing virtual links and bridges, the addresses of interfaces,
+//! the routing tables, the policy routing rules and the neighbour tables
+//!
+//! [`NetlinkBackend`] talks rtnetlink and supports any number of addresses per
+//! interface with labels, lifetimes and flags, routes of every type and table,
+//! policy routing rules, IPv4 and IPv6 neighbours, every kind of link and
+//! moving links between network namespaces, bridge options, VLANs and
+//! forwarding databases, and WireGuard devices
+//! through generic netlink. [`IoctlBackend`] uses the
+//! netdevice and routing ioctls and is kept as the fallback where netlink is
+//! unavailable, it has no support for rules.
+use crate::addr::{prefix_len_from_netmask, Address, AddressFlags, Cidr, Scope};
+use crate::bridge::{BridgeOptions, BridgeVlan, FdbEntry};
+use crate::flags::InterfaceFlags;
+use crate::interface::{ipv4_addresses, ipv6_addresses, Interface};
+use crate::ioctl::*;
+use crate::link::{vlans, Link, LinkKind, VlanProtocol};
+use crate::mac::LinkType;
+use crate::neigh::{arp_entries, Neighbour, NeighbourState};
+use crate::netlink::{self, NetlinkSocket, NETLINK_GENERIC, NETLINK_ROUTE};
+use crate::netns::Netns;
+use crate::route::{self, ipv4_routes, ipv6_routes, Route, Table};
+use crate::rule::Rule;
+use crate::socket::ControlSocket;
+use crate::tun;
+use crate::wireguard::{WireguardConfig, WireguardDevice};
+use anyhow::{anyhow, bail, Result};
+use ifstructs::ifreq;
+use log::warn;
+use nix::errno::Errno;
+use nix::sys::socket::AddressFamily;
+use std::ffi::CString;
+use std::net::IpAddr;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// Operations on links, the addresses of interfaces, routes, policy routing rules and neighbours
+pub trait Backend {
+    /// Create the virtual link `link`
+    fn add_link(&self, link: &Link) -> Result<()>;
+
+    /// Delete the virtual link `interface`, veths taking their peer along
+    fn del_link(&self, interface: &Interface) -> Result<()>;
+
+    /// Enslave `interface` to the bond or bridge `master`, or release it from its master when unset
+    fn set_master(&self, interface: &Interface, master: Option<&Interface>) -> Result<()>;
+
+    /// Replace the endpoints and options of the tunnel `interface` by those of `kind`, which must be its kind
+    fn change_tunnel(&self, interface: &Interface, kind: &LinkKind) -> Result<()>;
+
+    /// Move `interface` into the network namespace `netns`, where it is down and without addresses
+    fn set_netns(&self, _interface: &Interface, _netns: &Netns) -> Result<()> {
+        bail!("Moving interfaces between network namespaces needs the netlink backend")
+    }
+
+    /// Change the options of `bridge` which are set in `options`
+    fn set_bridge_options(&self, _bridge: &Interface, _options: &BridgeOptions) -> Result<()> {
+        bail!("Bridge options need the netlink backend")
+    }
+
+    /// VLANs of every bridge port
+    fn bridge_vlans(&self) -> Result<Vec<BridgeVlan>> {
+        bail!("Bridge VLANs need the netlink backend")
+    }
+
+    /// Add the port of `vlan` to it, or update its flags there
+    fn add_bridge_vlan(&self, _vlan: &BridgeVlan) -> Result<()> {
+        bail!("Bridge VLANs need the netlink backend")
+    }
+
+    /// Remove the port of `vlan` from it
+    fn del_bridge_vlan(&self, _vlan: &BridgeVlan) -> Result<()> {
+        bail!("Bridge VLANs need the netlink backend")
+    }
+
+    /// Entries of the forwarding databases of every bridge and port
+    fn fdb(&self) -> Result<Vec<FdbEntry>> {
+        bail!("Forwarding databases need the netlink backend")
+    }
+
+    /// Add `entry`, failing if its MAC address already has one
+    fn add_fdb(&self, _entry: &FdbEntry) -> Result<()> {
+        bail!("Forwarding databases need the netlink backend")
+    }
+
+    /// Add `entry`, as another destination of its MAC address if it already has one
+    fn append_fdb(&self, _entry: &FdbEntry) -> Result<()> {
+        bail!("Forwarding databases need the netlink backend")
+    }
+
+    /// Remove `entry`
+    fn del_fdb(&self, _entry: &FdbEntry) -> Result<()> {
+        bail!("Forwarding databases need the netlink backend")
+    }
+
+    /// Public key, port and peers of the WireGuard device `interface`
+    fn wireguard(&self, _interface: &Interface) -> Result<WireguardDevice> {
+        bail!("WireGuard devices need the netlink backend")
+    }
+
+    /// Apply `config` to the WireGuard device `interface`
+    fn set_wireguard(&self, _interface: &Interface, _config: &WireguardConfig) -> Result<()> {
+        bail!("WireGuard devices need the netlink backend")
+    }
+
+    /// Addresses of `interface`, IPv4 first
+    fn addresses(&self, interface: &Interface) -> Result<Vec<Address>>;
+
+    /// Add `address` to `interface`, failing if it already has it
+    fn add_address(&self, interface: &Interface, address: &Address) -> Result<()>;
+
+    /// Add `address` to `interface`, or update its properties if it already has it
+    fn replace_address(&self, interface: &Interface, address: &Address) -> Result<()>;
+
+    /// Remove `address` from `interface`
+    fn del_address(&self, interface: &Interface, address: &Address) -> Result<()>;
+
+    /// Address of `interface` matching `cidr`, the prefix length only being compared when given
+    fn find_address(&self, interface: &Interface, cidr: &Cidr) -> Result<Option<Address>> {
+        Ok(self.addresses(interface)?.into_iter().find(|address| {
+            address.cidr.addr == cidr.addr && cidr.prefix_len.is_none_or(|len| len == address.prefix_len())
+        }))
+    }
+
+    /// Remove the addresses of `interface` selected by `filter`, returning them
+    ///
+    /// Addresses are removed last to first, secondary ones before the primary
+    /// ones which would otherwise take them along.
+    fn flush_addresses(&self, interface: &Interface, filter: &dyn Fn(&Address) -> bool) -> Result<Vec<Address>> {
+        let mut addresses: Vec<Address> = self.addresses(interface)?.into_iter().filter(|a| filter(a)).collect();
+        addresses.reverse();
+        addresses.sort_by_key(|address| !address.flags.contains(AddressFlags::SECONDARY));
+        for address in &addresses {
+            self.del_address(interface, address)?;
+        }
+        Ok(addresses)
+    }
+
+    /// Routes of every table the backend can see
+    fn routes(&self) -> Result<Vec<Route>>;
+
+    /// Add `route`, failing if an identical one exists
+    fn add_route(&self, route: &Route) -> Result<()>;
+
+    /// Remove `route`, the fields left unset matching any route
+    fn del_route(&self, route: &Route) -> Result<()>;
+
+    /// Route used to reach `destination`
+    fn get_route(&self, destination: &IpAddr) -> Result<Route>;
+
+    /// Policy routing rules of both families
+    fn rules(&self) -> Result<Vec<Rule>> {
+        bail!("Policy routing rules need the netlink backend")
+    }
+
+    /// Add `rule`
+    fn add_rule(&self, _rule: &Rule) -> Result<()> {
+        bail!("Policy routing rules need the netlink backend")
+    }
+
+    /// Remove `rule`, the fields left unset matching any rule
+    fn del_rule(&self, _rule: &Rule) -> Result<()> {
+        bail!("Policy routing rules need the netlink backend")
+    }
+
+    /// Neighbours of every interface
+    fn neighbours(&self) -> Result<Vec<Neighbour>>;
+
+    /// Add `neighbour`, failing if an entry for its address exists
+    fn add_neighbour(&self, neighbour: &Neighbour) -> Result<()>;
+
+    /// Add `neighbour`, or update the existing entry for its address
+    fn replace_neighbour(&self, neighbour: &Neighbour) -> Result<()>;
+
+    /// Remove the entry for the address of `neighbour`
+    fn del_neighbour(&self, neighbour: &Neighbour) -> Result<()>;
+
+    /// Remove the neighbours selected by `filter`, returning them
+    fn flush_neighbours(&self, filter: &dyn Fn(&Neighbour) -> bool) -> Result<Vec<Neighbour>> {
+        let neighbours: Vec<Neighbour> = self.neighbours()?.into_iter().filter(|n| filter(n)).collect();
+        for neighbour in &neighbours {
+            self.del_neighbour(neighbour)?;
+        }
+        Ok(neighbours)
+    }
+}
+
+/// Netlink backend if a NETLINK_ROUTE socket can be opened, the ioctl one otherwise
+pub fn default_backend() -> Box<dyn Backend> {
+    match NetlinkBackend::new() {
+        Ok(backend) => Box::new(backend),
+        Err(e) => {
+            warn!("Falling back to ioctls: {}", e);
+            Box::new(IoctlBackend)
+        }
+    }
+}
+
+/// Backend using rtnetlink for addresses, routes, rules, neighbours, links, bridges and their forwarding databases,
+/// and generic netlink for WireGuard devices
+#[derive(Debug)]
+pub struct NetlinkBackend {
+    sock: NetlinkSocket,
+}
+
+impl NetlinkBackend {
+    pub fn new() -> Result<NetlinkBackend> {
+        Ok(NetlinkBackend {
+            sock: NetlinkSocket::open(NETLINK_ROUTE)?,
+        })
+    }
+}
+
+impl Backend for NetlinkBackend {
+    fn add_link(&self, link: &Link) -> Result<()> {
+        match link.kind {
+            // rtnetlink can't create them, /dev/net/tun has to
+            LinkKind::Tun(_) | LinkKind::Tap(_) => IoctlBackend.add_link(link),
+            _ => netlink::link::add(&self.sock, link),
+        }
+    }
+
+    fn del_link(&self, interface: &Interface) -> Result<()> {
+        netlink::link::del(&self.sock, interface.index()?)
+            .map_err(|e| e.context(format!("Failed to delete link '{}'", interface.name())))
+    }
+
+    fn set_master(&self, interface: &Interface, master: Option<&Interface>) -> Result<()> {
+        let index = match master {
+            Some(master) => {
+                // Bonds refuse slaves which are up, and bring them back up themselves
+                if master.bond()?.is_some() {
+                    interface.down()?;
+                }
+                master.index()? as u32
+            }
+            None => 0,
+        };
+        netlink::link::set_master(&self.sock, interface.index()?, index).map_err(|e| match master {
+            Some(master) => e.context(format!("Failed to enslave '{}' to '{}'", interface.name(), master.name())),
+            None => e.context(format!("Failed to release '{}' from its master", interface.name())),
+        })
+    }
+
+    fn set_netns(&self, interface: &Interface, netns: &Netns) -> Result<()> {
+        netlink::link::set_netns(&self.sock, interface.index()?, netns.as_raw_fd()).map_err(|e| {
+            e.context(format!("Failed to move '{}' to network namespace '{}'", interface.name(), netns.name()))
+        })
+    }
+
+    fn change_tunnel(&self, interface: &Interface, kind: &LinkKind) -> Result<()> {
+        if kind.tunnel_options().is_none() {
+            bail!("{} links are not tunnels", kind);
+        }
+        netlink::link::change(&self.sock, interface.index()?, kind)
+            .map_err(|e| e.context(format!("Failed to change tunnel '{}'", interface.name())))
+    }
+
+    fn set_bridge_options(&self, bridge: &Interface, options: &BridgeOptions) -> Result<()> {
+        netlink::link::set_bridge_options(&self.sock, bridge.index()?, options)
+            .map_err(|e| e.context(format!("Failed to set options of bridge '{}'", bridge.name())))
+    }
+
+    fn bridge_vlans(&self) -> Result<Vec<BridgeVlan>> {
+        netlink::link::vlans(&self.sock)
+    }
+
+    fn add_bridge_vlan(&self, vlan: &BridgeVlan) -> Result<()> {
+        netlink::link::add_vlan(&self.sock, Interface::new(&vlan.interface)?.index()?, vlan)
+            .map_err(|e| e.context(format!("Failed to add '{}' to VLAN {}", vlan.interface, vlan.vid)))
+    }
+
+    fn del_bridge_vlan(&self, vlan: &BridgeVlan) -> Result<()> {
+        netlink::link::del_vlan(&self.sock, Interface::new(&vlan.interface)?.index()?, vlan)
+            .map_err(|e| e.context(format!("Failed to remove '{}' from VLAN {}", vlan.interface, vlan.vid)))
+    }
+
+    fn fdb(&self) -> Result<Vec<FdbEntry>> {
+        netlink::neigh::fdb(&self.sock)
+    }
+
+    fn add_fdb(&self, entry: &FdbEntry) -> Result<()> {
+        netlink::neigh::add_fdb(&self.sock, entry, false)
+    }
+
+    fn append_fdb(&self, entry: &FdbEntry) -> Result<()> {
+        netlink::neigh::add_fdb(&self.sock, entry, true)
+    }
+
+    fn del_fdb(&self, entry: &FdbEntry) -> Result<()> {
+        netlink::neigh::del_fdb(&self.sock, entry)
+    }
+
+    fn wireguard(&self, interface: &Interface) -> Result<WireguardDevice> {
+        netlink::wireguard::get(&NetlinkSocket::open(NETLINK_GENERIC)?, interface.name())
+    }
+
+    fn set_wireguard(&self, interface: &Interface, config: &WireguardConfig) -> Result<()> {
+        netlink::wireguard::set(&NetlinkSocket::open(NETLINK_GENERIC)?, interface.name(), config)
+    }
+
+    fn addresses(&self, interface: &Interface) -> Result<Vec<Address>> {
+        let index = interface.index()? as u32;
+        let mut addresses: Vec<Address> = netlink::addr::list(&self.sock)?
+            .into_iter()
+            .filter(|(ifindex, _)| *ifindex == index)
+            .map(|(_, address)| address)
+            .collect();
+        addresses.sort_by_key(|address| address.cidr.addr.is_ipv6());
+        Ok(addresses)
+    }
+
+    fn add_address(&self, interface: &Interface, address: &Address) -> Result<()> {
+        netlink::addr::add(&self.sock, interface.index()? as u32, address, false)
+    }
+
+    fn replace_address(&self, interface: &Interface, address: &Address) -> Result<()> {
+        netlink::addr::add(&self.sock, interface.index()? as u32, address, true)
+    }
+
+    fn del_address(&self, interface: &Interface, address: &Address) -> Result<()> {
+        netlink::addr::del(&self.sock, interface.index()? as u32, address)
+    }
+
+    fn routes(&self) -> Result<Vec<Route>> {
+        netlink::route::list(&self.sock)
+    }
+
+    fn add_route(&self, route: &Route) -> Result<()> {
+        netlink::route::add(&self.sock, route, false)
+    }
+
+    fn del_route(&self, route: &Route) -> Result<()> {
+        netlink::route::del(&self.sock, route)
+    }
+
+    fn get_route(&self, destination: &IpAddr) -> Result<Route> {
+        netlink::route::get(&self.sock, destination)
+    }
+
+    fn rules(&self) -> Result<Vec<Rule>> {
+        netlink::rule::list(&self.sock)
+    }
+
+    fn add_rule(&self, rule: &Rule) -> Result<()> {
+        netlink::rule::add(&self.sock, rule)
+    }
+
+    fn del_rule(&self, rule: &Rule) -> Result<()> {
+        netlink::rule::del(&self.sock, rule)
+    }
+
+    fn neighbours(&self) -> Result<Vec<Neighbour>> {
+        netlink::neigh::list(&self.sock)
+    }
+
+    fn add_neighbour(&self, neighbour: &Neighbour) -> Result<()> {
+        netlink::neigh::add(&self.sock, neighbour, false)
+    }
+
+    fn replace_neighbour(&self, neighbour: &Neighbour) -> Result<()> {
+        netlink::neigh::add(&self.sock, neighbour, true)
+    }
+
+    fn del_neighbour(&self, neighbour: &Neighbour) -> Result<()> {
+        netlink::neigh::del(&self.sock, neighbour)
+    }
+}
+
+/// Backend using SIOCSIFADDR and friends
+///
+/// Each IPv4 address needs a label of its own, so IPv4 addresses added
+/// without a label to an interface which already has one get the first free
+/// alias label, e.g. `eth0:1`. Lifetimes and address flags are not supported.
+///
+/// Routes are limited to the main table, IPv4 ones to the unicast and
+/// unreachable types. Routes are read from procfs and looked up by longest
+/// prefix match in the main table. Neighbours are limited to the permanent
+/// and reachable IPv4 entries of the ARP table.
+///
+/// Bridges without options, 802.1Q VLANs, IPv4 tunnels, TUN and TAP devices
+/// are the only links which can be created. Bridge VLANs and forwarding
+/// databases are not supported.
+#[derive(Debug, Default)]
+pub struct IoctlBackend;
+
+impl IoctlBackend {
+    /// Set the address of the label of `address`, or add an IPv6 address
+    fn set_address(&self, interface: &Interface, address: &Address) -> Result<()> {
+        if address.valid_lifetime.is_some() || address.preferred_lifetime.is_some() || !address.flags.is_empty() {
+            bail!("Address lifetimes and flags need the netlink backend");
+        }
+
+        match address.cidr.addr {
+            IpAddr::V4(_) => {
+                let target = match &address.label {
+                    Some(label) => Interface::new(label)?,
+                    None => Interface::new(interface.name())?,
+                };
+                target.set_address(&address.cidr)?;
+                if let Some(broadcast) = address.broadcast {
+                    target.set_broadcast(&broadcast)?;
+                }
+                if let Some(IpAddr::V4(peer)) = address.peer {
+                    target.set_destination(&peer)?;
+                }
+            }
+            IpAddr::V6(_) => {
+                if address.peer.is_some() || address.label.is_some() {
+                    bail!("IPv6 peers and labels need the netlink backend");
+                }
+                interface.set_address(&address.cidr)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the VLAN `id` on top of `parent` through SIOCSIFVLAN, naming it `name`
+    fn add_vlan(&self, name: &str, parent: &str, id: u16) -> Result<Interface> {
+        let args = vlan_args(ADD_VLAN_CMD, parent, Some(id))?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        unsafe { set_vlan(sock.as_raw_fd(), &args) }
+            .map_err(|e| anyhow!("Failed to create vlan link '{}': {}", name, e))?;
+
+        // The kernel picks the name, `<parent>.<id>` unless told otherwise
+        let (created, _, _) = vlans()?
+            .into_iter()
+            .find(|(_, vid, vlan_parent)| *vid == id && vlan_parent == parent)
+            .ok_or_else(|| anyhow!("VLAN {} of '{}' vanished once created", id, parent))?;
+        let interface = Interface::new(&created)?;
+        if created == name {
+            return Ok(interface);
+        }
+        interface.rename(name).inspect_err(|_| {
+            let _ = self.del_link(&interface);
+        })
+    }
+
+    /// ip_tunnel_parm of the tunnel `name` of kind `kind`, along with the fallback device of its module
+    fn tunnel_parm(&self, name: &str, kind: &LinkKind) -> Result<(ip_tunnel_parm, &'static str)> {
+        let (protocol, fallback, options) = match kind {
+            LinkKind::Gre(options) => (libc::IPPROTO_GRE, "gre0", options),
+            LinkKind::Ipip(options) => (libc::IPPROTO_IPIP, "tunl0", options),
+            LinkKind::Sit(options) => (libc::IPPROTO_IPV6, "sit0", options),
+            LinkKind::Ip6tnl(_) => bail!("ip6tnl tunnels need the netlink backend"),
+            kind => bail!("{} links are not tunnels", kind),
+        };
+        let link = match &options.parent {
+            Some(parent) => Interface::new(parent)?.index()?,
+            None => 0,
+        };
+        Ok((tunnel_parm(name, protocol as u8, options, link)?, fallback))
+    }
+
+    /// Add the tunnel `name` through SIOCADDTUNNEL on the fallback device of its module
+    fn add_tunnel(&self, name: &str, kind: &LinkKind) -> Result<Interface> {
+        let (mut parm, fallback) = self.tunnel_parm(name, kind)?;
+        let mut ifr = ifreq::from_name(fallback)?;
+        set_data(&mut ifr, &mut parm);
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        match unsafe { add_ip_tunnel(sock.as_raw_fd(), &ifr) } {
+            Ok(_) => Interface::new(name),
+            Err(Errno::ENODEV) => {
+                bail!("Failed to create {} link '{}': {} is missing, is the module loaded?", kind, name, fallback)
+            }
+            Err(e) => bail!("Failed to create {} link '{}': {}", kind, name, e),
+        }
+    }
+
+    /// Add or remove `route` through SIOCADDRT or SIOCDELRT
+    fn change_route(&self, route: &Route, add: bool) -> Result<()> {
+        if route.table != Table::MAIN || route.source.is_some() || route.onlink {
+            bail!("Routes outside the main table, with a source address or onlink need the netlink backend");
+        }
+
+        let res = match route.destination.addr {
+            IpAddr::V4(_) => {
+                let dev = route.interface.as_deref().map(CString::new).transpose()?;
+                let rtentry = rtentry_from_route(route, dev.as_deref())?;
+                let sock = ControlSocket::shared(AddressFamily::Inet)?;
+                if add {
+                    unsafe { add_route(sock.as_raw_fd(), &rtentry) }
+                } else {
+                    unsafe { del_route(sock.as_raw_fd(), &rtentry) }
+                }
+            }
+            IpAddr::V6(_) => {
+                let ifindex = match &route.interface {
+                    Some(interface) => Interface::new(interface)?.index()?,
+                    None => 0,
+                };
+                let in6_rtmsg = in6_rtmsg_from_route(route, ifindex)?;
+                let sock = ControlSocket::shared(AddressFamily::Inet6)?;
+                if add {
+                    unsafe { add_route6(sock.as_raw_fd(), &in6_rtmsg) }
+                } else {
+                    unsafe { del_route6(sock.as_raw_fd(), &in6_rtmsg) }
+                }
+            }
+        };
+        res.map_err(|e| anyhow!("Failed to {} route '{}': {}", if add { "add" } else { "remove" }, route, e))?;
+        Ok(())
+    }
+
+    /// ARP entry of the IPv4 neighbour `neighbour`, `None` when there is none
+    fn arp_entry(&self, neighbour: &Neighbour) -> Result<Option<libc::arpreq>> {
+        if neighbour.address.is_ipv6() {
+            bail!("IPv6 neighbours need the netlink backend");
+        }
+        let mut arpreq = arpreq_from_ip(&neighbour.address, &neighbour.interface)?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        match unsafe { get_arp(sock.as_raw_fd(), &mut arpreq) } {
+            Ok(_) => Ok(Some(arpreq)),
+            Err(Errno::ENXIO) => Ok(None),
+            Err(e) => bail!("Failed to get neighbour '{}': {}", neighbour.address, e),
+        }
+    }
+
+    /// Set the ARP entry of `neighbour` through SIOCSARP
+    fn set_arp_entry(&self, neighbour: &Neighbour) -> Result<()> {
+        let permanent = match neighbour.state {
+            NeighbourState::Permanent => true,
+            NeighbourState::Reachable => false,
+            state => bail!("{} neighbours need the netlink backend", state),
+        };
+        let mac = neighbour
+            .mac
+            .ok_or_else(|| anyhow!("Neighbour '{}' needs a MAC address", neighbour.address))?;
+        let mut arpreq = arpreq_from_ip(&neighbour.address, &neighbour.interface)?;
+        set_arp_hwaddr(&mut arpreq, &mac, permanent);
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        unsafe { set_arp(sock.as_raw_fd(), &arpreq) }
+            .map_err(|e| anyhow!("Failed to add neighbour '{}': {}", neighbour.address, e))?;
+        Ok(())
+    }
+}
+
+/// The interface name if it has no IPv4 address, otherwise its first unused alias label
+fn free_label(interface: &Interface, existing: &[Address]) -> String {
+    let labels: Vec<&str> = existing.iter().filter_map(|a| a.label.as_deref()).collect();
+    if labels.is_empty() {
+        return interface.name().to_string();
+    }
+    (1..)
+        .map(|alias| format!("{}:{}", interface.name(), alias))
+        .find(|label| !labels.contains(&label.as_str()))
+        .unwrap()
+}
+
+impl Backend for IoctlBackend {
+    fn add_link(&self, link: &Link) -> Result<()> {
+        let interface = match &link.kind {
+            LinkKind::Bridge(options) => {
+                if !options.is_empty() {
+                    bail!("Bridge options need the netlink backend");
+                }
+                let name = CString::new(link.name.as_str())?;
+                let sock = ControlSocket::shared(AddressFamily::Inet)?;
+                unsafe { add_bridge(sock.as_raw_fd(), name.as_ptr()) }
+                    .map_err(|e| anyhow!("Failed to create bridge link '{}': {}", link.name, e))?;
+                Interface::new(&link.name)?
+            }
+            LinkKind::Vlan {
+                parent,
+                id,
+                protocol: VlanProtocol::Ieee8021Q,
+            } => self.add_vlan(&link.name, parent, *id)?,
+            LinkKind::Vlan { protocol, .. } => bail!("{} VLANs need the netlink backend", protocol),
+            LinkKind::Gre(_) | LinkKind::Ipip(_) | LinkKind::Sit(_) => self.add_tunnel(&link.name, &link.kind)?,
+            LinkKind::Ip6tnl(_) => bail!("ip6tnl tunnels need the netlink backend"),
+            LinkKind::Tun(options) => tun::create(&link.name, false, options)?,
+            LinkKind::Tap(options) => tun::create(&link.name, true, options)?,
+            kind => bail!("Creating {} links needs the netlink backend", kind),
+        };
+
+        // Don't leave a half configured link behind
+        let res = link
+            .mtu
+            .map(|mtu| interface.set_mtu(mtu))
+            .transpose()
+            .and_then(|_| link.mac.map(|mac| interface.set_mac_address(&mac)).transpose());
+        if let Err(e) = res {
+            if let Err(del) = self.del_link(&interface) {
+                warn!("Failed to delete link '{}': {}", interface.name(), del);
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn del_link(&self, interface: &Interface) -> Result<()> {
+        // Otherwise a missing interface is only noticed when reading its hardware address
+        interface.index()?;
+        if tun::is_tun(interface.name()) {
+            return tun::delete(interface.name());
+        }
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        if Path::new("/sys/class/net").join(interface.name()).join("bridge").exists() {
+            // Bridges which are up are busy
+            interface.down()?;
+            let name = CString::new(interface.name())?;
+            unsafe { del_bridge(sock.as_raw_fd(), name.as_ptr()) }
+        } else if vlans()?.iter().any(|(name, _, _)| name == interface.name()) {
+            let args = vlan_args(DEL_VLAN_CMD, interface.name(), None)?;
+            unsafe { set_vlan(sock.as_raw_fd(), &args) }
+        } else if matches!(
+            interface.hw_address()?.link_type,
+            LinkType::Gre | LinkType::Ipip | LinkType::Sit
+        ) {
+            // The tunnel being asked to delete itself, the parameters are ignored
+            let mut parm = ip_tunnel_parm::default();
+            let mut ifr = ifreq::from_name(interface.name())?;
+            set_data(&mut ifr, &mut parm);
+            unsafe { del_ip_tunnel(sock.as_raw_fd(), &ifr) }
+        } else {
+            bail!("Deleting links other than bridges, VLANs, IPv4 tunnels, TUN and TAP needs the netlink backend");
+        }
+        .map_err(|e| anyhow!("Failed to delete link '{}': {}", interface.name(), e))?;
+        Ok(())
+    }
+
+    fn change_tunnel(&self, interface: &Interface, kind: &LinkKind) -> Result<()> {
+        let (mut parm, _) = self.tunnel_parm(interface.name(), kind)?;
+        let mut ifr = ifreq::from_name(interface.name())?;
+        set_data(&mut ifr, &mut parm);
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        unsafe { change_ip_tunnel(sock.as_raw_fd(), &ifr) }
+            .map_err(|e| anyhow!("Failed to change tunnel '{}': {}", interface.name(), e))?;
+        Ok(())
+    }
+
+    fn set_master(&self, interface: &Interface, master: Option<&Interface>) -> Result<()> {
+        let (master, enslave) = match master {
+            Some(master) => (master.clone(), true),
+            None => match interface.master()? {
+                Some(master) => (master, false),
+                None => bail!("Interface '{}' has no master", interface.name()),
+            },
+        };
+        if enslave {
+            master.enslave(interface)
+        } else {
+            master.release(interface)
+        }
+    }
+
+    fn addresses(&self, interface: &Interface) -> Result<Vec<Address>> {
+        let mut addresses = Vec::new();
+        for (label, addr) in ipv4_addresses()? {
+            // Aliases are labeled `<name>:<alias>`
+            if label.split(':').next() != Some(interface.name()) {
+                continue;
+            }
+
+            let alias = Interface::new(&label)?;
+            let flags = alias.flags()?;
+            let mut address = Address::new(Cidr {
+                addr: IpAddr::from(addr),
+                prefix_len: prefix_len_from_netmask(&alias.netmask()?).ok(),
+            });
+            if flags.contains(InterfaceFlags::BROADCAST) {
+                address.broadcast = Some(alias.broadcast()?).filter(|broadcast| !broadcast.is_unspecified());
+            }
+            if flags.contains(InterfaceFlags::POINTOPOINT) {
+                address.peer = Some(IpAddr::from(alias.destination()?)).filter(|peer| *peer != address.cidr.addr);
+            }
+            if addr.is_loopback() {
+                address.scope = Scope::Host;
+            }
+            address.label = Some(label);
+            addresses.push(address);
+        }
+
+        let index = interface.index()? as u32;
+        addresses.extend(
+            ipv6_addresses()?
+                .into_iter()
+                .filter(|(ifindex, _)| *ifindex == index)
+                .map(|(_, address)| address),
+        );
+        Ok(addresses)
+    }
+
+    fn add_address(&self, interface: &Interface, address: &Address) -> Result<()> {
+        let existing = self.addresses(interface)?;
+        if existing.iter().any(|a| a.cidr.addr == address.cidr.addr) {
+            bail!("Interface '{}' already has the address '{}'", interface.name(), address.cidr.addr);
+        }
+
+        let mut address = address.clone();
+        if address.cidr.addr.is_ipv4() && address.label.is_none() {
+            address.label = Some(free_label(interface, &existing));
+        }
+        self.set_address(interface, &address)
+    }
+
+    fn replace_address(&self, interface: &Interface, address: &Address) -> Result<()> {
+        let existing = self.addresses(interface)?;
+        let mut address = address.clone();
+        match existing.iter().find(|a| a.cidr.addr == address.cidr.addr) {
+            // Setting the address of its label again updates its netmask, broadcast and peer
+            Some(current) if address.cidr.addr.is_ipv4() && address.label.is_none() => {
+                address.label = current.label.clone();
+            }
+            Some(_) if address.cidr.addr.is_ipv4() => {}
+            // IPv6 addresses are identified by their prefix length as well
+            Some(current) => self.del_address(interface, current)?,
+            None if address.cidr.addr.is_ipv4() && address.label.is_none() => {
+                address.label = Some(free_label(interface, &existing));
+            }
+            None => {}
+        }
+        self.set_address(interface, &address)
+    }
+
+    fn del_address(&self, interface: &Interface, address: &Address) -> Result<()> {
+        interface.remove_address(&address.cidr)
+    }
+
+    fn routes(&self) -> Result<Vec<Route>> {
+        let mut routes = ipv4_routes()?;
+        routes.extend(ipv6_routes()?);
+        Ok(routes)
+    }
+
+    fn add_route(&self, route: &Route) -> Result<()> {
+        self.change_route(route, true)
+    }
+
+    fn del_route(&self, route: &Route) -> Result<()> {
+        self.change_route(route, false)
+    }
+
+    fn get_route(&self, destination: &IpAddr) -> Result<Route> {
+        let routes = self.routes()?;
+        route::lookup(&routes, destination)
+            .cloned()
+            .ok_or_else(|| anyhow!("No route to '{}'", destination))
+    }
+
+    fn neighbours(&self) -> Result<Vec<Neighbour>> {
+        arp_entries()
+    }
+
+    fn add_neighbour(&self, neighbour: &Neighbour) -> Result<()> {
+        // SIOCSARP silently overwrites existing entries
+        if let Some(arpreq) = self.arp_entry(neighbour)? {
+            if get_arp_hwaddr(&arpreq).is_some() {
+                bail!("Neighbour '{}' already exists", neighbour.address);
+            }
+        }
+        self.set_arp_entry(neighbour)
+    }
+
+    fn replace_neighbour(&self, neighbour: &Neighbour) -> Result<()> {
+        if neighbour.address.is_ipv6() {
+            bail!("IPv6 neighbours need the netlink backend");
+        }
+        self.set_arp_entry(neighbour)
+    }
+
+    fn del_neighbour(&self, neighbour: &Neighbour) -> Result<()> {
+        if neighbour.address.is_ipv6() {
+            bail!("IPv6 neighbours need the netlink backend");
+        }
+        let arpreq = arpreq_from_ip(&neighbour.address, &neighbour.interface)?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        unsafe { del_arp(sock.as_raw_fd(), &arpreq) }
+            .map_err(|e| anyhow!("Failed to remove neighbour '{}': {}", neighbour.address, e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,400 @@
+//! Bonding devices, aggregating several links into one, see the kernel's bonding.rst
+use anyhow::{bail, Result};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// How a bond spreads packets over its slaves (BOND_MODE_*)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BondMode {
+    /// Round robin over every slave
+    BalanceRr,
+    /// A single slave is used, another one taking over when it fails
+    ActiveBackup,
+    /// Slave picked by a hash of each packet, see [`XmitHashPolicy`]
+    BalanceXor,
+    /// Every packet sent on every slave
+    Broadcast,
+    /// IEEE 802.3ad dynamic link aggregation, negotiated with LACP
+    Ieee8023Ad,
+    /// Outgoing packets spread by load
+    BalanceTlb,
+    /// Incoming and outgoing packets spread by load
+    BalanceAlb,
+    Other(u8),
+}
+
+impl BondMode {
+    /// Whether the bond sends through a single active slave at a time
+    pub fn has_active_slave(self) -> bool {
+        matches!(self, BondMode::ActiveBackup | BondMode::BalanceTlb | BondMode::BalanceAlb)
+    }
+}
+
+impl From<u8> for BondMode {
+    fn from(mode: u8) -> BondMode {
+        match mode {
+            0 => BondMode::BalanceRr,
+            1 => BondMode::ActiveBackup,
+            2 => BondMode::BalanceXor,
+            3 => BondMode::Broadcast,
+            4 => BondMode::Ieee8023Ad,
+            5 => BondMode::BalanceTlb,
+            6 => BondMode::BalanceAlb,
+            _ => BondMode::Other(mode),
+        }
+    }
+}
+
+impl From<BondMode> for u8 {
+    fn from(mode: BondMode) -> u8 {
+        match mode {
+            BondMode::BalanceRr => 0,
+            BondMode::ActiveBackup => 1,
+            BondMode::BalanceXor => 2,
+            BondMode::Broadcast => 3,
+            BondMode::Ieee8023Ad => 4,
+            BondMode::BalanceTlb => 5,
+            BondMode::BalanceAlb => 6,
+            BondMode::Other(mode) => mode,
+        }
+    }
+}
+
+/// Formats as iproute2 does, e.g. `active-backup` or `802.3ad`
+impl fmt::Display for BondMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BondMode::BalanceRr => write!(f, "balance-rr"),
+            BondMode::ActiveBackup => write!(f, "active-backup"),
+            BondMode::BalanceXor => write!(f, "balance-xor"),
+            BondMode::Broadcast => write!(f, "broadcast"),
+            BondMode::Ieee8023Ad => write!(f, "802.3ad"),
+            BondMode::BalanceTlb => write!(f, "balance-tlb"),
+            BondMode::BalanceAlb => write!(f, "balance-alb"),
+            BondMode::Other(mode) => write!(f, "{}", mode),
+        }
+    }
+}
+
+/// Parses an iproute2 name or the number of the mode, e.g. `active-backup` or `1`
+impl FromStr for BondMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "balance-rr" | "0" => Ok(BondMode::BalanceRr),
+            "active-backup" | "1" => Ok(BondMode::ActiveBackup),
+            "balance-xor" | "2" => Ok(BondMode::BalanceXor),
+            "broadcast" | "3" => Ok(BondMode::Broadcast),
+            "802.3ad" | "4" => Ok(BondMode::Ieee8023Ad),
+            "balance-tlb" | "5" => Ok(BondMode::BalanceTlb),
+            "balance-alb" | "6" => Ok(BondMode::BalanceAlb),
+            _ => bail!("Unknown bond mode '{}'", s),
+        }
+    }
+}
+
+/// Serializes as its iproute2 name, e.g. `"802.3ad"`
+impl Serialize for BondMode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Rate at which 802.3ad partners are asked to send LACPDUs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LacpRate {
+    /// Every 30 seconds
+    Slow,
+    /// Every second
+    Fast,
+}
+
+impl From<LacpRate> for u8 {
+    fn from(rate: LacpRate) -> u8 {
+        match rate {
+            LacpRate::Slow => 0,
+            LacpRate::Fast => 1,
+        }
+    }
+}
+
+impl fmt::Display for LacpRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LacpRate::Slow => write!(f, "slow"),
+            LacpRate::Fast => write!(f, "fast"),
+        }
+    }
+}
+
+impl FromStr for LacpRate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "slow" => Ok(LacpRate::Slow),
+            "fast" => Ok(LacpRate::Fast),
+            _ => bail!("Unknown LACP rate '{}'", s),
+        }
+    }
+}
+
+/// Headers hashed to pick the slave of a packet in the balance-xor, 802.3ad and tlb modes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XmitHashPolicy {
+    Layer2,
+    Layer34,
+    Layer23,
+    /// Layer 2 and 3 headers of the encapsulated packet
+    Encap23,
+    /// Layer 3 and 4 headers of the encapsulated packet
+    Encap34,
+    VlanSrcMac,
+}
+
+impl From<XmitHashPolicy> for u8 {
+    fn from(policy: XmitHashPolicy) -> u8 {
+        match policy {
+            XmitHashPolicy::Layer2 => 0,
+            XmitHashPolicy::Layer34 => 1,
+            XmitHashPolicy::Layer23 => 2,
+            XmitHashPolicy::Encap23 => 3,
+            XmitHashPolicy::Encap34 => 4,
+            XmitHashPolicy::VlanSrcMac => 5,
+        }
+    }
+}
+
+/// Formats as iproute2 does, e.g. `layer3+4`
+impl fmt::Display for XmitHashPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XmitHashPolicy::Layer2 => write!(f, "layer2"),
+            XmitHashPolicy::Layer34 => write!(f, "layer3+4"),
+            XmitHashPolicy::Layer23 => write!(f, "layer2+3"),
+            XmitHashPolicy::Encap23 => write!(f, "encap2+3"),
+            XmitHashPolicy::Encap34 => write!(f, "encap3+4"),
+            XmitHashPolicy::VlanSrcMac => write!(f, "vlan+srcmac"),
+        }
+    }
+}
+
+impl FromStr for XmitHashPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "layer2" => Ok(XmitHashPolicy::Layer2),
+            "layer3+4" => Ok(XmitHashPolicy::Layer34),
+            "layer2+3" => Ok(XmitHashPolicy::Layer23),
+            "encap2+3" => Ok(XmitHashPolicy::Encap23),
+            "encap3+4" => Ok(XmitHashPolicy::Encap34),
+            "vlan+srcmac" => Ok(XmitHashPolicy::VlanSrcMac),
+            _ => bail!("Unknown transmit hash policy '{}'", s),
+        }
+    }
+}
+
+/// Options of a bond being created, the kernel defaults applying to those left unset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BondOptions {
+    pub mode: BondMode,
+    /// Milliseconds between two checks of the link state of the slaves, 0 disabling them
+    pub miimon: Option<u32>,
+    /// Only applies to 802.3ad bonds
+    pub lacp_rate: Option<LacpRate>,
+    pub xmit_hash_policy: Option<XmitHashPolicy>,
+}
+
+impl BondOptions {
+    pub fn new(mode: BondMode) -> BondOptions {
+        BondOptions {
+            mode,
+            miimon: None,
+            lacp_rate: None,
+            xmit_hash_policy: None,
+        }
+    }
+}
+
+/// Formats as iproute2 does, e.g. `mode 802.3ad miimon 100 lacp_rate fast`
+impl fmt::Display for BondOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "mode {}", self.mode)?;
+        if let Some(miimon) = self.miimon {
+            write!(f, " miimon {}", miimon)?;
+        }
+        if let Some(lacp_rate) = self.lacp_rate {
+            write!(f, " lacp_rate {}", lacp_rate)?;
+        }
+        if let Some(policy) = self.xmit_hash_policy {
+            write!(f, " xmit_hash_policy {}", policy)?;
+        }
+        Ok(())
+    }
+}
+
+/// Slave of a bond as reported by SIOCBONDSLAVEINFOQUERY
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BondSlave {
+    pub name: String,
+    /// Whether the link of the slave is up
+    pub link_up: bool,
+    /// Whether the slave is used to send packets, as opposed to a backup
+    pub active: bool,
+    /// Number of times the link of the slave went down
+    pub link_failures: u32,
+}
+
+/// Formats as e.g. `slave eth0 up active link_failures 0`
+impl fmt::Display for BondSlave {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "slave {} {} {} link_failures {}",
+            self.name,
+            if self.link_up { "up" } else { "down" },
+            if self.active { "active" } else { "backup" },
+            self.link_failures
+        )
+    }
+}
+
+/// State of a bond as reported by SIOCBONDINFOQUERY
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BondInfo {
+    pub mode: BondMode,
+    pub miimon: u32,
+    /// Only set in the modes sending through a single slave at a time
+    pub active_slave: Option<String>,
+    pub slaves: Vec<BondSlave>,
+}
+
+/// Formats as iproute2 does, e.g. `bond mode active-backup active_slave eth0 miimon 100`
+impl fmt::Display for BondInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bond mode {}", self.mode)?;
+        if let Some(active_slave) = &self.active_slave {
+            write!(f, " active_slave {}", active_slave)?;
+        }
+        write!(f, " miimon {}", self.miimon)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mode() {
+        let names = [
+            "balance-rr",
+            "active-backup",
+            "balance-xor",
+            "broadcast",
+            "802.3ad",
+            "balance-tlb",
+            "balance-alb",
+        ];
+        for (number, name) in names.iter().enumerate() {
+            let mode = BondMode::from(number as u8);
+            assert_eq!(u8::from(mode), number as u8);
+            assert_eq!(mode.to_string(), *name);
+            assert_eq!(name.parse::<BondMode>().unwrap(), mode);
+            assert_eq!(number.to_string().parse::<BondMode>().unwrap(), mode);
+        }
+        assert_eq!(BondMode::from(7), BondMode::Other(7));
+        assert_eq!(BondMode::Other(7).to_string(), "7");
+        assert!("7".parse::<BondMode>().is_err());
+        assert!("Active-Backup".parse::<BondMode>().is_err());
+        assert!(BondMode::ActiveBackup.has_active_slave());
+        assert!(!BondMode::Ieee8023Ad.has_active_slave());
+    }
+
+    #[test]
+    fn lacp_rate() {
+        for (number, name) in ["slow", "fast"].iter().enumerate() {
+            let rate = name.parse::<LacpRate>().unwrap();
+            assert_eq!(u8::from(rate), number as u8);
+            assert_eq!(rate.to_string(), *name);
+        }
+        assert!("1".parse::<LacpRate>().is_err());
+    }
+
+    #[test]
+    fn xmit_hash_policy() {
+        let names = ["layer2", "layer3+4", "layer2+3", "encap2+3", "encap3+4", "vlan+srcmac"];
+        for (number, name) in names.iter().enumerate() {
+            let policy = name.parse::<XmitHashPolicy>().unwrap();
+            assert_eq!(u8::from(policy), number as u8);
+            assert_eq!(policy.to_string(), *name);
+        }
+        assert!("layer3".parse::<XmitHashPolicy>().is_err());
+    }
+
+    #[test]
+    fn display() {
+        let mut options = BondOptions::new(BondMode::Ieee8023Ad);
+        assert_eq!(options.to_string(), "mode 802.3ad");
+        options.miimon = Some(100);
+        options.lacp_rate = Some(LacpRate::Fast);
+        options.xmit_hash_policy = Some(XmitHashPolicy::Layer34);
+        assert_eq!(options.to_string(), "mode 802.3ad miimon 100 lacp_rate fast xmit_hash_policy layer3+4");
+
+        let slave = BondSlave {
+            name: "eth0".to_string(),
+            link_up: true,
+            active: true,
+            link_failures: 0,
+        };
+        assert_eq!(slave.to_string(), "slave eth0 up active link_failures 0");
+        let backup = BondSlave {
+            name: "eth1".to_string(),
+            link_up: false,
+            active: false,
+            link_failures: 3,
+        };
+        assert_eq!(backup.to_string(), "slave eth1 down backup link_failures 3");
+
+        let mut info = BondInfo {
+            mode: BondMode::ActiveBackup,
+            miimon: 100,
+            active_slave: Some("eth0".to_string()),
+            slaves: vec![slave, backup],
+        };
+        assert_eq!(info.to_string(), "bond mode active-backup active_slave eth0 miimon 100");
+        info.mode = BondMode::BalanceRr;
+        info.active_slave = None;
+        assert_eq!(info.to_string(), "bond mode balance-rr miimon 100");
+    }
+
+    #[test]
+    fn serialize() {
+        let slave = |name: &str, active, link_failures| BondSlave {
+            name: name.to_string(),
+            link_up: true,
+            active,
+            link_failures,
+        };
+        let info = BondInfo {
+            mode: BondMode::ActiveBackup,
+            miimon: 100,
+            active_slave: Some("eth0".to_string()),
+            slaves: vec![slave("eth0", true, 0), slave("eth1", false, 1)],
+        };
+        assert_eq!(
+            serde_json::to_value(&info).unwrap(),
+            serde_json::json!({
+                "mode": "active-backup",
+                "miimon": 100,
+                "active_slave": "eth0",
+                "slaves": [
+                    {"name": "eth0", "link_up": true, "active": true, "link_failures": 0},
+                    {"name": "eth1", "link_up": true, "active": false, "link_failures": 1}
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Bridge options, per-port VLANs and forwarding database entries
+use crate::mac::MacAddr;
+use crate::neigh::NeighbourState;
+use serde::Serialize;
+use std::fmt;
+use std::net::IpAddr;
+
+/// Options of a bridge, those left unset being kept as they are
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BridgeOptions {
+    /// Whether the bridge runs the spanning tree protocol
+    pub stp: Option<bool>,
+    /// Seconds spent in the listening and learning states when STP is on
+    pub forward_delay: Option<u32>,
+    /// Seconds a learned MAC address is kept in the forwarding database
+    pub ageing_time: Option<u32>,
+    /// Whether the bridge filters packets by the VLANs of its ports
+    pub vlan_filtering: Option<bool>,
+}
+
+impl BridgeOptions {
+    /// Whether no option is set
+    pub fn is_empty(&self) -> bool {
+        *self == BridgeOptions::default()
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// Formats as e.g. `stp on forward_delay 15 vlan_filtering off`, times in seconds
+impl fmt::Display for BridgeOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut options = Vec::new();
+        if let Some(stp) = self.stp {
+            options.push(format!("stp {}", on_off(stp)));
+        }
+        if let Some(forward_delay) = self.forward_delay {
+            options.push(format!("forward_delay {}", forward_delay));
+        }
+        if let Some(ageing_time) = self.ageing_time {
+            options.push(format!("ageing_time {}", ageing_time));
+        }
+        if let Some(vlan_filtering) = self.vlan_filtering {
+            options.push(format!("vlan_filtering {}", on_off(vlan_filtering)));
+        }
+        write!(f, "{}", options.join(" "))
+    }
+}
+
+/// VLAN a bridge port belongs to, only enforced once the bridge filters VLANs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BridgeVlan {
+    /// Port, or the bridge itself for the VLANs of its own packets
+    pub interface: String,
+    pub vid: u16,
+    /// Whether untagged packets received on the port are put in this VLAN
+    pub pvid: bool,
+    /// Whether packets of this VLAN leave the port untagged
+    pub untagged: bool,
+}
+
+impl BridgeVlan {
+    /// Tagged membership of `interface` in the VLAN `vid`
+    pub fn new(interface: &str, vid: u16) -> BridgeVlan {
+        BridgeVlan {
+            interface: interface.to_string(),
+            vid,
+            pvid: false,
+            untagged: false,
+        }
+    }
+}
+
+/// Formats as iproute2 does, e.g. `eth0 vid 10 PVID Egress Untagged`
+impl fmt::Display for BridgeVlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} vid {}", self.interface, self.vid)?;
+        if self.pvid {
+            write!(f, " PVID")?;
+        }
+        if self.untagged {
+            write!(f, " Egress Untagged")?;
+        }
+        Ok(())
+    }
+}
+
+/// Entry of a forwarding database, telling which port a MAC address is reached through
+///
+/// Entries of VXLAN links also tell which remote VTEP the address is behind,
+/// the all-zeros address standing for unknown and broadcast destinations.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FdbEntry {
+    pub mac: MacAddr,
+    /// Port the address is reached through
+    pub interface: String,
+    /// Unset for entries matching every VLAN
+    pub vlan: Option<u16>,
+    /// Remote VTEP of the entries of VXLAN links
+    pub destination: Option<IpAddr>,
+    /// Bridge of the port, unset for the entries of the port's own database
+    pub master: Option<String>,
+    /// `PERMANENT` for the addresses of the bridge itself, `NOARP` for static entries
+    pub state: NeighbourState,
+}
+
+impl FdbEntry {
+    /// Permanent entry of the own database of `interface`, e.g. a VXLAN link
+    pub fn new(mac: MacAddr, interface: &str) -> FdbEntry {
+        FdbEntry {
+            mac,
+            interface: interface.to_string(),
+            vlan: None,
+            destination: None,
+            master: None,
+            state: NeighbourState::Permanent,
+        }
+    }
+}
+
+/// Formats as iproute2 does, e.g. `02:00:5e:10:00:01 dev eth0 vlan 10 master br0 static`
+impl fmt::Display for FdbEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} dev {}", self.mac, self.interface)?;
+        if let Some(vlan) = self.vlan {
+            write!(f, " vlan {}", vlan)?;
+        }
+        if let Some(destination) = self.destination {
+            write!(f, " dst {}", destination)?;
+        }
+        match &self.master {
+            Some(master) => write!(f, " master {}", master)?,
+            None => write!(f, " self")?,
+        }
+        match self.state {
+            NeighbourState::Permanent => write!(f, " permanent"),
+            NeighbourState::Noarp => write!(f, " static"),
+            NeighbourState::Stale => write!(f, " stale"),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options() {
+        let mut options = BridgeOptions::default();
+        assert!(options.is_empty());
+        assert_eq!(options.to_string(), "");
+        options.stp = Some(true);
+        options.vlan_filtering = Some(false);
+        assert!(!options.is_empty());
+        assert_eq!(options.to_string(), "stp on vlan_filtering off");
+        options.forward_delay = Some(15);
+        options.ageing_time = Some(300);
+        assert_eq!(options.to_string(), "stp on forward_delay 15 ageing_time 300 vlan_filtering off");
+    }
+
+    #[test]
+    fn vlan() {
+        let mut vlan = BridgeVlan::new("eth0", 10);
+        assert_eq!(vlan.to_string(), "eth0 vid 10");
+        vlan.pvid = true;
+        vlan.untagged = true;
+        assert_eq!(vlan.to_string(), "eth0 vid 10 PVID Egress Untagged");
+        vlan.pvid = false;
+        assert_eq!(vlan.to_string(), "eth0 vid 10 Egress Untagged");
+    }
+
+    #[test]
+    fn fdb_entry() {
+        let mut entry = FdbEntry {
+            mac: "02:00:5e:10:00:01".parse().unwrap(),
+            interface: "eth0".to_string(),
+            vlan: Some(10),
+            destination: None,
+            master: Some("br0".to_string()),
+            state: NeighbourState::Noarp,
+        };
+        assert_eq!(entry.to_string(), "02:00:5e:10:00:01 dev eth0 vlan 10 master br0 static");
+        entry.state = NeighbourState::Permanent;
+        assert_eq!(entry.to_string(), "02:00:5e:10:00:01 dev eth0 vlan 10 master br0 permanent");
+        entry.vlan = None;
+        entry.master = None;
+        entry.state = NeighbourState::Reachable;
+        assert_eq!(entry.to_string(), "02:00:5e:10:00:01 dev eth0 self");
+        entry.state = NeighbourState::Stale;
+        assert_eq!(entry.to_string(), "02:00:5e:10:00:01 dev eth0 self stale");
+
+        let mut vtep = FdbEntry::new(MacAddr::from([0; 6]), "vxlan0");
+        vtep.destination = Some(IpAddr::from([10, 0, 0, 2]));
+        assert_eq!(vtep.to_string(), "00:00:00:00:00:00 dev vxlan0 dst 10.0.0.2 self permanent");
+    }
+}
@@ -0,0 +1,214 @@
+//! `addr` subcommands, see docs/output.md for the output of `addr show`
+use super::output::OutputFormat;
+use anyhow::{anyhow, bail, Result};
+use log::info;
+use network_config::{Address, AddressFlags, Backend, Cidr, Interface, Scope};
+use serde::Serialize;
+use std::net::{IpAddr, Ipv4Addr};
+use structopt::StructOpt;
+
+/// Address to add or replace along with its properties
+#[derive(Debug, StructOpt)]
+pub struct AddrArgs {
+    /// Interface to change
+    interface: String,
+
+    /// IPv4 or IPv6 address with an optional prefix length (e.g. `10.1.2.3/24`)
+    address: Cidr,
+
+    /// IPv4 broadcast address
+    #[structopt(long)]
+    broadcast: Option<Ipv4Addr>,
+
+    /// Peer address of a point-to-point interface
+    #[structopt(long)]
+    peer: Option<IpAddr>,
+
+    /// Label of an IPv4 address, `<interface>` or `<interface>:<alias>`
+    #[structopt(long)]
+    label: Option<String>,
+
+    /// Scope of the address: global, site, link, host or a number
+    #[structopt(long, default_value = "global")]
+    scope: Scope,
+
+    /// Comma separated flags, e.g. `noprefixroute,nodad`
+    #[structopt(long)]
+    flags: Option<AddressFlags>,
+
+    /// Seconds the address stays valid, forever when omitted
+    #[structopt(long)]
+    valid_lft: Option<u32>,
+
+    /// Seconds the address stays preferred, forever when omitted
+    #[structopt(long)]
+    preferred_lft: Option<u32>,
+}
+
+impl AddrArgs {
+    fn address(&self) -> Result<Address> {
+        if let Some(label) = &self.label {
+            if label.split(':').next() != Some(self.interface.as_str()) {
+                bail!("Label '{}' must start with the interface name '{}'", label, self.interface);
+            }
+        }
+        if self.address.addr.is_ipv6() && (self.broadcast.is_some() || self.label.is_some()) {
+            bail!("Broadcast addresses and labels only apply to IPv4");
+        }
+        if self.peer.is_some_and(|peer| peer.is_ipv4() != self.address.addr.is_ipv4()) {
+            bail!("The peer and the address must be of the same family");
+        }
+
+        let mut address = Address::new(self.address);
+        address.broadcast = self.broadcast;
+        address.peer = self.peer;
+        address.label = self.label.clone();
+        address.scope = self.scope;
+        address.flags = self.flags.unwrap_or_else(AddressFlags::empty);
+        address.valid_lifetime = self.valid_lft;
+        address.preferred_lifetime = self.preferred_lft;
+        Ok(address)
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum AddrCommand {
+    /// Show the addresses of an interface, or of every interface
+    Show {
+        /// Interface to query
+        interface: Option<String>,
+    },
+    /// Add an address, leaving the existing ones in place
+    Add(AddrArgs),
+    /// Add an address, or update it if the interface already has it
+    Replace(AddrArgs),
+    /// Remove an address
+    Del {
+        /// Interface to change
+        interface: String,
+
+        /// Address to remove, the prefix length may be omitted
+        address: Cidr,
+    },
+    /// Remove every address of an interface
+    Flush {
+        /// Interface to change
+        interface: String,
+
+        /// Only remove IPv4 addresses
+        #[structopt(short = "4", conflicts_with = "ipv6")]
+        ipv4: bool,
+
+        /// Only remove IPv6 addresses
+        #[structopt(short = "6")]
+        ipv6: bool,
+    },
+}
+
+/// Addresses of one interface in the result of `addr show`
+#[derive(Debug, Serialize)]
+struct AddrOutput {
+    name: String,
+    index: u32,
+    addresses: Vec<Address>,
+}
+
+fn lifetime(lifetime: Option<u32>) -> String {
+    match lifetime {
+        Some(seconds) => format!("{}sec", seconds),
+        None => "forever".to_string(),
+    }
+}
+
+/// iproute2 like description of `address`, on two lines
+fn describe(address: &Address) -> String {
+    let family = if address.cidr.addr.is_ipv4() { "inet" } else { "inet6" };
+    let mut line = format!("{} {}/{}", family, address.cidr.addr, address.prefix_len());
+    if let Some(peer) = address.peer {
+        line += &format!(" peer {}", peer);
+    }
+    if let Some(broadcast) = address.broadcast {
+        line += &format!(" brd {}", broadcast);
+    }
+    line += &format!(" scope {}", address.scope);
+    if !address.flags.is_empty() {
+        line += &format!(" {}", address.flags);
+    }
+    if let Some(label) = &address.label {
+        line += &format!(" {}", label);
+    }
+    line += &format!(
+        "\n       valid_lft {} preferred_lft {}",
+        lifetime(address.valid_lifetime),
+        lifetime(address.preferred_lifetime)
+    );
+    line
+}
+
+fn show(interface: Option<String>, backend: &dyn Backend, output: OutputFormat) -> Result<()> {
+    let interfaces = match interface {
+        Some(name) => vec![Interface::new(&name)?],
+        None => Interface::list()?,
+    };
+    let result = interfaces
+        .iter()
+        .map(|interface| {
+            Ok(AddrOutput {
+                name: interface.name().to_string(),
+                index: interface.index()? as u32,
+                addresses: backend.addresses(interface)?,
+            })
+        })
+        .collect::<Result<Vec<AddrOutput>>>()?;
+    output.print(&result, |result| {
+        for interface in result {
+            println!("{}: {}:", interface.index, interface.name);
+            for address in &interface.addresses {
+                println!("    {}", describe(address));
+            }
+        }
+    })
+}
+
+fn add(args: AddrArgs, backend: &dyn Backend, replace: bool) -> Result<()> {
+    let interface = Interface::new(&args.interface)?;
+    let address = args.address()?;
+    if replace {
+        backend.replace_address(&interface, &address)?;
+    } else {
+        backend.add_address(&interface, &address)?;
+    }
+    info!("Address '{}' set on interface '{}'", address.cidr, interface.name());
+    Ok(())
+}
+
+fn del(interface: &str, cidr: &Cidr, backend: &dyn Backend) -> Result<()> {
+    let interface = Interface::new(interface)?;
+    let address = backend
+        .find_address(&interface, cidr)?
+        .ok_or_else(|| anyhow!("Interface '{}' has no address '{}'", interface.name(), cidr))?;
+    backend.del_address(&interface, &address)?;
+    info!("Address '{}' removed from interface '{}'", cidr, interface.name());
+    Ok(())
+}
+
+fn flush(interface: &str, ipv4: bool, ipv6: bool, backend: &dyn Backend) -> Result<()> {
+    let interface = Interface::new(interface)?;
+    let filter = |address: &Address| match address.cidr.addr {
+        IpAddr::V4(_) => !ipv6,
+        IpAddr::V6(_) => !ipv4,
+    };
+    let removed = backend.flush_addresses(&interface, &filter)?;
+    info!("Removed {} addresses from interface '{}'", removed.len(), interface.name());
+    Ok(())
+}
+
+pub fn run(command: AddrCommand, backend: &dyn Backend, output: OutputFormat) -> Result<()> {
+    match command {
+        AddrCommand::Show { interface } => show(interface, backend, output),
+        AddrCommand::Add(args) => add(args, backend, false),
+        AddrCommand::Replace(args) => add(args, backend, true),
+        AddrCommand::Del { interface, address } => del(&interface, &address, backend),
+        AddrCommand::Flush { interface, ipv4, ipv6 } => flush(&interface, ipv4, ipv6, backend),
+    }
+}
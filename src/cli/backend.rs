@@ -0,0 +1,36 @@
+//! Selection of the backend managing addresses
+use anyhow::{bail, Result};
+use network_config::{default_backend, Backend, IoctlBackend, NetlinkBackend};
+use std::str::FromStr;
+
+/// Backend picked with `--backend`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    /// Netlink, falling back to ioctls when unavailable
+    Auto,
+    Netlink,
+    Ioctl,
+}
+
+impl FromStr for BackendKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(BackendKind::Auto),
+            "netlink" => Ok(BackendKind::Netlink),
+            "ioctl" => Ok(BackendKind::Ioctl),
+            _ => bail!("Unknown backend '{}', expected auto, netlink or ioctl", s),
+        }
+    }
+}
+
+impl BackendKind {
+    pub fn open(self) -> Result<Box<dyn Backend>> {
+        Ok(match self {
+            BackendKind::Auto => default_backend(),
+            BackendKind::Netlink => Box::new(NetlinkBackend::new()?),
+            BackendKind::Ioctl => Box::new(IoctlBackend),
+        })
+    }
+}
@@ -0,0 +1,248 @@
+//! `bridge` subcommands, see docs/output.md for the output of `bridge vlan list` and `bridge fdb list`
+use super::output::OutputFormat;
+use anyhow::{anyhow, bail, Result};
+use log::info;
+use network_config::{Backend, BridgeOptions, BridgeVlan, FdbEntry, Interface, MacAddr, NeighbourState};
+use std::net::IpAddr;
+use structopt::StructOpt;
+
+/// Forward delay in seconds, within the range the kernel accepts
+fn parse_forward_delay(s: &str) -> Result<u32> {
+    let delay = s.parse()?;
+    if !(2..=30).contains(&delay) {
+        bail!("Forward delay {} is out of range 2-30", delay);
+    }
+    Ok(delay)
+}
+
+/// Options of a bridge, those omitted being left as they are
+#[derive(Debug, StructOpt)]
+pub struct BridgeArgs {
+    /// Run the spanning tree protocol, `on` or `off`
+    #[structopt(long, parse(try_from_str = crate::parse_on_off))]
+    stp: Option<bool>,
+
+    /// Seconds spent listening and learning before forwarding when STP is on, from 2 to 30
+    #[structopt(long, parse(try_from_str = parse_forward_delay))]
+    forward_delay: Option<u32>,
+
+    /// Seconds a learned MAC address is remembered
+    #[structopt(long)]
+    ageing_time: Option<u32>,
+
+    /// Filter packets by the VLANs of the ports, `on` or `off`
+    #[structopt(long, parse(try_from_str = crate::parse_on_off))]
+    vlan_filtering: Option<bool>,
+}
+
+impl BridgeArgs {
+    pub fn options(&self) -> BridgeOptions {
+        BridgeOptions {
+            stp: self.stp,
+            forward_delay: self.forward_delay,
+            ageing_time: self.ageing_time,
+            vlan_filtering: self.vlan_filtering,
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum VlanCommand {
+    /// List the VLANs of the ports of every bridge, or of one port
+    List {
+        /// Port to query
+        interface: Option<String>,
+    },
+    /// Add a port to a VLAN, or change its flags there
+    Add {
+        /// Bridge port
+        interface: String,
+
+        /// VLAN id, from 1 to 4094
+        vid: u16,
+
+        /// Put the untagged packets received on the port in this VLAN
+        #[structopt(long)]
+        pvid: bool,
+
+        /// Send the packets of this VLAN untagged
+        #[structopt(long)]
+        untagged: bool,
+    },
+    /// Remove a port from a VLAN
+    Del {
+        /// Bridge port
+        interface: String,
+
+        /// VLAN id
+        vid: u16,
+    },
+}
+
+/// Forwarding database entry to add or remove
+#[derive(Debug, StructOpt)]
+pub struct FdbArgs {
+    /// MAC address, 00:00:00:00:00:00 standing for unknown and broadcast destinations of VXLAN links
+    mac: MacAddr,
+
+    /// Port the address is reached through, or VXLAN link
+    interface: String,
+
+    /// Remote VTEP the address is behind, for VXLAN links
+    #[structopt(long)]
+    dst: Option<IpAddr>,
+
+    /// VLAN the entry applies to, every VLAN when omitted
+    #[structopt(long)]
+    vlan: Option<u16>,
+
+    /// Use the database of the bridge of the port rather than the port's own one
+    #[structopt(long)]
+    master: bool,
+
+    /// Forward to the port rather than deliver to the bridge, for entries of the bridge's database
+    #[structopt(long = "static")]
+    is_static: bool,
+}
+
+impl FdbArgs {
+    fn entry(&self) -> Result<FdbEntry> {
+        let interface = Interface::new(&self.interface)?;
+        if let Some(vlan) = self.vlan {
+            if !(1..=4094).contains(&vlan) {
+                bail!("VLAN id {} is out of range 1-4094", vlan);
+            }
+        }
+        let mut entry = FdbEntry::new(self.mac, &self.interface);
+        entry.vlan = self.vlan;
+        entry.destination = self.dst;
+        if self.master {
+            let master = interface
+                .master()?
+                .ok_or_else(|| anyhow!("Interface '{}' is not a bridge port", self.interface))?;
+            entry.master = Some(master.name().to_string());
+        }
+        if self.is_static {
+            entry.state = NeighbourState::Noarp;
+        }
+        Ok(entry)
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum FdbCommand {
+    /// List the forwarding database entries of every bridge, or of one bridge or port
+    List {
+        /// Bridge or port to query
+        interface: Option<String>,
+    },
+    /// Add an entry, failing if its MAC address already has one
+    Add(FdbArgs),
+    /// Add an entry, as another destination of its MAC address if it already has one
+    Append(FdbArgs),
+    /// Remove an entry, only its destination when given
+    Del(FdbArgs),
+}
+
+#[derive(Debug, StructOpt)]
+pub enum BridgeCommand {
+    /// Change the options of a bridge
+    Set {
+        /// Bridge to change
+        bridge: String,
+
+        #[structopt(flatten)]
+        options: BridgeArgs,
+    },
+    /// List, add or remove the VLANs of bridge ports
+    Vlan(VlanCommand),
+    /// List, add or remove forwarding database entries
+    Fdb(FdbCommand),
+}
+
+fn set(bridge: &str, options: BridgeOptions, backend: &dyn Backend) -> Result<()> {
+    if options.is_empty() {
+        bail!("No bridge option given");
+    }
+    backend.set_bridge_options(&Interface::new(bridge)?, &options)?;
+    info!("Bridge '{}' set to {}", bridge, options);
+    Ok(())
+}
+
+fn vlan_list(interface: Option<String>, backend: &dyn Backend, output: OutputFormat) -> Result<()> {
+    let mut vlans = backend.bridge_vlans()?;
+    if let Some(interface) = &interface {
+        vlans.retain(|vlan| vlan.interface == *interface);
+    }
+    output.print(&vlans, |vlans| {
+        for vlan in vlans {
+            println!("{}", vlan);
+        }
+    })
+}
+
+fn vlan_add(vlan: BridgeVlan, backend: &dyn Backend) -> Result<()> {
+    if !(1..=4094).contains(&vlan.vid) {
+        bail!("VLAN id {} is out of range 1-4094", vlan.vid);
+    }
+    backend.add_bridge_vlan(&vlan)?;
+    info!("Port '{}' added to VLAN {}", vlan.interface, vlan.vid);
+    Ok(())
+}
+
+fn vlan_del(vlan: BridgeVlan, backend: &dyn Backend) -> Result<()> {
+    backend.del_bridge_vlan(&vlan)?;
+    info!("Port '{}' removed from VLAN {}", vlan.interface, vlan.vid);
+    Ok(())
+}
+
+fn fdb_list(interface: Option<String>, backend: &dyn Backend, output: OutputFormat) -> Result<()> {
+    let mut entries = backend.fdb()?;
+    if let Some(interface) = &interface {
+        entries.retain(|entry: &FdbEntry| entry.interface == *interface || entry.master.as_ref() == Some(interface));
+    }
+    output.print(&entries, |entries| {
+        for entry in entries {
+            println!("{}", entry);
+        }
+    })
+}
+
+fn fdb_add(entry: FdbEntry, append: bool, backend: &dyn Backend) -> Result<()> {
+    if append {
+        backend.append_fdb(&entry)?;
+    } else {
+        backend.add_fdb(&entry)?;
+    }
+    info!("Forwarding database entry '{}' added", entry);
+    Ok(())
+}
+
+fn fdb_del(entry: FdbEntry, backend: &dyn Backend) -> Result<()> {
+    backend.del_fdb(&entry)?;
+    info!("Forwarding database entry '{}' removed", entry);
+    Ok(())
+}
+
+pub fn run(command: BridgeCommand, backend: &dyn Backend, output: OutputFormat) -> Result<()> {
+    match command {
+        BridgeCommand::Set { bridge, options } => set(&bridge, options.options(), backend),
+        BridgeCommand::Vlan(VlanCommand::List { interface }) => vlan_list(interface, backend, output),
+        BridgeCommand::Vlan(VlanCommand::Add {
+            interface,
+            vid,
+            pvid,
+            untagged,
+        }) => {
+            let mut vlan = BridgeVlan::new(&interface, vid);
+            vlan.pvid = pvid;
+            vlan.untagged = untagged;
+            vlan_add(vlan, backend)
+        }
+        BridgeCommand::Vlan(VlanCommand::Del { interface, vid }) => vlan_del(BridgeVlan::new(&interface, vid), backend),
+        BridgeCommand::Fdb(FdbCommand::List { interface }) => fdb_list(interface, backend, output),
+        BridgeCommand::Fdb(FdbCommand::Add(args)) => fdb_add(args.entry()?, false, backend),
+        BridgeCommand::Fdb(FdbCommand::Append(args)) => fdb_add(args.entry()?, true, backend),
+        BridgeCommand::Fdb(FdbCommand::Del(args)) => fdb_del(args.entry()?, backend),
+    }
+}
@@ -0,0 +1,529 @@
+//! `link` subcommands
+use super::bridge::BridgeArgs;
+use anyhow::{anyhow, bail, Result};
+use log::{info, warn};
+use network_config::{
+    Address, Backend, BondMode, BondOptions, Cidr, GeneveOptions, Interface, IpvlanMode, LacpRate, Link, LinkKind,
+    LinkType, MacAddr, MacvlanMode, Netns, TunOptions, TunnelOptions, VlanProtocol, VxlanOptions, XmitHashPolicy,
+};
+use nix::unistd::{Group, User};
+use std::net::IpAddr;
+use structopt::clap::AppSettings;
+use structopt::StructOpt;
+
+/// Largest VXLAN or GENEVE network identifier, they are 24 bits long
+const MAX_VNI: u32 = (1 << 24) - 1;
+
+/// User id, or the name of a user
+fn parse_user(s: &str) -> Result<u32> {
+    if let Ok(uid) = s.parse() {
+        return Ok(uid);
+    }
+    let user = User::from_name(s)?.ok_or_else(|| anyhow!("Unknown user '{}'", s))?;
+    Ok(user.uid.as_raw())
+}
+
+/// Group id, or the name of a group
+fn parse_group(s: &str) -> Result<u32> {
+    if let Ok(gid) = s.parse() {
+        return Ok(gid);
+    }
+    let group = Group::from_name(s)?.ok_or_else(|| anyhow!("Unknown group '{}'", s))?;
+    Ok(group.gid.as_raw())
+}
+
+/// Options of a TUN or TAP device
+#[derive(Debug, StructOpt)]
+pub struct TunArgs {
+    /// User allowed to attach to the device, a name or an id
+    #[structopt(long, parse(try_from_str = parse_user))]
+    user: Option<u32>,
+
+    /// Group allowed to attach to the device, a name or an id
+    #[structopt(long, parse(try_from_str = parse_group))]
+    group: Option<u32>,
+
+    /// Allow several file descriptors to attach to the device, one queue each
+    #[structopt(long)]
+    multi_queue: bool,
+
+    /// Precede packets with a virtio_net_hdr
+    #[structopt(long)]
+    vnet_hdr: bool,
+}
+
+impl TunArgs {
+    fn options(&self) -> TunOptions {
+        TunOptions {
+            owner: self.user,
+            group: self.group,
+            multi_queue: self.multi_queue,
+            vnet_hdr: self.vnet_hdr,
+        }
+    }
+}
+
+/// Check `parent` can have macvlan, macvtap or ipvlan children, which only Ethernet interfaces can
+fn check_parent(parent: &str, kind: &str) -> Result<Interface> {
+    let interface = Interface::new(parent)?;
+    if interface.hw_address()?.link_type != LinkType::Ether {
+        bail!("The parent of a {} must be an Ethernet interface, '{}' is not", kind, parent);
+    }
+    Ok(interface)
+}
+
+/// Check `parent` can have a macvlan or macvtap child in `mode`
+fn check_macvlan_parent(parent: &str, mode: MacvlanMode, kind: &str) -> Result<()> {
+    let interface = check_parent(parent, kind)?;
+    // sysfs doesn't tell the kind of the children, any of them may be a macvlan
+    let children = interface.children()?;
+    if mode == MacvlanMode::Passthru && !children.is_empty() {
+        bail!("A passthru {} must be the only child of '{}', which has {}", kind, parent, children.join(", "));
+    }
+    Ok(())
+}
+
+/// Parent and mode of a macvlan or macvtap
+#[derive(Debug, StructOpt)]
+pub struct MacvlanArgs {
+    /// Ethernet interface to stack the link on
+    #[structopt(long)]
+    parent: String,
+
+    /// private, vepa, bridge or passthru
+    #[structopt(long, default_value = "vepa")]
+    mode: MacvlanMode,
+}
+
+/// Endpoints and options of a GRE, IPIP, SIT or ip6tnl tunnel
+#[derive(Debug, StructOpt)]
+pub struct TunnelArgs {
+    /// Source address of the encapsulated packets
+    #[structopt(long)]
+    local: Option<IpAddr>,
+
+    /// Other end of the tunnel
+    #[structopt(long)]
+    remote: Option<IpAddr>,
+
+    /// Interface the encapsulated packets are sent through
+    #[structopt(long)]
+    dev: Option<String>,
+
+    /// TTL or hop limit of the encapsulated packets, 0 inheriting the one of the inner packet
+    #[structopt(long)]
+    ttl: Option<u8>,
+
+    /// Key of the packets sent and expected, GRE only
+    #[structopt(long)]
+    key: Option<u32>,
+
+    /// Set DF on the encapsulated packets, `on` or `off`, IPv4 tunnels only
+    #[structopt(long, parse(try_from_str = crate::parse_on_off))]
+    pmtudisc: Option<bool>,
+}
+
+impl TunnelArgs {
+    /// Options of a tunnel of kind `kind`, checking they apply to it
+    fn options(self, kind: &str) -> Result<TunnelOptions> {
+        let ipv6 = kind == "ip6tnl";
+        for address in self.local.iter().chain(&self.remote) {
+            if address.is_ipv6() != ipv6 {
+                bail!("The endpoints of {} tunnels are IPv{} addresses", kind, if ipv6 { 6 } else { 4 });
+            }
+        }
+        if self.key.is_some() && kind != "gre" {
+            bail!("Keys only apply to GRE tunnels");
+        }
+        if self.pmtudisc.is_some() && ipv6 {
+            bail!("Path MTU discovery only applies to IPv4 tunnels");
+        }
+        if self.pmtudisc == Some(false) && self.ttl.is_some_and(|ttl| ttl != 0) {
+            bail!("A fixed TTL needs path MTU discovery");
+        }
+        if let Some(dev) = &self.dev {
+            Interface::new(dev)?.index()?;
+        }
+        Ok(TunnelOptions {
+            local: self.local,
+            remote: self.remote,
+            parent: self.dev,
+            ttl: self.ttl,
+            key: self.key,
+            pmtudisc: self.pmtudisc,
+        })
+    }
+}
+
+// Type of the link to create and its parameters, not a doc comment which would replace the one of `link add`
+#[derive(Debug, StructOpt)]
+pub enum KindArgs {
+    /// Interface dropping whatever is sent through it
+    Dummy,
+    /// Pair of interfaces connected back to back
+    Veth {
+        /// Name of the other end
+        #[structopt(long)]
+        peer: String,
+    },
+    /// Ethernet bridge
+    Bridge(BridgeArgs),
+    /// Bond aggregating the links later enslaved to it
+    Bond {
+        /// balance-rr, active-backup, balance-xor, broadcast, 802.3ad, balance-tlb or balance-alb
+        #[structopt(long, default_value = "balance-rr")]
+        mode: BondMode,
+
+        /// Milliseconds between two checks of the link of the slaves, 0 disabling them
+        #[structopt(long)]
+        miimon: Option<u32>,
+
+        /// Rate of the LACPDUs asked to the partner of an 802.3ad bond, slow or fast
+        #[structopt(long)]
+        lacp_rate: Option<LacpRate>,
+
+        /// layer2, layer2+3, layer3+4, encap2+3, encap3+4 or vlan+srcmac
+        #[structopt(long)]
+        xmit_hash_policy: Option<XmitHashPolicy>,
+    },
+    /// VLAN tagging the packets of another interface
+    Vlan {
+        /// Interface carrying the tagged packets
+        #[structopt(long)]
+        parent: String,
+
+        /// VLAN id, from 1 to 4094
+        #[structopt(long)]
+        id: u16,
+
+        /// Tag protocol, 802.1Q or 802.1ad
+        #[structopt(long, default_value = "802.1Q")]
+        protocol: VlanProtocol,
+    },
+    /// VXLAN overlay carrying Ethernet frames over UDP
+    Vxlan {
+        /// VXLAN network identifier, up to 16777215
+        #[structopt(long)]
+        id: u32,
+
+        /// Source address of the encapsulated packets
+        #[structopt(long)]
+        local: Option<IpAddr>,
+
+        /// VTEP receiving the frames to unknown addresses, others being added to the forwarding database
+        #[structopt(long, conflicts_with = "group")]
+        remote: Option<IpAddr>,
+
+        /// Multicast group joined to reach every VTEP
+        #[structopt(long)]
+        group: Option<IpAddr>,
+
+        /// Interface the encapsulated packets are sent through
+        #[structopt(long)]
+        dev: Option<String>,
+
+        /// UDP destination port
+        #[structopt(long, default_value = "4789")]
+        dstport: u16,
+
+        /// Learn the VTEPs of the source addresses of received frames, `on` or `off`
+        #[structopt(long, parse(try_from_str = crate::parse_on_off))]
+        learning: Option<bool>,
+    },
+    /// GENEVE overlay carrying Ethernet frames over UDP to a single endpoint
+    Geneve {
+        /// Virtual network identifier, up to 16777215
+        #[structopt(long)]
+        id: u32,
+
+        /// Tunnel endpoint
+        #[structopt(long)]
+        remote: IpAddr,
+
+        /// UDP destination port
+        #[structopt(long, default_value = "6081")]
+        dstport: u16,
+    },
+    /// Persistent TUN device, exchanging IP packets with a program
+    Tun(TunArgs),
+    /// Persistent TAP device, exchanging Ethernet frames with a program
+    Tap(TunArgs),
+    /// GRE tunnel carrying IPv4 and IPv6 packets over IPv4
+    Gre(TunnelArgs),
+    /// IPIP tunnel carrying IPv4 packets over IPv4
+    Ipip(TunnelArgs),
+    /// SIT tunnel carrying IPv6 packets over IPv4
+    Sit(TunnelArgs),
+    /// ip6tnl tunnel carrying IPv4 and IPv6 packets over IPv6
+    Ip6tnl(TunnelArgs),
+    /// Child of an Ethernet interface with a MAC address of its own
+    Macvlan(MacvlanArgs),
+    /// Macvlan whose frames are also handed to a program through /dev/tapN
+    Macvtap(MacvlanArgs),
+    /// Child of an Ethernet interface sharing its MAC address
+    Ipvlan {
+        /// Ethernet interface to stack the link on
+        #[structopt(long)]
+        parent: String,
+
+        /// l2, l3 or l3s
+        #[structopt(long, default_value = "l3")]
+        mode: IpvlanMode,
+    },
+    /// WireGuard tunnel, configured with `wg set` and `wg peer`
+    Wireguard,
+}
+
+impl KindArgs {
+    fn kind(self) -> Result<LinkKind> {
+        Ok(match self {
+            KindArgs::Dummy => LinkKind::Dummy,
+            KindArgs::Veth { peer } => {
+                Interface::new(&peer)?;
+                LinkKind::Veth { peer }
+            }
+            KindArgs::Bridge(options) => LinkKind::Bridge(options.options()),
+            KindArgs::Bond {
+                mode,
+                miimon,
+                lacp_rate,
+                xmit_hash_policy,
+            } => {
+                if lacp_rate.is_some() && mode != BondMode::Ieee8023Ad {
+                    bail!("The LACP rate only applies to 802.3ad bonds");
+                }
+                let mut options = BondOptions::new(mode);
+                options.miimon = miimon;
+                options.lacp_rate = lacp_rate;
+                options.xmit_hash_policy = xmit_hash_policy;
+                LinkKind::Bond(options)
+            }
+            KindArgs::Vlan { parent, id, protocol } => {
+                if !(1..=4094).contains(&id) {
+                    bail!("VLAN id {} is out of range 1-4094", id);
+                }
+                Interface::new(&parent)?.index()?;
+                LinkKind::Vlan { parent, id, protocol }
+            }
+            KindArgs::Vxlan {
+                id,
+                local,
+                remote,
+                group,
+                dev,
+                dstport,
+                learning,
+            } => {
+                if id > MAX_VNI {
+                    bail!("VNI {} is out of range 0-{}", id, MAX_VNI);
+                }
+                if let Some(group) = group {
+                    if !group.is_multicast() {
+                        bail!("VXLAN group {} is not a multicast address", group);
+                    }
+                }
+                if let Some(remote) = remote {
+                    if remote.is_multicast() {
+                        bail!("VXLAN remote {} is a multicast address, give it as the group", remote);
+                    }
+                }
+                if let (Some(local), Some(peer)) = (local, remote.or(group)) {
+                    if local.is_ipv4() != peer.is_ipv4() {
+                        bail!("VXLAN local {} and remote {} are of different families", local, peer);
+                    }
+                }
+                if let Some(dev) = &dev {
+                    Interface::new(dev)?.index()?;
+                }
+                let mut options = VxlanOptions::new(id);
+                options.local = local;
+                options.remote = remote;
+                options.group = group;
+                options.parent = dev;
+                options.port = Some(dstport);
+                options.learning = learning;
+                LinkKind::Vxlan(options)
+            }
+            KindArgs::Geneve { id, remote, dstport } => {
+                if id > MAX_VNI {
+                    bail!("VNI {} is out of range 0-{}", id, MAX_VNI);
+                }
+                let mut options = GeneveOptions::new(id, remote);
+                options.port = Some(dstport);
+                LinkKind::Geneve(options)
+            }
+            KindArgs::Tun(args) => LinkKind::Tun(args.options()),
+            KindArgs::Tap(args) => LinkKind::Tap(args.options()),
+            KindArgs::Gre(args) => LinkKind::Gre(args.options("gre")?),
+            KindArgs::Ipip(args) => LinkKind::Ipip(args.options("ipip")?),
+            KindArgs::Sit(args) => LinkKind::Sit(args.options("sit")?),
+            KindArgs::Ip6tnl(args) => LinkKind::Ip6tnl(args.options("ip6tnl")?),
+            KindArgs::Macvlan(MacvlanArgs { parent, mode }) => {
+                check_macvlan_parent(&parent, mode, "macvlan")?;
+                LinkKind::Macvlan { parent, mode }
+            }
+            KindArgs::Macvtap(MacvlanArgs { parent, mode }) => {
+                check_macvlan_parent(&parent, mode, "macvtap")?;
+                LinkKind::Macvtap { parent, mode }
+            }
+            KindArgs::Ipvlan { parent, mode } => {
+                check_parent(&parent, "ipvlan")?;
+                LinkKind::Ipvlan { parent, mode }
+            }
+            KindArgs::Wireguard => LinkKind::Wireguard,
+        })
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum LinkCommand {
+    /// Create a virtual link
+    // Names like `tap0` are otherwise refused as misspelled link types, which can now be abbreviated
+    #[structopt(setting = AppSettings::InferSubcommands)]
+    Add {
+        /// Name of the new interface
+        name: String,
+
+        /// MTU of the new interface
+        #[structopt(long)]
+        mtu: Option<u32>,
+
+        /// MAC address of the new interface, random when omitted
+        #[structopt(long)]
+        address: Option<MacAddr>,
+
+        /// IPv4 or IPv6 address to add to the new interface, e.g. `10.1.2.3/24`, can be repeated
+        #[structopt(long = "ip", number_of_values = 1)]
+        addresses: Vec<Cidr>,
+
+        /// Bring the new interface up
+        #[structopt(long)]
+        up: bool,
+
+        #[structopt(subcommand)]
+        kind: KindArgs,
+    },
+    /// Replace the endpoints and options of a GRE, IPIP, SIT or ip6tnl tunnel
+    #[structopt(setting = AppSettings::InferSubcommands)]
+    Change {
+        /// Tunnel to change
+        name: String,
+
+        #[structopt(subcommand)]
+        kind: KindArgs,
+    },
+    /// Delete a virtual link, along with the peer of a veth
+    Del {
+        /// Interface to delete
+        name: String,
+    },
+    /// Enslave an interface to a bond, or make it a port of a bridge
+    Enslave {
+        /// Interface to enslave, brought down and back up by bonds
+        interface: String,
+
+        /// Bond or bridge to enslave the interface to
+        master: String,
+    },
+    /// Release an interface from its bond or bridge
+    Release {
+        /// Interface to release
+        interface: String,
+    },
+    /// Move an interface into a network namespace, where it is down and without addresses
+    SetNetns {
+        /// Interface to move, veths leaving their peer behind
+        interface: String,
+
+        /// Network namespace, as created by `netns add`
+        namespace: String,
+    },
+}
+
+/// Add `addresses` to the new `interface` and bring it up if `up` is set
+fn configure(interface: &Interface, addresses: &[Cidr], up: bool, backend: &dyn Backend) -> Result<()> {
+    for cidr in addresses {
+        backend.add_address(interface, &Address::new(*cidr))?;
+        info!("Address '{}' added to '{}'", cidr, interface.name());
+    }
+    if up {
+        interface.up()?;
+    }
+    Ok(())
+}
+
+fn add(link: Link, addresses: &[Cidr], up: bool, backend: &dyn Backend) -> Result<()> {
+    // Only the link created here may be deleted below, never one which was already there
+    if Interface::new(&link.name)?.index().is_ok() {
+        bail!("Link '{}' already exists", link.name);
+    }
+    backend.add_link(&link)?;
+    info!("Link '{}' created", link);
+
+    // Don't leave a half configured link behind
+    let interface = Interface::new(&link.name)?;
+    if let Err(e) = configure(&interface, addresses, up, backend) {
+        if let Err(del) = backend.del_link(&interface) {
+            warn!("Failed to delete link '{}': {}", interface.name(), del);
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+fn change(name: &str, kind: LinkKind, backend: &dyn Backend) -> Result<()> {
+    let options = kind
+        .tunnel_options()
+        .ok_or_else(|| anyhow!("Only tunnels can be changed, not {} links", kind))?;
+    backend.change_tunnel(&Interface::new(name)?, &kind)?;
+    info!("Tunnel '{}' changed to {}", name, options);
+    Ok(())
+}
+
+fn del(name: &str, backend: &dyn Backend) -> Result<()> {
+    backend.del_link(&Interface::new(name)?)?;
+    info!("Link '{}' deleted", name);
+    Ok(())
+}
+
+fn enslave(interface: &str, master: &str, backend: &dyn Backend) -> Result<()> {
+    backend.set_master(&Interface::new(interface)?, Some(&Interface::new(master)?))?;
+    info!("Interface '{}' enslaved to '{}'", interface, master);
+    Ok(())
+}
+
+fn release(interface: &str, backend: &dyn Backend) -> Result<()> {
+    backend.set_master(&Interface::new(interface)?, None)?;
+    info!("Interface '{}' released", interface);
+    Ok(())
+}
+
+fn set_netns(interface: &str, netns: &str, backend: &dyn Backend) -> Result<()> {
+    backend.set_netns(&Interface::new(interface)?, &Netns::open(netns)?)?;
+    info!("Interface '{}' moved to network namespace '{}'", interface, netns);
+    Ok(())
+}
+
+pub fn run(command: LinkCommand, backend: &dyn Backend) -> Result<()> {
+    match command {
+        LinkCommand::Add {
+            name,
+            mtu,
+            address,
+            addresses,
+            up,
+            kind,
+        } => {
+            Interface::new(&name)?;
+            let mut link = Link::new(&name, kind.kind()?);
+            link.mtu = mtu;
+            link.mac = address;
+            add(link, &addresses, up, backend)
+        }
+        LinkCommand::Change { name, kind } => change(&name, kind.kind()?, backend),
+        LinkCommand::Del { name } => del(&name, backend),
+        LinkCommand::Enslave { interface, master } => enslave(&interface, &master, backend),
+        LinkCommand::Release { interface } => release(&interface, backend),
+        LinkCommand::SetNetns { interface, namespace } => set_netns(&interface, &namespace, backend),
+    }
+}
@@ -0,0 +1,11 @@
+//! Command line helpers shared by the subcommands
+pub mod addr;
+pub mod backend;
+pub mod bridge;
+pub mod link;
+pub mod neigh;
+pub mod netns;
+pub mod output;
+pub mod route;
+pub mod rule;
+pub mod wg;
@@ -0,0 +1,143 @@
+//! `neigh` subcommands, see docs/output.md for the output of `neigh list`
+use super::output::OutputFormat;
+use anyhow::{anyhow, Result};
+use log::info;
+use network_config::{Backend, Interface, MacAddr, Neighbour, NeighbourState};
+use std::net::IpAddr;
+use structopt::StructOpt;
+
+/// Neighbour to add or replace
+#[derive(Debug, StructOpt)]
+pub struct NeighArgs {
+    /// Interface the neighbour is reached through
+    interface: String,
+
+    /// IPv4 or IPv6 address of the neighbour
+    address: IpAddr,
+
+    /// MAC address of the neighbour
+    mac: MacAddr,
+
+    /// State of the entry, permanent or reachable (the netlink backend also takes stale, noarp, ...)
+    #[structopt(long, default_value = "permanent")]
+    state: NeighbourState,
+
+    /// Mark the neighbour as an IPv6 router
+    #[structopt(long)]
+    router: bool,
+}
+
+impl NeighArgs {
+    fn neighbour(&self) -> Result<Neighbour> {
+        // Fail early on unknown interfaces, the ioctl backend would only report ENODEV
+        Interface::new(&self.interface)?.index()?;
+        let mut neighbour = Neighbour::new(self.address, &self.interface, self.mac);
+        neighbour.state = self.state;
+        neighbour.router = self.router;
+        Ok(neighbour)
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum NeighCommand {
+    /// List the neighbours of an interface, or of every interface
+    List {
+        /// Interface to query
+        interface: Option<String>,
+
+        /// Only list IPv4 neighbours
+        #[structopt(short = "4", conflicts_with = "ipv6")]
+        ipv4: bool,
+
+        /// Only list IPv6 neighbours
+        #[structopt(short = "6")]
+        ipv6: bool,
+    },
+    /// Add a neighbour, failing if its address already has an entry
+    Add(NeighArgs),
+    /// Add a neighbour, or update the entry of its address
+    Replace(NeighArgs),
+    /// Remove a neighbour
+    Del {
+        /// Interface the neighbour is reached through
+        interface: String,
+
+        /// Address of the neighbour
+        address: IpAddr,
+    },
+    /// Remove the learned neighbours of an interface, or of every interface
+    Flush {
+        /// Interface to change
+        interface: Option<String>,
+
+        /// Remove permanent entries as well
+        #[structopt(long)]
+        all: bool,
+    },
+}
+
+/// Neighbours of `interface`, or of every interface
+fn neighbours(interface: Option<&str>, backend: &dyn Backend) -> Result<Vec<Neighbour>> {
+    let mut neighbours = backend.neighbours()?;
+    if let Some(interface) = interface {
+        Interface::new(interface)?.index()?;
+        neighbours.retain(|neighbour| neighbour.interface == interface);
+    }
+    Ok(neighbours)
+}
+
+fn list(interface: Option<String>, ipv4: bool, ipv6: bool, backend: &dyn Backend, output: OutputFormat) -> Result<()> {
+    let mut result = neighbours(interface.as_deref(), backend)?;
+    result.retain(|neighbour| match neighbour.address {
+        IpAddr::V4(_) => !ipv6,
+        IpAddr::V6(_) => !ipv4,
+    });
+    output.print(&result, |result| {
+        for neighbour in result {
+            println!("{}", neighbour);
+        }
+    })
+}
+
+fn add(args: NeighArgs, backend: &dyn Backend, replace: bool) -> Result<()> {
+    let neighbour = args.neighbour()?;
+    if replace {
+        backend.replace_neighbour(&neighbour)?;
+    } else {
+        backend.add_neighbour(&neighbour)?;
+    }
+    info!("Neighbour '{}' set", neighbour);
+    Ok(())
+}
+
+fn del(interface: &str, address: &IpAddr, backend: &dyn Backend) -> Result<()> {
+    let neighbour = neighbours(Some(interface), backend)?
+        .into_iter()
+        .find(|neighbour| neighbour.address == *address)
+        .ok_or_else(|| anyhow!("Interface '{}' has no neighbour '{}'", interface, address))?;
+    backend.del_neighbour(&neighbour)?;
+    info!("Neighbour '{}' removed from interface '{}'", address, interface);
+    Ok(())
+}
+
+fn flush(interface: Option<String>, all: bool, backend: &dyn Backend) -> Result<()> {
+    if let Some(interface) = &interface {
+        Interface::new(interface)?.index()?;
+    }
+    let filter = |neighbour: &Neighbour| {
+        interface.as_ref().is_none_or(|interface| neighbour.interface == *interface) && (all || !neighbour.is_static())
+    };
+    let removed = backend.flush_neighbours(&filter)?;
+    info!("Removed {} neighbours", removed.len());
+    Ok(())
+}
+
+pub fn run(command: NeighCommand, backend: &dyn Backend, output: OutputFormat) -> Result<()> {
+    match command {
+        NeighCommand::List { interface, ipv4, ipv6 } => list(interface, ipv4, ipv6, backend, output),
+        NeighCommand::Add(args) => add(args, backend, false),
+        NeighCommand::Replace(args) => add(args, backend, true),
+        NeighCommand::Del { interface, address } => del(&interface, &address, backend),
+        NeighCommand::Flush { interface, all } => flush(interface, all, backend),
+    }
+}
@@ -0,0 +1,75 @@
+//! `netns` subcommands, see docs/output.md for the output of `netns list`
+use super::output::OutputFormat;
+use anyhow::{bail, Result};
+use log::info;
+use network_config::Netns;
+use serde::Serialize;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+use structopt::clap::AppSettings;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub enum NetnsCommand {
+    /// List the named network namespaces
+    List,
+    /// Create a network namespace, with only a loopback interface which is down
+    Add {
+        /// Name of the new namespace
+        name: String,
+    },
+    /// Delete a network namespace, which lives on while processes are left in it
+    Del {
+        /// Namespace to delete
+        name: String,
+    },
+    /// Run a command in a network namespace
+    #[structopt(setting = AppSettings::TrailingVarArg)]
+    Exec {
+        /// Namespace to run the command in
+        name: String,
+
+        /// Command and its arguments
+        #[structopt(required = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+}
+
+/// Entry of `netns list`
+#[derive(Debug, Serialize)]
+struct NetnsOutput {
+    name: String,
+}
+
+fn list(output: OutputFormat) -> Result<()> {
+    let namespaces: Vec<NetnsOutput> = Netns::list()?.into_iter().map(|name| NetnsOutput { name }).collect();
+    output.print(&namespaces, |namespaces| {
+        for netns in namespaces {
+            println!("{}", netns.name);
+        }
+    })
+}
+
+/// Replace the process by `command` run in the namespace `name`
+fn exec(name: &str, command: &[String]) -> Result<()> {
+    Netns::open(name)?.enter()?;
+    let e = Command::new(&command[0]).args(&command[1..]).exec();
+    bail!("Failed to run '{}': {}", command[0], e)
+}
+
+pub fn run(command: NetnsCommand, output: OutputFormat) -> Result<()> {
+    match command {
+        NetnsCommand::List => list(output),
+        NetnsCommand::Add { name } => {
+            Netns::add(&name)?;
+            info!("Network namespace '{}' created", name);
+            Ok(())
+        }
+        NetnsCommand::Del { name } => {
+            Netns::open(&name)?.delete()?;
+            info!("Network namespace '{}' deleted", name);
+            Ok(())
+        }
+        NetnsCommand::Exec { name, command } => exec(&name, &command),
+    }
+}
@@ -0,0 +1,38 @@
+//! Output formats of the read operations, see docs/output.md
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Format results are printed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable, iproute2 like
+    Table,
+    Json,
+    Yaml,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" => Ok(OutputFormat::Yaml),
+            _ => bail!("Unknown output format '{}', expected json, yaml or table", s),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Print `value` serialized, or through `table` for the table format
+    pub fn print<T: Serialize>(self, value: &T, table: impl FnOnce(&T)) -> Result<()> {
+        match self {
+            OutputFormat::Table => table(value),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+            OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        }
+        Ok(())
+    }
+}
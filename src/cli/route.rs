@@ -0,0 +1,144 @@
+//! `route` subcommands, see docs/output.md for the output of `route list` and `route get`
+use super::output::OutputFormat;
+use anyhow::{bail, Result};
+use log::info;
+use network_config::{Backend, Cidr, Route, RouteType, Scope, Table};
+use std::net::IpAddr;
+use structopt::StructOpt;
+
+/// Route to add or remove
+#[derive(Debug, StructOpt)]
+pub struct RouteArgs {
+    /// `default`, or a prefix such as `10.1.0.0/16`, a single host when the prefix length is omitted
+    destination: String,
+
+    /// Gateway to send packets through
+    #[structopt(long)]
+    via: Option<IpAddr>,
+
+    /// Outgoing interface
+    #[structopt(long)]
+    dev: Option<String>,
+
+    /// Priority of the route, lower being preferred
+    #[structopt(long)]
+    metric: Option<u32>,
+
+    /// Routing table: main, local, default or a number
+    #[structopt(long, default_value = "main")]
+    table: Table,
+
+    /// unicast, local, blackhole, unreachable, prohibit or throw
+    #[structopt(long = "type", default_value = "unicast")]
+    kind: RouteType,
+
+    /// Assume the gateway is reachable on the interface even without a matching prefix
+    #[structopt(long)]
+    onlink: bool,
+
+    /// Preferred source address of packets sent along the route
+    #[structopt(long)]
+    src: Option<IpAddr>,
+
+    /// Scope of the route, derived from its type and gateway when omitted
+    #[structopt(long)]
+    scope: Option<Scope>,
+
+    /// Make `default` an IPv6 route when no IPv6 gateway tells so
+    #[structopt(short = "6")]
+    ipv6: bool,
+}
+
+impl RouteArgs {
+    /// Route described by the arguments, the scope being derived for added routes only
+    fn route(&self, add: bool) -> Result<Route> {
+        let ipv6 = self.ipv6 || self.via.is_some_and(|via| via.is_ipv6());
+        let destination = match self.destination.as_str() {
+            "default" if ipv6 => Cidr { addr: IpAddr::from([0u8; 16]), prefix_len: Some(0) },
+            "default" => Cidr { addr: IpAddr::from([0u8; 4]), prefix_len: Some(0) },
+            destination => destination.parse()?,
+        };
+        let family_matches = |addr: &IpAddr| addr.is_ipv4() == destination.addr.is_ipv4();
+        if !self.via.iter().chain(&self.src).all(family_matches) {
+            bail!("The gateway, source and destination must be of the same family");
+        }
+
+        let mut route = Route::new(destination);
+        route.kind = self.kind;
+        route.gateway = self.via;
+        route.interface = self.dev.clone();
+        route.source = self.src;
+        route.metric = self.metric;
+        route.table = self.table;
+        route.onlink = self.onlink;
+        route.scope = match self.scope {
+            Some(scope) => scope,
+            None if add => route.default_scope(),
+            None => Scope::Universe,
+        };
+        Ok(route)
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum RouteCommand {
+    /// List the routes of a table
+    List {
+        /// Routing table: main, local, default, a number or all
+        #[structopt(long, default_value = "main")]
+        table: Table,
+
+        /// Only list IPv4 routes
+        #[structopt(short = "4", conflicts_with = "ipv6")]
+        ipv4: bool,
+
+        /// Only list IPv6 routes
+        #[structopt(short = "6")]
+        ipv6: bool,
+    },
+    /// Print the route used to reach an address
+    Get {
+        /// Address to reach
+        address: IpAddr,
+    },
+    /// Add a route
+    Add(RouteArgs),
+    /// Remove a route
+    Del(RouteArgs),
+}
+
+fn list(table: Table, ipv4: bool, ipv6: bool, backend: &dyn Backend, output: OutputFormat) -> Result<()> {
+    let routes: Vec<Route> = backend
+        .routes()?
+        .into_iter()
+        .filter(|route| table == Table::UNSPEC || route.table == table)
+        .filter(|route| if route.destination.addr.is_ipv4() { !ipv6 } else { !ipv4 })
+        .collect();
+    output.print(&routes, |routes| {
+        for route in routes {
+            println!("{}", route);
+        }
+    })
+}
+
+pub fn run(command: RouteCommand, backend: &dyn Backend, output: OutputFormat) -> Result<()> {
+    match command {
+        RouteCommand::List { table, ipv4, ipv6 } => list(table, ipv4, ipv6, backend, output),
+        RouteCommand::Get { address } => {
+            let route = backend.get_route(&address)?;
+            output.print(&route, |route| println!("{}", route))
+        }
+        RouteCommand::Add(args) => {
+            let route = args.route(true)?;
+            backend.add_route(&route)?;
+            info!("Route '{}' added", route);
+            Ok(())
+        }
+        RouteCommand::Del(args) => {
+            let route = args.route(false)?;
+            backend.del_route(&route)?;
+            info!("Route '{}' removed", route);
+            Ok(())
+        }
+    }
+}
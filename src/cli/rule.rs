@@ -0,0 +1,173 @@
+//! `rule` subcommands, see docs/output.md for the output of `rule list`
+use super::output::OutputFormat;
+use anyhow::{bail, Result};
+use log::info;
+use network_config::{Backend, Cidr, Family, Rule, RuleAction, Table};
+use structopt::StructOpt;
+
+/// Parse a firewall mark with an optional mask, e.g. `0x10/0xff`
+pub fn parse_fwmark(s: &str) -> Result<(u32, Option<u32>)> {
+    let parse = |s: &str| match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    let parsed = match s.split_once('/') {
+        Some((mark, mask)) => parse(mark).and_then(|mark| Ok((mark, Some(parse(mask)?)))),
+        None => parse(s).map(|mark| (mark, None)),
+    };
+    parsed.map_err(|_| anyhow::anyhow!("Invalid firewall mark '{}'", s))
+}
+
+/// Rule to add or remove
+#[derive(Debug, StructOpt)]
+pub struct RuleArgs {
+    /// Source prefix of packets
+    #[structopt(long)]
+    from: Option<Cidr>,
+
+    /// Destination prefix of packets
+    #[structopt(long)]
+    to: Option<Cidr>,
+
+    /// Firewall mark of packets with an optional mask, e.g. `0x10/0xff`
+    #[structopt(long, parse(try_from_str = parse_fwmark))]
+    fwmark: Option<(u32, Option<u32>)>,
+
+    /// Interface packets are received on
+    #[structopt(long)]
+    iif: Option<String>,
+
+    /// Interface packets are sent from
+    #[structopt(long)]
+    oif: Option<String>,
+
+    /// Priority of the rule, lower being evaluated first
+    #[structopt(long)]
+    priority: Option<u32>,
+
+    /// Table to look routes up in, main when adding a lookup rule
+    #[structopt(long)]
+    table: Option<Table>,
+
+    /// lookup, goto, nop, blackhole, unreachable or prohibit
+    #[structopt(long, default_value = "lookup")]
+    action: RuleAction,
+
+    /// Priority of the rule to continue with, for the goto action
+    #[structopt(long)]
+    goto: Option<u32>,
+
+    /// Match packets not matching the selectors
+    #[structopt(long)]
+    not: bool,
+
+    /// Make a rule without prefixes an IPv6 one
+    #[structopt(short = "6")]
+    ipv6: bool,
+}
+
+impl RuleArgs {
+    /// Rule described by the arguments, the table defaulting to main for added rules only
+    fn rule(&self, add: bool) -> Result<Rule> {
+        let families: Vec<Family> = self.from.iter().chain(&self.to).map(|cidr| Family::of(&cidr.addr)).collect();
+        let family = match families.first() {
+            Some(family) if families.iter().any(|other| other != family) => {
+                bail!("The source and destination prefixes must be of the same family")
+            }
+            Some(family) => *family,
+            None if self.ipv6 => Family::Inet6,
+            None => Family::Inet,
+        };
+        if self.goto.is_some() != (self.action == RuleAction::Goto) {
+            bail!("The goto action needs --goto and only it");
+        }
+
+        let mut rule = Rule::new(family, Table::MAIN);
+        rule.priority = self.priority;
+        rule.from = self.from;
+        rule.to = self.to;
+        rule.fwmark = self.fwmark.map(|(mark, _)| mark);
+        rule.fwmask = self.fwmark.and_then(|(_, mask)| mask);
+        rule.iif = self.iif.clone();
+        rule.oif = self.oif.clone();
+        rule.invert = self.not;
+        rule.action = self.action;
+        rule.goto = self.goto;
+        rule.table = match self.table {
+            Some(table) => Some(table),
+            None if add && self.action == RuleAction::Lookup => Some(Table::MAIN),
+            None => None,
+        };
+        Ok(rule)
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum RuleCommand {
+    /// List the policy routing rules
+    List {
+        /// List the IPv4 rules, the default as with `ip rule`
+        #[structopt(short = "4", conflicts_with = "ipv6")]
+        ipv4: bool,
+
+        /// List the IPv6 rules instead
+        #[structopt(short = "6")]
+        ipv6: bool,
+    },
+    /// Add a rule
+    Add(RuleArgs),
+    /// Remove the first rule matching the given selectors
+    Del(RuleArgs),
+}
+
+fn list(family: Family, backend: &dyn Backend, output: OutputFormat) -> Result<()> {
+    // Rules without prefixes print the same in both families, only one of them is listed
+    let rules: Vec<Rule> = backend.rules()?.into_iter().filter(|rule| rule.family == family).collect();
+    output.print(&rules, |rules| {
+        for rule in rules {
+            println!("{}", rule);
+        }
+    })
+}
+
+pub fn run(command: RuleCommand, backend: &dyn Backend, output: OutputFormat) -> Result<()> {
+    match command {
+        RuleCommand::List { ipv4, ipv6 } => {
+            let family = if ipv6 && !ipv4 { Family::Inet6 } else { Family::Inet };
+            list(family, backend, output)
+        }
+        RuleCommand::Add(args) => {
+            let rule = args.rule(true)?;
+            backend.add_rule(&rule)?;
+            info!("Rule '{}' added", rule);
+            Ok(())
+        }
+        RuleCommand::Del(args) => {
+            let rule = args.rule(false)?;
+            backend.del_rule(&rule)?;
+            info!("Rule '{}' removed", rule);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fwmark() {
+        assert_eq!(parse_fwmark("16").unwrap(), (16, None));
+        assert_eq!(parse_fwmark("0x10").unwrap(), (16, None));
+        assert_eq!(parse_fwmark("0x10/0xff").unwrap(), (16, Some(255)));
+        assert_eq!(parse_fwmark("1/3").unwrap(), (1, Some(3)));
+        assert_eq!(parse_fwmark("0xffffffff").unwrap(), (u32::MAX, None));
+    }
+
+    #[test]
+    fn invalid_fwmark() {
+        for s in ["", "0x", "0x1g", "-1", "0x100000000", "1/", "/1", "1/2/3"] {
+            assert!(parse_fwmark(s).is_err(), "{}", s);
+        }
+    }
+}
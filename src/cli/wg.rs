@@ -0,0 +1,230 @@
+//! `wg` subcommands, see docs/output.md for the output of `wg show`
+use super::output::OutputFormat;
+use anyhow::{anyhow, bail, Result};
+use log::info;
+use network_config::{Backend, Cidr, Interface, WireguardConfig, WireguardDevice, WireguardKey, WireguardPeerConfig};
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use structopt::StructOpt;
+
+/// Parse a firewall mark, e.g. `0x10`
+fn parse_fwmark(s: &str) -> Result<u32> {
+    match super::rule::parse_fwmark(s)? {
+        (mark, None) => Ok(mark),
+        (_, Some(_)) => bail!("WireGuard firewall marks take no mask, got '{}'", s),
+    }
+}
+
+/// Read a base64 key from `path`, as written by `wg genkey` or `wg genpsk`
+fn read_key(path: &Path) -> Result<WireguardKey> {
+    let content = fs::read_to_string(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    content
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("{} does not hold a WireGuard key", path.display()))
+}
+
+/// Peer to add or change
+#[derive(Debug, StructOpt)]
+pub struct PeerArgs {
+    /// WireGuard device
+    interface: String,
+
+    /// Public key of the peer, in base64
+    public_key: WireguardKey,
+
+    /// File holding a key shared with the peer, as written by `wg genpsk`
+    #[structopt(long)]
+    preshared_key: Option<PathBuf>,
+
+    /// Address and port of the peer, e.g. `192.0.2.1:51820` or `[2001:db8::1]:51820`
+    #[structopt(long)]
+    endpoint: Option<SocketAddr>,
+
+    /// Destination routed to the peer and source accepted from it, e.g. `10.0.0.2/32`, can be repeated
+    #[structopt(long = "allowed-ip", number_of_values = 1)]
+    allowed_ips: Vec<Cidr>,
+
+    /// Replace the allowed IPs of the peer rather than add to them
+    #[structopt(long)]
+    replace_allowed_ips: bool,
+
+    /// Seconds between two keepalives keeping NAT mappings open, 0 for none
+    #[structopt(long)]
+    persistent_keepalive: Option<u16>,
+}
+
+impl PeerArgs {
+    fn peer(&self) -> Result<WireguardPeerConfig> {
+        let mut peer = WireguardPeerConfig::new(self.public_key);
+        peer.preshared_key = self.preshared_key.as_deref().map(read_key).transpose()?;
+        peer.endpoint = self.endpoint;
+        peer.allowed_ips = self.allowed_ips.clone();
+        peer.replace_allowed_ips = self.replace_allowed_ips;
+        peer.persistent_keepalive = self.persistent_keepalive;
+        Ok(peer)
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum PeerCommand {
+    /// Add a peer, or change it if the device already has it
+    Add(PeerArgs),
+    /// Remove a peer
+    Del {
+        /// WireGuard device
+        interface: String,
+
+        /// Public key of the peer, in base64
+        public_key: WireguardKey,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum WgCommand {
+    /// Show the public key, port and peers of every WireGuard device, or of one
+    Show {
+        /// WireGuard device to query
+        interface: Option<String>,
+    },
+    /// Change the private key, listening port or firewall mark of a WireGuard device
+    Set {
+        /// WireGuard device
+        interface: String,
+
+        /// File holding the private key, as written by `wg genkey`
+        #[structopt(long)]
+        private_key: Option<PathBuf>,
+
+        /// UDP port to listen on, 0 for a random one
+        #[structopt(long)]
+        listen_port: Option<u16>,
+
+        /// Firewall mark of the encapsulated packets, 0 for none
+        #[structopt(long, parse(try_from_str = parse_fwmark))]
+        fwmark: Option<u32>,
+    },
+    /// Add, change or remove the peers of a WireGuard device
+    Peer(PeerCommand),
+}
+
+/// Formats a handshake as wg(8) does, e.g. `42 seconds ago`
+fn since(seconds: u64) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs());
+    match now.saturating_sub(seconds) {
+        0 => "now".to_string(),
+        1 => "1 second ago".to_string(),
+        elapsed => format!("{} seconds ago", elapsed),
+    }
+}
+
+fn print_device(device: &WireguardDevice) {
+    println!("interface: {}", device.interface);
+    if let Some(public_key) = &device.public_key {
+        println!("  public key: {}", public_key);
+    }
+    println!("  listening port: {}", device.listen_port);
+    if let Some(fwmark) = device.fwmark {
+        println!("  fwmark: {:#x}", fwmark);
+    }
+    for peer in &device.peers {
+        println!();
+        println!("peer: {}", peer.public_key);
+        if let Some(endpoint) = peer.endpoint {
+            println!("  endpoint: {}", endpoint);
+        }
+        let allowed_ips: Vec<String> = peer.allowed_ips.iter().map(Cidr::to_string).collect();
+        if allowed_ips.is_empty() {
+            println!("  allowed ips: (none)");
+        } else {
+            println!("  allowed ips: {}", allowed_ips.join(", "));
+        }
+        if let Some(last_handshake) = peer.last_handshake {
+            println!("  latest handshake: {}", since(last_handshake));
+        }
+        if peer.rx_bytes != 0 || peer.tx_bytes != 0 {
+            println!("  transfer: {} B received, {} B sent", peer.rx_bytes, peer.tx_bytes);
+        }
+        if let Some(interval) = peer.persistent_keepalive {
+            println!("  persistent keepalive: every {} seconds", interval);
+        }
+    }
+}
+
+fn show(interface: Option<String>, backend: &dyn Backend, output: OutputFormat) -> Result<()> {
+    let interfaces = match interface {
+        Some(interface) => vec![Interface::new(&interface)?],
+        None => Interface::list()?
+            .into_iter()
+            .filter_map(|interface| match interface.is_wireguard() {
+                Ok(true) => Some(Ok(interface)),
+                Ok(false) => None,
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<Result<Vec<Interface>>>()?,
+    };
+    let devices = interfaces
+        .iter()
+        .map(|interface| backend.wireguard(interface))
+        .collect::<Result<Vec<WireguardDevice>>>()?;
+    output.print(&devices, |devices| {
+        for (i, device) in devices.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            print_device(device);
+        }
+    })
+}
+
+fn set(interface: &str, config: &WireguardConfig, backend: &dyn Backend) -> Result<()> {
+    backend.set_wireguard(&Interface::new(interface)?, config)
+}
+
+pub fn run(command: WgCommand, backend: &dyn Backend, output: OutputFormat) -> Result<()> {
+    match command {
+        WgCommand::Show { interface } => show(interface, backend, output),
+        WgCommand::Set {
+            interface,
+            private_key,
+            listen_port,
+            fwmark,
+        } => {
+            let config = WireguardConfig {
+                private_key: private_key.as_deref().map(read_key).transpose()?,
+                listen_port,
+                fwmark,
+                ..Default::default()
+            };
+            if config.is_empty() {
+                bail!("No WireGuard option given");
+            }
+            set(&interface, &config, backend)?;
+            info!("WireGuard device '{}' configured", interface);
+            Ok(())
+        }
+        WgCommand::Peer(PeerCommand::Add(args)) => {
+            let peer = args.peer()?;
+            let config = WireguardConfig {
+                peers: vec![peer.clone()],
+                ..Default::default()
+            };
+            set(&args.interface, &config, backend)?;
+            info!("WireGuard device '{}' set with {}", args.interface, peer);
+            Ok(())
+        }
+        WgCommand::Peer(PeerCommand::Del { interface, public_key }) => {
+            let mut peer = WireguardPeerConfig::new(public_key);
+            peer.remove = true;
+            let config = WireguardConfig {
+                peers: vec![peer],
+                ..Default::default()
+            };
+            set(&interface, &config, backend)?;
+            info!("Peer {} removed from WireGuard device '{}'", public_key, interface);
+            Ok(())
+        }
+    }
+}
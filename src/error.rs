@@ -0,0 +1,33 @@
+//! Typed errors for failures callers are expected to handle
+use nix::errno::Errno;
+use std::fmt;
+
+/// Error returned when an MTU can not be applied to an interface
+///
+/// Returned inside the `anyhow::Error` of [`crate::Interface::set_mtu`],
+/// get it back with `downcast_ref::<MtuError>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MtuError {
+    /// The MTU is outside of the range the device accepts, `max` being unset when it has no upper limit
+    OutOfRange { mtu: u32, min: u32, max: Option<u32> },
+    /// The driver refused the MTU
+    Rejected { interface: String, mtu: u32, errno: Errno },
+}
+
+impl fmt::Display for MtuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MtuError::OutOfRange { mtu, min, max: Some(max) } => {
+                write!(f, "MTU {} is out of range, expected {} to {}", mtu, min, max)
+            }
+            MtuError::OutOfRange { mtu, min, max: None } => {
+                write!(f, "MTU {} is out of range, expected at least {}", mtu, min)
+            }
+            MtuError::Rejected { interface, mtu, errno } => {
+                write!(f, "Interface '{}' rejected MTU {}: {}", interface, mtu, errno)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MtuError {}
@@ -0,0 +1,117 @@
+//! Interface flags carried in `ifr_flags`, see netdevice(7)
+use anyhow::{bail, Result};
+use bitflags::bitflags;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+bitflags! {
+    /// Flags of a network interface
+    pub struct InterfaceFlags: libc::c_int {
+        /// Interface is running
+        const UP = libc::IFF_UP;
+        /// Valid broadcast address set
+        const BROADCAST = libc::IFF_BROADCAST;
+        /// Internal debugging flag
+        const DEBUG = libc::IFF_DEBUG;
+        /// Interface is a loopback interface
+        const LOOPBACK = libc::IFF_LOOPBACK;
+        /// Interface is a point-to-point link
+        const POINTOPOINT = libc::IFF_POINTOPOINT;
+        /// Avoid use of trailers
+        const NOTRAILERS = libc::IFF_NOTRAILERS;
+        /// Resources allocated
+        const RUNNING = libc::IFF_RUNNING;
+        /// No arp protocol, L2 destination address not set
+        const NOARP = libc::IFF_NOARP;
+        /// Interface is in promiscuous mode
+        const PROMISC = libc::IFF_PROMISC;
+        /// Receive all multicast packets
+        const ALLMULTI = libc::IFF_ALLMULTI;
+        /// Master of a load balancing bundle
+        const MASTER = libc::IFF_MASTER;
+        /// Slave of a load balancing bundle
+        const SLAVE = libc::IFF_SLAVE;
+        /// Supports multicast
+        const MULTICAST = libc::IFF_MULTICAST;
+        /// Is able to select media type via ifmap
+        const PORTSEL = libc::IFF_PORTSEL;
+        /// Auto media selection active
+        const AUTOMEDIA = libc::IFF_AUTOMEDIA;
+        /// The addresses are lost when the interface goes down
+        const DYNAMIC = libc::IFF_DYNAMIC;
+    }
+}
+
+const NAMES: &[(InterfaceFlags, &str)] = &[
+    (InterfaceFlags::UP, "UP"),
+    (InterfaceFlags::BROADCAST, "BROADCAST"),
+    (InterfaceFlags::DEBUG, "DEBUG"),
+    (InterfaceFlags::LOOPBACK, "LOOPBACK"),
+    (InterfaceFlags::POINTOPOINT, "POINTOPOINT"),
+    (InterfaceFlags::NOTRAILERS, "NOTRAILERS"),
+    (InterfaceFlags::RUNNING, "RUNNING"),
+    (InterfaceFlags::NOARP, "NOARP"),
+    (InterfaceFlags::PROMISC, "PROMISC"),
+    (InterfaceFlags::ALLMULTI, "ALLMULTI"),
+    (InterfaceFlags::MASTER, "MASTER"),
+    (InterfaceFlags::SLAVE, "SLAVE"),
+    (InterfaceFlags::MULTICAST, "MULTICAST"),
+    (InterfaceFlags::PORTSEL, "PORTSEL"),
+    (InterfaceFlags::AUTOMEDIA, "AUTOMEDIA"),
+    (InterfaceFlags::DYNAMIC, "DYNAMIC"),
+];
+
+impl InterfaceFlags {
+    /// Names of the flags set, e.g. `["UP", "BROADCAST"]`
+    pub fn names(&self) -> Vec<&'static str> {
+        NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+/// Formats as `UP,BROADCAST,RUNNING`
+impl fmt::Display for InterfaceFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.names().join(","))
+    }
+}
+
+/// Parses comma separated flag names, case insensitive
+impl FromStr for InterfaceFlags {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut flags = InterfaceFlags::empty();
+        for name in s.split(',').filter(|name| !name.is_empty()) {
+            match NAMES.iter().find(|(_, known)| known.eq_ignore_ascii_case(name)) {
+                Some((flag, _)) => flags |= *flag,
+                None => bail!("Unknown interface flag '{}'", name),
+            }
+        }
+        Ok(flags)
+    }
+}
+
+/// Serializes as the list of flag names, e.g. `["UP", "BROADCAST"]`
+impl Serialize for InterfaceFlags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.names())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let flags: InterfaceFlags = "up,broadcast,running,multicast".parse().unwrap();
+        let expected = serde_json::json!(["UP", "BROADCAST", "RUNNING", "MULTICAST"]);
+        assert_eq!(serde_json::to_value(flags).unwrap(), expected);
+        assert_eq!(serde_json::to_value(InterfaceFlags::empty()).unwrap(), serde_json::json!([]));
+    }
+}
@@ -0,0 +1,644 @@
+//! Safe handle over the netdevice ioctls of a single interface
+use crate::addr::{
+    broadcast_from_prefix_len, netmask_from_prefix_len, prefix_len_from_netmask, Address, AddressFlags, Cidr, Scope,
+};
+use crate::bond::{BondInfo, BondMode, BondSlave};
+use crate::error::MtuError;
+use crate::flags::InterfaceFlags;
+use crate::mac::{HwAddress, MacAddr};
+use crate::ioctl::*;
+use crate::netlink::{self, NetlinkSocket, NETLINK_ROUTE};
+use crate::socket::ControlSocket;
+use anyhow::{anyhow, bail, Result};
+use ifstructs::ifreq;
+use nix::errno::Errno;
+use nix::net::if_::if_nameindex;
+use nix::sys::socket::AddressFamily;
+use serde::Serialize;
+use std::fs;
+use std::io::ErrorKind;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::unix::io::AsRawFd;
+
+/// Snapshot of the configuration of an interface
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InterfaceInfo {
+    pub name: String,
+    pub index: u32,
+    pub flags: InterfaceFlags,
+    pub mtu: u32,
+    pub hw_address: HwAddress,
+    /// IPv4 addresses, including aliases, followed by IPv6 addresses
+    pub addresses: Vec<Cidr>,
+    /// Only set on bonds
+    pub bond: Option<BondInfo>,
+    /// Interfaces stacked on this one, such as its VLANs, macvlans and ipvlans
+    pub children: Vec<String>,
+}
+
+/// Primary IPv4 address of an interface along with its broadcast and peer addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Ipv4Info {
+    pub address: Cidr,
+    /// Unset when the interface has no broadcast address
+    pub broadcast: Option<Ipv4Addr>,
+    /// Only set on point-to-point interfaces
+    pub peer: Option<Ipv4Addr>,
+}
+
+/// Handle to a network interface, identified by its name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interface {
+    name: String,
+}
+
+impl Interface {
+    /// Create a handle for the interface `name`
+    ///
+    /// The interface itself is only looked up by the kernel once an operation is made.
+    pub fn new(name: &str) -> Result<Interface> {
+        // Validates the name fits in an ifreq
+        ifreq::from_name(name)?;
+        Ok(Interface { name: name.to_string() })
+    }
+
+    /// Handle for the interface with the index `index`
+    pub fn from_index(index: u32) -> Result<Interface> {
+        let mut name = [0 as libc::c_char; libc::IFNAMSIZ];
+        if unsafe { libc::if_indextoname(index, name.as_mut_ptr()) }.is_null() {
+            bail!("Failed to find interface with index {}: {}", index, Errno::last());
+        }
+        let name = unsafe { std::ffi::CStr::from_ptr(name.as_ptr()) };
+        Interface::new(&name.to_string_lossy())
+    }
+
+    /// All interfaces of the system, including those without addresses
+    pub fn list() -> Result<Vec<Interface>> {
+        if_nameindex()?
+            .iter()
+            .map(|interface| Interface::new(&interface.name().to_string_lossy()))
+            .collect()
+    }
+
+    /// Name of the interface
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Snapshot of the index, flags, MTU, hardware address and addresses of the interface
+    pub fn info(&self) -> Result<InterfaceInfo> {
+        Ok(InterfaceInfo {
+            name: self.name.clone(),
+            index: self.index()? as u32,
+            flags: self.flags()?,
+            mtu: self.mtu()?,
+            hw_address: self.hw_address()?,
+            addresses: self.addresses()?,
+            bond: self.bond()?,
+            children: self.children()?,
+        })
+    }
+
+    /// Primary IPv4 address of the interface with its prefix length, broadcast and peer addresses
+    pub fn ipv4_info(&self) -> Result<Ipv4Info> {
+        let addr = self.address()?;
+        let broadcast = self.broadcast()?;
+        let destination = self.destination()?;
+        Ok(Ipv4Info {
+            address: Cidr {
+                addr,
+                prefix_len: prefix_len_from_netmask(&self.netmask()?).ok(),
+            },
+            broadcast: if broadcast.is_unspecified() { None } else { Some(broadcast) },
+            peer: if self.flags()?.contains(InterfaceFlags::POINTOPOINT) && IpAddr::from(destination) != addr {
+                Some(destination)
+            } else {
+                None
+            },
+        })
+    }
+
+    /// IPv4 addresses of the interface, including aliases, followed by its IPv6 addresses
+    pub fn addresses(&self) -> Result<Vec<Cidr>> {
+        let mut addresses = Vec::new();
+        for (label, addr) in ipv4_addresses()? {
+            // Aliases are labeled `<name>:<alias>`
+            if label.split(':').next() != Some(self.name.as_str()) {
+                continue;
+            }
+
+            let netmask = Interface { name: label }.netmask()?;
+            addresses.push(Cidr {
+                addr: IpAddr::from(addr),
+                prefix_len: prefix_len_from_netmask(&netmask).ok(),
+            });
+        }
+
+        let index = self.index()? as u32;
+        addresses.extend(
+            ipv6_addresses()?
+                .into_iter()
+                .filter(|(ifindex, _)| *ifindex == index)
+                .map(|(_, addr)| addr.cidr),
+        );
+        Ok(addresses)
+    }
+
+    fn ifreq(&self) -> Result<ifreq> {
+        Ok(ifreq::from_name(&self.name)?)
+    }
+
+    /// Index of the interface
+    pub fn index(&self) -> Result<i32> {
+        let mut ifreq = self.ifreq()?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        unsafe { get_interface_index(sock.as_raw_fd(), &mut ifreq) }
+            .map_err(|e| anyhow!("Failed to get index of interface '{}': {}", self.name, e))?;
+        Ok(get_index(&ifreq))
+    }
+
+    /// Flags of the interface
+    pub fn flags(&self) -> Result<InterfaceFlags> {
+        let mut ifreq = self.ifreq()?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        unsafe { get_interface_flags(sock.as_raw_fd(), &mut ifreq) }
+            .map_err(|e| anyhow!("Failed to get flags of interface '{}': {}", self.name, e))?;
+        Ok(get_flags(&ifreq))
+    }
+
+    /// Replace the flags of the interface
+    pub fn set_flags(&self, flags: InterfaceFlags) -> Result<()> {
+        let mut ifreq = self.ifreq()?;
+        set_flags(&mut ifreq, flags);
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        unsafe { set_interface_flags(sock.as_raw_fd(), &ifreq) }
+            .map_err(|e| anyhow!("Failed to set flags of interface '{}': {}", self.name, e))?;
+        Ok(())
+    }
+
+    /// Set the flags in `set` and clear the flags in `clear`, keeping all others
+    pub fn update_flags(&self, set: InterfaceFlags, clear: InterfaceFlags) -> Result<()> {
+        let flags = self.flags()?;
+        let updated = (flags | set) - clear;
+        if updated != flags {
+            self.set_flags(updated)?;
+        }
+        Ok(())
+    }
+
+    /// Bring the interface up
+    pub fn up(&self) -> Result<()> {
+        self.update_flags(InterfaceFlags::UP, InterfaceFlags::empty())
+    }
+
+    /// Bring the interface down
+    pub fn down(&self) -> Result<()> {
+        self.update_flags(InterfaceFlags::empty(), InterfaceFlags::UP)
+    }
+
+    /// MTU of the interface
+    pub fn mtu(&self) -> Result<u32> {
+        let mut ifreq = self.ifreq()?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        unsafe { get_interface_mtu(sock.as_raw_fd(), &mut ifreq) }
+            .map_err(|e| anyhow!("Failed to get mtu of interface '{}': {}", self.name, e))?;
+        Ok(get_mtu(&ifreq))
+    }
+
+    /// Smallest and largest MTU the device accepts, from IFLA_MIN_MTU and IFLA_MAX_MTU
+    fn mtu_range(&self) -> Result<Option<(u32, u32)>> {
+        let sock = NetlinkSocket::open(NETLINK_ROUTE)?;
+        netlink::link::mtu_range(&sock, self.index()?)
+    }
+
+    /// Set the MTU of the interface
+    ///
+    /// MTUs outside of the range the device reports through rtnetlink are
+    /// refused before reaching the kernel, which otherwise has the last word.
+    /// Both cases are reported as a [`MtuError`].
+    pub fn set_mtu(&self, mtu: u32) -> Result<()> {
+        // Best effort, the kernel checks the MTU anyway
+        if let Ok(Some((min, max))) = self.mtu_range() {
+            let max = Some(max).filter(|max| *max != 0);
+            if mtu < min || max.is_some_and(|max| mtu > max) {
+                return Err(MtuError::OutOfRange { mtu, min, max }.into());
+            }
+        }
+
+        let mut ifreq = self.ifreq()?;
+        set_mtu(&mut ifreq, mtu);
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        match unsafe { set_interface_mtu(sock.as_raw_fd(), &ifreq) } {
+            Ok(_) => Ok(()),
+            Err(errno @ (Errno::EINVAL | Errno::ERANGE | Errno::EOPNOTSUPP)) => Err(MtuError::Rejected {
+                interface: self.name.clone(),
+                mtu,
+                errno,
+            }
+            .into()),
+            Err(e) => bail!("Failed to set mtu of interface '{}': {}", self.name, e),
+        }
+    }
+
+    /// Hardware address of the interface
+    pub fn hw_address(&self) -> Result<HwAddress> {
+        let mut ifreq = self.ifreq()?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        unsafe { get_interface_hwaddr(sock.as_raw_fd(), &mut ifreq) }
+            .map_err(|e| anyhow!("Failed to get hardware address of interface '{}': {}", self.name, e))?;
+        Ok(get_hwaddr(&ifreq))
+    }
+
+    /// Set the MAC address of the interface
+    ///
+    /// Most drivers refuse to change the address of an interface which is up.
+    pub fn set_mac_address(&self, mac: &MacAddr) -> Result<()> {
+        let link_type = self.hw_address()?.link_type;
+        if !link_type.has_mac() {
+            bail!("Interface '{}' of type '{}' has no MAC address", self.name, link_type);
+        }
+
+        let mut ifreq = self.ifreq()?;
+        set_hwaddr(&mut ifreq, link_type, mac);
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        unsafe { set_interface_hwaddr(sock.as_raw_fd(), &ifreq) }
+            .map_err(|e| anyhow!("Failed to set MAC address of interface '{}': {}", self.name, e))?;
+        Ok(())
+    }
+
+    /// Rename the interface, which must be down, returning a handle to it under its new name
+    pub fn rename(&self, name: &str) -> Result<Interface> {
+        let mut ifreq = self.ifreq()?;
+        set_newname(&mut ifreq, name)?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        unsafe { set_interface_name(sock.as_raw_fd(), &ifreq) }
+            .map_err(|e| anyhow!("Failed to rename interface '{}' to '{}': {}", self.name, name, e))?;
+        Interface::new(name)
+    }
+
+    /// Bond or bridge the interface is enslaved to, from sysfs
+    pub fn master(&self) -> Result<Option<Interface>> {
+        let path = format!("/sys/class/net/{}/master", self.name);
+        match fs::read_link(&path) {
+            Ok(master) => match master.file_name() {
+                Some(name) => Ok(Some(Interface::new(&name.to_string_lossy())?)),
+                None => bail!("Unexpected link {} to '{}'", path, master.display()),
+            },
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => bail!("Failed to read {}: {}", path, e),
+        }
+    }
+
+    /// Names of the interfaces stacked on this one, from the `upper_*` links of sysfs
+    ///
+    /// The master of the interface is an upper device as well and is left out.
+    pub fn children(&self) -> Result<Vec<String>> {
+        let path = format!("/sys/class/net/{}", self.name);
+        let master = self.master()?;
+        let mut children = Vec::new();
+        for entry in fs::read_dir(&path).map_err(|e| anyhow!("Failed to read {}: {}", path, e))? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if let Some(child) = name.strip_prefix("upper_") {
+                if master.as_ref().is_none_or(|master| master.name() != child) {
+                    children.push(child.to_string());
+                }
+            }
+        }
+        children.sort();
+        Ok(children)
+    }
+
+    /// Whether the interface is a WireGuard device, from the DEVTYPE of its uevent in sysfs
+    pub fn is_wireguard(&self) -> Result<bool> {
+        let path = format!("/sys/class/net/{}/uevent", self.name);
+        let uevent = fs::read_to_string(&path).map_err(|e| anyhow!("Failed to read {}: {}", path, e))?;
+        Ok(uevent.lines().any(|line| line == "DEVTYPE=wireguard"))
+    }
+
+    /// State of the bond, `None` when the interface is not a bond
+    pub fn bond(&self) -> Result<Option<BondInfo>> {
+        let mut ifreq = self.ifreq()?;
+        let mut info = ifbond::default();
+        set_data(&mut ifreq, &mut info);
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        match unsafe { get_bond_info(sock.as_raw_fd(), &mut ifreq) } {
+            Ok(_) => {}
+            Err(Errno::EOPNOTSUPP) => return Ok(None),
+            Err(e) => bail!("Failed to get bond state of interface '{}': {}", self.name, e),
+        }
+
+        let mut slaves = Vec::new();
+        for id in 0..info.num_slaves {
+            let mut slave = ifslave {
+                slave_id: id,
+                ..Default::default()
+            };
+            set_data(&mut ifreq, &mut slave);
+            unsafe { get_bond_slave_info(sock.as_raw_fd(), &mut ifreq) }
+                .map_err(|e| anyhow!("Failed to get slave {} of bond '{}': {}", id, self.name, e))?;
+            slaves.push(BondSlave {
+                name: get_slave_name(&slave),
+                link_up: is_slave_up(&slave),
+                active: is_slave_active(&slave),
+                link_failures: slave.link_failure_count,
+            });
+        }
+
+        let mode = BondMode::from(info.bond_mode as u8);
+        Ok(Some(BondInfo {
+            mode,
+            miimon: info.miimon as u32,
+            active_slave: slaves
+                .iter()
+                .find(|slave| mode.has_active_slave() && slave.active)
+                .map(|slave| slave.name.clone()),
+            slaves,
+        }))
+    }
+
+    /// Enslave `slave` to the interface, a bond or a bridge
+    ///
+    /// Bonds refuse slaves which are up, `slave` is then brought down first
+    /// and back up by the bond.
+    pub fn enslave(&self, slave: &Interface) -> Result<()> {
+        let mut ifreq = self.ifreq()?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        let res = if self.bond()?.is_some() {
+            slave.down()?;
+            set_slave(&mut ifreq, &slave.name)?;
+            unsafe { enslave_bond(sock.as_raw_fd(), &ifreq) }
+        } else {
+            set_index(&mut ifreq, slave.index()?);
+            unsafe { add_bridge_port(sock.as_raw_fd(), &ifreq) }
+        };
+        res.map_err(|e| anyhow!("Failed to enslave '{}' to '{}': {}", slave.name, self.name, e))?;
+        Ok(())
+    }
+
+    /// Release `slave` from the interface, a bond or a bridge
+    pub fn release(&self, slave: &Interface) -> Result<()> {
+        let mut ifreq = self.ifreq()?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        let res = if self.bond()?.is_some() {
+            set_slave(&mut ifreq, &slave.name)?;
+            unsafe { release_bond(sock.as_raw_fd(), &ifreq) }
+        } else {
+            set_index(&mut ifreq, slave.index()?);
+            unsafe { del_bridge_port(sock.as_raw_fd(), &ifreq) }
+        };
+        res.map_err(|e| anyhow!("Failed to release '{}' from '{}': {}", slave.name, self.name, e))?;
+        Ok(())
+    }
+
+    /// Primary IPv4 address of the interface
+    pub fn address(&self) -> Result<IpAddr> {
+        let mut ifreq = self.ifreq()?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        match unsafe { get_interface_ip(sock.as_raw_fd(), &mut ifreq) } {
+            Ok(_) => get_ip(&ifreq),
+            Err(Errno::EADDRNOTAVAIL) => bail!("Interface '{}' has no IPv4 address assigned", self.name),
+            Err(e) => bail!("Failed to get ip address of interface '{}': {}", self.name, e),
+        }
+    }
+
+    /// Netmask of the primary IPv4 address of the interface
+    pub fn netmask(&self) -> Result<Ipv4Addr> {
+        let mut ifreq = self.ifreq()?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        match unsafe { get_interface_netmask(sock.as_raw_fd(), &mut ifreq) } {
+            Ok(_) => match get_netmask(&ifreq)? {
+                IpAddr::V4(netmask) => Ok(netmask),
+                IpAddr::V6(_) => bail!("Received an IPv6 netmask"),
+            },
+            Err(Errno::EADDRNOTAVAIL) => bail!("Interface '{}' has no IPv4 address assigned", self.name),
+            Err(e) => bail!("Failed to get netmask of interface '{}': {}", self.name, e),
+        }
+    }
+
+    /// Set the netmask of the primary IPv4 address of the interface
+    pub fn set_netmask(&self, netmask: &Ipv4Addr) -> Result<()> {
+        let mut ifreq = self.ifreq()?;
+        set_netmask(&mut ifreq, &IpAddr::from(*netmask))?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        unsafe { set_interface_netmask(sock.as_raw_fd(), &ifreq) }
+            .map_err(|e| anyhow!("Failed to set netmask of interface '{}': {}", self.name, e))?;
+        Ok(())
+    }
+
+    /// Broadcast address of the primary IPv4 address of the interface
+    pub fn broadcast(&self) -> Result<Ipv4Addr> {
+        let mut ifreq = self.ifreq()?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        match unsafe { get_interface_broadcast(sock.as_raw_fd(), &mut ifreq) } {
+            Ok(_) => match get_broadcast(&ifreq)? {
+                IpAddr::V4(broadcast) => Ok(broadcast),
+                IpAddr::V6(_) => bail!("Received an IPv6 broadcast address"),
+            },
+            Err(Errno::EADDRNOTAVAIL) => bail!("Interface '{}' has no IPv4 address assigned", self.name),
+            Err(e) => bail!("Failed to get broadcast address of interface '{}': {}", self.name, e),
+        }
+    }
+
+    /// Set the broadcast address of the primary IPv4 address of the interface
+    pub fn set_broadcast(&self, broadcast: &Ipv4Addr) -> Result<()> {
+        let mut ifreq = self.ifreq()?;
+        set_broadcast(&mut ifreq, &IpAddr::from(*broadcast))?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        unsafe { set_interface_broadcast(sock.as_raw_fd(), &ifreq) }
+            .map_err(|e| anyhow!("Failed to set broadcast address of interface '{}': {}", self.name, e))?;
+        Ok(())
+    }
+
+    /// Peer address of a point-to-point interface
+    ///
+    /// Interfaces which are not point-to-point report their own address.
+    pub fn destination(&self) -> Result<Ipv4Addr> {
+        let mut ifreq = self.ifreq()?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        match unsafe { get_interface_destination(sock.as_raw_fd(), &mut ifreq) } {
+            Ok(_) => match get_destination(&ifreq)? {
+                IpAddr::V4(destination) => Ok(destination),
+                IpAddr::V6(_) => bail!("Received an IPv6 destination address"),
+            },
+            Err(Errno::EADDRNOTAVAIL) => bail!("Interface '{}' has no IPv4 address assigned", self.name),
+            Err(e) => bail!("Failed to get destination address of interface '{}': {}", self.name, e),
+        }
+    }
+
+    /// Set the peer address of a point-to-point interface
+    pub fn set_destination(&self, destination: &Ipv4Addr) -> Result<()> {
+        let mut ifreq = self.ifreq()?;
+        set_destination(&mut ifreq, &IpAddr::from(*destination))?;
+        let sock = ControlSocket::shared(AddressFamily::Inet)?;
+        unsafe { set_interface_destination(sock.as_raw_fd(), &ifreq) }
+            .map_err(|e| anyhow!("Failed to set destination address of interface '{}': {}", self.name, e))?;
+        Ok(())
+    }
+
+    /// Set the IPv4 address of the interface, or add an IPv6 address to it
+    ///
+    /// IPv4 addresses without a prefix length keep the netmask the kernel
+    /// picks, otherwise the netmask and broadcast address are derived from it.
+    /// IPv6 addresses without a prefix length are added as /128.
+    pub fn set_address(&self, addr: &Cidr) -> Result<()> {
+        match addr.addr {
+            IpAddr::V4(_) => {
+                let mut ifreq = self.ifreq()?;
+                set_ip(&mut ifreq, &addr.addr)?;
+                let sock = ControlSocket::shared(AddressFamily::Inet)?;
+                unsafe { set_interface_ip(sock.as_raw_fd(), &ifreq)? };
+
+                // Setting the address resets the netmask, so it is applied after
+                if let (IpAddr::V4(ip), Some(prefix_len)) = (addr.addr, addr.prefix_len) {
+                    self.set_netmask(&netmask_from_prefix_len(prefix_len)?)?;
+                    if let Some(broadcast) = broadcast_from_prefix_len(&ip, prefix_len)? {
+                        self.set_broadcast(&broadcast)?;
+                    }
+                }
+            }
+            IpAddr::V6(_) => {
+                let prefix_len = addr.prefix_len.unwrap_or(128);
+                let in6_ifreq = in6_ifreq_from_ip(self.index()?, &addr.addr, prefix_len)?;
+                let sock = ControlSocket::shared(AddressFamily::Inet6)?;
+                unsafe { set_interface_ip6(sock.as_raw_fd(), &in6_ifreq)? };
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove an address from the interface
+    ///
+    /// IPv4 addresses are removed by setting the address of their label,
+    /// the interface itself or one of its aliases, to 0.0.0.0.
+    pub fn remove_address(&self, addr: &Cidr) -> Result<()> {
+        match addr.addr {
+            IpAddr::V4(ip) => {
+                let label = ipv4_addresses()?
+                    .into_iter()
+                    .find(|(label, addr)| *addr == ip && label.split(':').next() == Some(self.name.as_str()))
+                    .map(|(label, _)| label)
+                    .ok_or_else(|| anyhow!("Interface '{}' has no address '{}'", self.name, ip))?;
+                let mut ifreq = ifreq::from_name(&label)?;
+                set_ip(&mut ifreq, &IpAddr::from(Ipv4Addr::UNSPECIFIED))?;
+                let sock = ControlSocket::shared(AddressFamily::Inet)?;
+                unsafe { set_interface_ip(sock.as_raw_fd(), &ifreq) }
+                    .map_err(|e| anyhow!("Failed to remove address '{}' of interface '{}': {}", ip, label, e))?;
+            }
+            IpAddr::V6(_) => {
+                let prefix_len = addr.prefix_len.unwrap_or(128);
+                let in6_ifreq = in6_ifreq_from_ip(self.index()?, &addr.addr, prefix_len)?;
+                let sock = ControlSocket::shared(AddressFamily::Inet6)?;
+                unsafe { del_interface_ip6(sock.as_raw_fd(), &in6_ifreq)? };
+            }
+        }
+        Ok(())
+    }
+}
+
+/// IPv4 addresses of all interfaces along with their label, through SIOCGIFCONF
+pub(crate) fn ipv4_addresses() -> Result<Vec<(String, Ipv4Addr)>> {
+    let sock = ControlSocket::shared(AddressFamily::Inet)?;
+    let mut capacity = 16;
+    loop {
+        let mut ifreqs: Vec<ifreq> = vec![unsafe { mem::zeroed() }; capacity];
+        let mut ifconf = ifconf {
+            ifc_len: (capacity * mem::size_of::<ifreq>()) as libc::c_int,
+            ifc_req: ifreqs.as_mut_ptr(),
+        };
+        unsafe { get_interface_conf(sock.as_raw_fd(), &mut ifconf) }
+            .map_err(|e| anyhow!("Failed to list interface addresses: {}", e))?;
+
+        // A full buffer may have been truncated, retry with a larger one
+        let count = ifconf.ifc_len as usize / mem::size_of::<ifreq>();
+        if count == capacity {
+            capacity *= 2;
+            continue;
+        }
+
+        let mut addresses = Vec::new();
+        for ifreq in &ifreqs[..count] {
+            if let IpAddr::V4(addr) = get_ip(ifreq)? {
+                addresses.push((get_name(ifreq), addr));
+            }
+        }
+        return Ok(addresses);
+    }
+}
+
+/// IPv6 addresses of all interfaces along with their index, from procfs
+///
+/// Each line of /proc/net/if_inet6 holds the address, interface index,
+/// prefix length, scope, flags and interface name, all but the name in hex.
+pub(crate) fn ipv6_addresses() -> Result<Vec<(u32, Address)>> {
+    let content = match fs::read_to_string("/proc/net/if_inet6") {
+        Ok(content) => content,
+        // IPv6 is disabled
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => bail!("Failed to read /proc/net/if_inet6: {}", e),
+    };
+
+    let mut addresses = Vec::new();
+    for line in content.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 5 || fields[0].len() != 32 {
+            bail!("Unexpected line in /proc/net/if_inet6: '{}'", line);
+        }
+        let addr = u128::from_str_radix(fields[0], 16)?;
+        let ifindex = u32::from_str_radix(fields[1], 16)?;
+        let prefix_len = u8::from_str_radix(fields[2], 16)?;
+        let mut address = Address::new(Cidr {
+            addr: IpAddr::from(Ipv6Addr::from(addr)),
+            prefix_len: Some(prefix_len),
+        });
+        // IPv6 scopes (IPV6_ADDR_*), not the RT_SCOPE_* of netlink
+        address.scope = match u8::from_str_radix(fields[3], 16)? {
+            0x10 => Scope::Host,
+            0x20 => Scope::Link,
+            0x40 => Scope::Site,
+            _ => Scope::Universe,
+        };
+        address.flags = AddressFlags::from_bits_truncate(u32::from_str_radix(fields[4], 16)?);
+        addresses.push((ifindex, address));
+    }
+    Ok(addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mac::{LinkType, MacAddr};
+
+    #[test]
+    fn serialize_info() {
+        let info = InterfaceInfo {
+            name: "lo".to_string(),
+            index: 1,
+            flags: InterfaceFlags::UP | InterfaceFlags::LOOPBACK | InterfaceFlags::RUNNING,
+            mtu: 65536,
+            hw_address: HwAddress {
+                link_type: LinkType::Loopback,
+                mac: Some(MacAddr::new([0; 6])),
+            },
+            addresses: vec!["127.0.0.1/8".parse().unwrap(), "::1/128".parse().unwrap()],
+            bond: None,
+            children: Vec::new(),
+        };
+        assert_eq!(
+            serde_json::to_value(&info).unwrap(),
+            serde_json::json!({
+                "name": "lo",
+                "index": 1,
+                "flags": ["UP", "LOOPBACK", "RUNNING"],
+                "mtu": 65536,
+                "hw_address": {"link_type": "loopback", "mac": "00:00:00:00:00:00"},
+                "addresses": [
+                    {"addr": "127.0.0.1", "prefix_len": 8},
+                    {"addr": "::1", "prefix_len": 128}
+                ],
+                "bond": null,
+                "children": []
+            })
+        );
+    }
+}
@@ -0,0 +1,557 @@
+//! Raw netdevice ioctls and helpers to fill the structures they pass
+use crate::addr::{ip_from_sockaddr, netmask_from_prefix_len, sockaddr_from_ip, sockaddr_in6_from_ip};
+use crate::flags::InterfaceFlags;
+use crate::mac::{HwAddress, LinkType, MacAddr};
+use crate::route::{Route, RouteType};
+use crate::tunnel::TunnelOptions;
+use anyhow::{bail, Result};
+use ifstructs::ifreq;
+use nix::libc::{
+    SIOCADDRT, SIOCDARP, SIOCDELRT, SIOCDIFADDR, SIOCGARP, SIOCGIFADDR, SIOCGIFBRDADDR, SIOCGIFCONF, SIOCGIFDSTADDR, SIOCGIFFLAGS,
+    SIOCGIFHWADDR, SIOCGIFINDEX, SIOCGIFMTU, SIOCGIFNETMASK, SIOCSARP, SIOCSIFADDR, SIOCSIFBRDADDR,
+    SIOCSIFDSTADDR, SIOCSIFFLAGS, SIOCSIFHWADDR, SIOCSIFMTU, SIOCSIFNAME, SIOCSIFNETMASK, TUNSETGROUP, TUNSETIFF,
+    TUNSETOWNER, TUNSETPERSIST,
+};
+use nix::{ioctl_read_bad, ioctl_readwrite_bad, ioctl_write_int_bad, ioctl_write_ptr_bad};
+use std::ffi::CStr;
+use std::net::IpAddr;
+
+/// Buffer of ifreqs filled by SIOCGIFCONF, see netdevice(7)
+#[repr(C)]
+pub struct ifconf {
+    pub ifc_len: libc::c_int,
+    pub ifc_req: *mut ifreq,
+}
+
+// arp_flags of an arpreq
+const ATF_COM: libc::c_int = 0x02;
+const ATF_PERM: libc::c_int = 0x04;
+
+/// IPv4 route passed to SIOCADDRT and SIOCDELRT, see route(8)
+///
+/// Declared as in linux/route.h, glibc's version differs in its padding.
+#[repr(C)]
+pub struct rtentry {
+    pub rt_pad1: libc::c_ulong,
+    pub rt_dst: libc::sockaddr,
+    pub rt_gateway: libc::sockaddr,
+    pub rt_genmask: libc::sockaddr,
+    pub rt_flags: libc::c_ushort,
+    pub rt_pad2: libc::c_short,
+    pub rt_pad3: libc::c_ulong,
+    pub rt_pad4: *mut libc::c_void,
+    pub rt_metric: libc::c_short,
+    pub rt_dev: *const libc::c_char,
+    pub rt_mtu: libc::c_ulong,
+    pub rt_window: libc::c_ulong,
+    pub rt_irtt: libc::c_ushort,
+}
+
+/// IPv6 route passed to SIOCADDRT and SIOCDELRT on an AF_INET6 socket
+#[repr(C)]
+pub struct in6_rtmsg {
+    pub rtmsg_dst: libc::in6_addr,
+    pub rtmsg_src: libc::in6_addr,
+    pub rtmsg_gateway: libc::in6_addr,
+    pub rtmsg_type: u32,
+    pub rtmsg_dst_len: u16,
+    pub rtmsg_src_len: u16,
+    pub rtmsg_metric: u32,
+    pub rtmsg_info: libc::c_ulong,
+    pub rtmsg_flags: u32,
+    pub rtmsg_ifindex: libc::c_int,
+}
+
+// Requests of the bonding, bridge and 8021q modules, missing from libc
+const SIOCSIFVLAN: libc::c_ulong = 0x8983;
+const SIOCBONDENSLAVE: libc::c_ulong = 0x8990;
+const SIOCBONDRELEASE: libc::c_ulong = 0x8991;
+const SIOCBONDSLAVEINFOQUERY: libc::c_ulong = 0x8993;
+const SIOCBONDINFOQUERY: libc::c_ulong = 0x8994;
+const SIOCBRADDBR: libc::c_ulong = 0x89a0;
+const SIOCBRDELBR: libc::c_ulong = 0x89a1;
+const SIOCBRADDIF: libc::c_ulong = 0x89a2;
+const SIOCBRDELIF: libc::c_ulong = 0x89a3;
+
+// Private requests of the ip_gre, ipip and sit modules, from SIOCDEVPRIVATE on
+const SIOCADDTUNNEL: libc::c_ulong = 0x89f1;
+const SIOCDELTUNNEL: libc::c_ulong = 0x89f2;
+const SIOCCHGTUNNEL: libc::c_ulong = 0x89f3;
+
+// i_flags and o_flags of an ip_tunnel_parm, big endian
+const GRE_KEY: u16 = 0x2000;
+
+// frag_off of an iphdr, big endian
+const IP_DF: u16 = 0x4000;
+
+// cmd of a vlan_ioctl_args
+pub const ADD_VLAN_CMD: libc::c_int = 0;
+pub const DEL_VLAN_CMD: libc::c_int = 1;
+
+// link and state of an ifslave
+const BOND_LINK_UP: i8 = 0;
+const BOND_STATE_ACTIVE: i8 = 0;
+
+/// Bond state filled by SIOCBONDINFOQUERY through ifr_data, see linux/if_bonding.h
+#[repr(C)]
+#[derive(Default)]
+pub struct ifbond {
+    pub bond_mode: i32,
+    pub num_slaves: i32,
+    pub miimon: i32,
+}
+
+/// Slave state filled by SIOCBONDSLAVEINFOQUERY through ifr_data, `slave_id` being set by the caller
+#[repr(C)]
+#[derive(Default)]
+pub struct ifslave {
+    pub slave_id: i32,
+    pub slave_name: [libc::c_char; libc::IFNAMSIZ],
+    pub link: i8,
+    pub state: i8,
+    pub link_failure_count: u32,
+}
+
+/// Request passed to SIOCSIFVLAN, see linux/if_vlan.h
+#[repr(C)]
+pub struct vlan_ioctl_args {
+    pub cmd: libc::c_int,
+    pub device1: [libc::c_char; 24],
+    pub u: vlan_ioctl_args_u,
+    pub vlan_qos: libc::c_short,
+}
+
+#[repr(C)]
+pub union vlan_ioctl_args_u {
+    pub device2: [libc::c_char; 24],
+    pub vid: libc::c_int,
+}
+
+/// IPv4 header, the first byte holding both the version and the header length, see linux/ip.h
+#[repr(C)]
+#[derive(Default)]
+pub struct iphdr {
+    pub version_ihl: u8,
+    pub tos: u8,
+    pub tot_len: u16,
+    pub id: u16,
+    pub frag_off: u16,
+    pub ttl: u8,
+    pub protocol: u8,
+    pub check: u16,
+    pub saddr: u32,
+    pub daddr: u32,
+}
+
+/// Tunnel passed to SIOC*TUNNEL through ifr_data, see linux/if_tunnel.h
+#[repr(C)]
+#[derive(Default)]
+pub struct ip_tunnel_parm {
+    pub name: [u8; libc::IFNAMSIZ],
+    pub link: libc::c_int,
+    pub i_flags: u16,
+    pub o_flags: u16,
+    pub i_key: u32,
+    pub o_key: u32,
+    pub iph: iphdr,
+}
+
+// Creation of icotl functions needed
+ioctl_read_bad!(get_interface_ip, SIOCGIFADDR, ifreq);
+ioctl_write_ptr_bad!(set_interface_ip, SIOCSIFADDR, ifreq);
+ioctl_read_bad!(get_interface_index, SIOCGIFINDEX, ifreq);
+ioctl_read_bad!(get_interface_flags, SIOCGIFFLAGS, ifreq);
+ioctl_write_ptr_bad!(set_interface_flags, SIOCSIFFLAGS, ifreq);
+ioctl_read_bad!(get_interface_mtu, SIOCGIFMTU, ifreq);
+ioctl_write_ptr_bad!(set_interface_mtu, SIOCSIFMTU, ifreq);
+ioctl_read_bad!(get_interface_hwaddr, SIOCGIFHWADDR, ifreq);
+ioctl_write_ptr_bad!(set_interface_hwaddr, SIOCSIFHWADDR, ifreq);
+ioctl_readwrite_bad!(get_interface_conf, SIOCGIFCONF, ifconf);
+ioctl_read_bad!(get_interface_netmask, SIOCGIFNETMASK, ifreq);
+ioctl_write_ptr_bad!(set_interface_netmask, SIOCSIFNETMASK, ifreq);
+ioctl_read_bad!(get_interface_broadcast, SIOCGIFBRDADDR, ifreq);
+ioctl_write_ptr_bad!(set_interface_broadcast, SIOCSIFBRDADDR, ifreq);
+ioctl_read_bad!(get_interface_destination, SIOCGIFDSTADDR, ifreq);
+ioctl_write_ptr_bad!(set_interface_destination, SIOCSIFDSTADDR, ifreq);
+// IPv6 addresses are added and removed through an AF_INET6 socket
+ioctl_write_ptr_bad!(set_interface_ip6, SIOCSIFADDR, libc::in6_ifreq);
+ioctl_write_ptr_bad!(del_interface_ip6, SIOCDIFADDR, libc::in6_ifreq);
+ioctl_write_ptr_bad!(add_route, SIOCADDRT, rtentry);
+ioctl_write_ptr_bad!(del_route, SIOCDELRT, rtentry);
+ioctl_write_ptr_bad!(add_route6, SIOCADDRT, in6_rtmsg);
+ioctl_write_ptr_bad!(del_route6, SIOCDELRT, in6_rtmsg);
+ioctl_write_ptr_bad!(set_arp, SIOCSARP, libc::arpreq);
+ioctl_read_bad!(get_arp, SIOCGARP, libc::arpreq);
+ioctl_write_ptr_bad!(del_arp, SIOCDARP, libc::arpreq);
+ioctl_write_ptr_bad!(set_interface_name, SIOCSIFNAME, ifreq);
+// Bridges are created and deleted by name
+ioctl_write_ptr_bad!(add_bridge, SIOCBRADDBR, libc::c_char);
+ioctl_write_ptr_bad!(del_bridge, SIOCBRDELBR, libc::c_char);
+// Ports are named by ifr_ifindex, the bridge by ifr_name
+ioctl_write_ptr_bad!(add_bridge_port, SIOCBRADDIF, ifreq);
+ioctl_write_ptr_bad!(del_bridge_port, SIOCBRDELIF, ifreq);
+ioctl_write_ptr_bad!(set_vlan, SIOCSIFVLAN, vlan_ioctl_args);
+// Bonds are named by ifr_name and their slave by ifr_slave
+ioctl_write_ptr_bad!(enslave_bond, SIOCBONDENSLAVE, ifreq);
+ioctl_write_ptr_bad!(release_bond, SIOCBONDRELEASE, ifreq);
+ioctl_read_bad!(get_bond_info, SIOCBONDINFOQUERY, ifreq);
+ioctl_read_bad!(get_bond_slave_info, SIOCBONDSLAVEINFOQUERY, ifreq);
+// on gre0, tunl0 or sit0 to add a tunnel, on the tunnel itself otherwise
+ioctl_write_ptr_bad!(add_ip_tunnel, SIOCADDTUNNEL, ifreq);
+ioctl_write_ptr_bad!(del_ip_tunnel, SIOCDELTUNNEL, ifreq);
+ioctl_write_ptr_bad!(change_ip_tunnel, SIOCCHGTUNNEL, ifreq);
+// on a /dev/net/tun file
+ioctl_write_ptr_bad!(set_tun_iff, TUNSETIFF, ifreq);
+ioctl_write_int_bad!(set_tun_persist, TUNSETPERSIST);
+ioctl_write_int_bad!(set_tun_owner, TUNSETOWNER);
+ioctl_write_int_bad!(set_tun_group, TUNSETGROUP);
+
+// get the name of interface, which is the label of an address in SIOCGIFCONF results
+pub fn get_name(ifr: &ifreq) -> String {
+    // ifr_name is a NUL padded array of IFNAMSIZ bytes
+    let name = unsafe { std::slice::from_raw_parts(ifr.ifr_name.as_ptr().cast::<u8>(), libc::IFNAMSIZ) };
+    let len = name.iter().position(|byte| *byte == 0).unwrap_or(name.len());
+    String::from_utf8_lossy(&name[..len]).into_owned()
+}
+
+// get the ip of interface
+pub fn get_ip(ifr: &ifreq) -> Result<IpAddr> {
+    ip_from_sockaddr(unsafe { &ifr.ifr_ifru.ifr_addr })
+}
+
+// set the ip of interface
+pub fn set_ip(ifr: &mut ifreq, ip_addr: &IpAddr) -> Result<()> {
+    ifr.ifr_ifru.ifr_addr = sockaddr_from_ip(ip_addr)?;
+    Ok(())
+}
+
+// get the netmask of interface
+pub fn get_netmask(ifr: &ifreq) -> Result<IpAddr> {
+    ip_from_sockaddr(unsafe { &ifr.ifr_ifru.ifr_netmask })
+}
+
+// set the netmask of interface
+pub fn set_netmask(ifr: &mut ifreq, netmask: &IpAddr) -> Result<()> {
+    ifr.ifr_ifru.ifr_netmask = sockaddr_from_ip(netmask)?;
+    Ok(())
+}
+
+// get the broadcast address of interface
+pub fn get_broadcast(ifr: &ifreq) -> Result<IpAddr> {
+    ip_from_sockaddr(unsafe { &ifr.ifr_ifru.ifr_broadaddr })
+}
+
+// set the broadcast address of interface
+pub fn set_broadcast(ifr: &mut ifreq, broadcast: &IpAddr) -> Result<()> {
+    ifr.ifr_ifru.ifr_broadaddr = sockaddr_from_ip(broadcast)?;
+    Ok(())
+}
+
+// get the point-to-point destination address of interface
+pub fn get_destination(ifr: &ifreq) -> Result<IpAddr> {
+    ip_from_sockaddr(unsafe { &ifr.ifr_ifru.ifr_dstaddr })
+}
+
+// set the point-to-point destination address of interface
+pub fn set_destination(ifr: &mut ifreq, destination: &IpAddr) -> Result<()> {
+    ifr.ifr_ifru.ifr_dstaddr = sockaddr_from_ip(destination)?;
+    Ok(())
+}
+
+// get the flags of interface
+pub fn get_flags(ifr: &ifreq) -> InterfaceFlags {
+    // ifr_flags is a short, the extended flags above it are not reported here
+    InterfaceFlags::from_bits_truncate(unsafe { ifr.ifr_ifru.ifr_flags } as u16 as libc::c_int)
+}
+
+// set the flags of interface
+pub fn set_flags(ifr: &mut ifreq, flags: InterfaceFlags) {
+    ifr.ifr_ifru.ifr_flags = flags.bits() as libc::c_short;
+}
+
+// get the mtu of interface
+pub fn get_mtu(ifr: &ifreq) -> u32 {
+    unsafe { ifr.ifr_ifru.ifr_mtu as u32 }
+}
+
+// set the mtu of interface
+pub fn set_mtu(ifr: &mut ifreq, mtu: u32) {
+    ifr.ifr_ifru.ifr_mtu = mtu as libc::c_int;
+}
+
+// get the hardware address of interface
+pub fn get_hwaddr(ifr: &ifreq) -> HwAddress {
+    let hwaddr = unsafe { &ifr.ifr_ifru.ifr_hwaddr };
+    let link_type = LinkType::from(hwaddr.sa_family);
+    let mut octets = [0u8; 6];
+    for (byte, data) in octets.iter_mut().zip(&hwaddr.sa_data) {
+        *byte = *data as u8;
+    }
+    HwAddress {
+        link_type,
+        mac: if link_type.has_mac() { Some(MacAddr::from(octets)) } else { None },
+    }
+}
+
+// set the hardware address of interface
+pub fn set_hwaddr(ifr: &mut ifreq, link_type: LinkType, mac: &MacAddr) {
+    let mut sa_data = [0; 14];
+    for (data, byte) in sa_data.iter_mut().zip(&mac.octets()) {
+        *data = *byte as _;
+    }
+    ifr.ifr_ifru.ifr_hwaddr = libc::sockaddr {
+        sa_family: u16::from(link_type),
+        sa_data,
+    };
+}
+
+// encode name as the NUL padded array of an ifreq
+fn ifname(name: &str) -> Result<[u8; libc::IFNAMSIZ]> {
+    if name.len() >= libc::IFNAMSIZ {
+        bail!("Interface name '{}' is too long", name);
+    }
+    let mut ifname = [0; libc::IFNAMSIZ];
+    ifname[..name.len()].copy_from_slice(name.as_bytes());
+    Ok(ifname)
+}
+
+// set the name interface is renamed to
+pub fn set_newname(ifr: &mut ifreq, name: &str) -> Result<()> {
+    ifr.ifr_ifru.ifr_newname = ifname(name)?;
+    Ok(())
+}
+
+// set the slave a bond ioctl applies to
+pub fn set_slave(ifr: &mut ifreq, name: &str) -> Result<()> {
+    ifr.ifr_ifru.ifr_slave = ifname(name)?;
+    Ok(())
+}
+
+// set the IFF_TUN or IFF_TAP flags of a TUNSETIFF, along with the other IFF_* flags of /dev/net/tun
+pub fn set_tun_flags(ifr: &mut ifreq, flags: libc::c_int) {
+    ifr.ifr_ifru.ifr_flags = flags as libc::c_short;
+}
+
+// point ifr_data at the structure a private ioctl fills
+pub fn set_data<T>(ifr: &mut ifreq, data: &mut T) {
+    ifr.ifr_ifru.ifr_data = data as *mut T as *mut libc::c_char;
+}
+
+// get the name of a bond slave
+pub fn get_slave_name(slave: &ifslave) -> String {
+    let name = unsafe { CStr::from_ptr(slave.slave_name.as_ptr()) };
+    name.to_string_lossy().into_owned()
+}
+
+// whether the link of a bond slave is up
+pub fn is_slave_up(slave: &ifslave) -> bool {
+    slave.link == BOND_LINK_UP
+}
+
+// whether a bond slave is active rather than a backup
+pub fn is_slave_active(slave: &ifslave) -> bool {
+    slave.state == BOND_STATE_ACTIVE
+}
+
+// get the index of interface
+pub fn get_index(ifr: &ifreq) -> i32 {
+    unsafe { ifr.ifr_ifru.ifr_ifindex }
+}
+
+// set the index of the port a bridge ioctl applies to
+pub fn set_index(ifr: &mut ifreq, index: i32) {
+    ifr.ifr_ifru.ifr_ifindex = index;
+}
+
+// build the in6_ifreq used to add or remove an IPv6 address of interface
+pub fn in6_ifreq_from_ip(ifindex: i32, ip_addr: &IpAddr, prefix_len: u8) -> Result<libc::in6_ifreq> {
+    Ok(libc::in6_ifreq {
+        ifr6_addr: sockaddr_in6_from_ip(ip_addr)?.sin6_addr,
+        ifr6_prefixlen: prefix_len as u32,
+        ifr6_ifindex: ifindex,
+    })
+}
+
+// build the rtentry used to add or remove an IPv4 route, going out of `dev` when given
+pub fn rtentry_from_route(route: &Route, dev: Option<&CStr>) -> Result<rtentry> {
+    let prefix_len = route.prefix_len();
+    let mut flags = libc::RTF_UP;
+    if prefix_len == 32 {
+        flags |= libc::RTF_HOST;
+    }
+    match route.kind {
+        RouteType::Unicast => {}
+        // The kernel turns rejecting routes into unreachable ones
+        RouteType::Unreachable => flags |= libc::RTF_REJECT,
+        kind => bail!("{} routes can't be managed through ioctls", kind),
+    }
+    let gateway = match route.gateway {
+        Some(gateway) => {
+            flags |= libc::RTF_GATEWAY;
+            sockaddr_from_ip(&gateway)?
+        }
+        None => sockaddr_from_ip(&IpAddr::from([0u8; 4]))?,
+    };
+    let metric = match route.metric {
+        // The kernel stores the metric minus one, zero leaving it unset
+        Some(metric) if metric < i16::MAX as u32 => metric as libc::c_short + 1,
+        Some(metric) => bail!("Metric {} is too large for ioctls", metric),
+        None => 0,
+    };
+
+    Ok(rtentry {
+        rt_pad1: 0,
+        rt_dst: sockaddr_from_ip(&route.destination.addr)?,
+        rt_gateway: gateway,
+        rt_genmask: sockaddr_from_ip(&IpAddr::from(netmask_from_prefix_len(prefix_len)?))?,
+        rt_flags: flags,
+        rt_pad2: 0,
+        rt_pad3: 0,
+        rt_pad4: std::ptr::null_mut(),
+        rt_metric: metric,
+        rt_dev: dev.map_or(std::ptr::null(), CStr::as_ptr),
+        rt_mtu: 0,
+        rt_window: 0,
+        rt_irtt: 0,
+    })
+}
+
+// build the in6_rtmsg used to add or remove an IPv6 route, going out of `ifindex` when not 0
+pub fn in6_rtmsg_from_route(route: &Route, ifindex: i32) -> Result<in6_rtmsg> {
+    let mut flags = libc::RTF_UP as u32;
+    if route.prefix_len() == 128 {
+        flags |= libc::RTF_HOST as u32;
+    }
+    match route.kind {
+        RouteType::Unicast => {}
+        // The kind of rejecting route is taken from rtmsg_type
+        RouteType::Blackhole | RouteType::Unreachable | RouteType::Prohibit | RouteType::Throw => {
+            flags |= libc::RTF_REJECT as u32
+        }
+        kind => bail!("{} routes can't be managed through ioctls", kind),
+    }
+    let gateway = match route.gateway {
+        Some(gateway) => {
+            flags |= libc::RTF_GATEWAY as u32;
+            sockaddr_in6_from_ip(&gateway)?.sin6_addr
+        }
+        None => sockaddr_in6_from_ip(&IpAddr::from([0u8; 16]))?.sin6_addr,
+    };
+
+    Ok(in6_rtmsg {
+        rtmsg_dst: sockaddr_in6_from_ip(&route.destination.addr)?.sin6_addr,
+        rtmsg_src: sockaddr_in6_from_ip(&IpAddr::from([0u8; 16]))?.sin6_addr,
+        rtmsg_gateway: gateway,
+        rtmsg_type: u8::from(route.kind) as u32,
+        rtmsg_dst_len: route.prefix_len() as u16,
+        rtmsg_src_len: 0,
+        rtmsg_metric: route.metric.unwrap_or(0),
+        rtmsg_info: 0,
+        rtmsg_flags: flags,
+        rtmsg_ifindex: ifindex,
+    })
+}
+
+// build the arpreq of the IPv4 neighbour `ip_addr` on the interface `dev`
+pub fn arpreq_from_ip(ip_addr: &IpAddr, dev: &str) -> Result<libc::arpreq> {
+    let mut arp_dev = [0 as libc::c_char; 16];
+    if dev.len() >= arp_dev.len() {
+        bail!("Interface name '{}' is too long", dev);
+    }
+    for (byte, data) in arp_dev.iter_mut().zip(dev.as_bytes()) {
+        *byte = *data as libc::c_char;
+    }
+    Ok(libc::arpreq {
+        arp_pa: sockaddr_from_ip(ip_addr)?,
+        arp_ha: libc::sockaddr {
+            sa_family: 0,
+            sa_data: [0; 14],
+        },
+        arp_flags: 0,
+        arp_netmask: libc::sockaddr {
+            sa_family: 0,
+            sa_data: [0; 14],
+        },
+        arp_dev,
+    })
+}
+
+// get the hardware address of a neighbour, unset while unresolved
+pub fn get_arp_hwaddr(arp: &libc::arpreq) -> Option<MacAddr> {
+    if arp.arp_flags & ATF_COM == 0 {
+        return None;
+    }
+    let mut octets = [0u8; 6];
+    for (byte, data) in octets.iter_mut().zip(&arp.arp_ha.sa_data) {
+        *byte = *data as u8;
+    }
+    Some(MacAddr::from(octets))
+}
+
+// set the ethernet address of a neighbour, which never expires when `permanent` is set
+pub fn set_arp_hwaddr(arp: &mut libc::arpreq, mac: &MacAddr, permanent: bool) {
+    let mut sa_data = [0; 14];
+    for (data, byte) in sa_data.iter_mut().zip(&mac.octets()) {
+        *data = *byte as _;
+    }
+    arp.arp_ha = libc::sockaddr {
+        sa_family: libc::ARPHRD_ETHER,
+        sa_data,
+    };
+    arp.arp_flags = if permanent { ATF_COM | ATF_PERM } else { ATF_COM };
+}
+
+// build the vlan_ioctl_args running `cmd` on `dev`, `vid` being the id of an added VLAN
+pub fn vlan_args(cmd: libc::c_int, dev: &str, vid: Option<u16>) -> Result<vlan_ioctl_args> {
+    let mut device1 = [0 as libc::c_char; 24];
+    if dev.len() >= libc::IFNAMSIZ {
+        bail!("Interface name '{}' is too long", dev);
+    }
+    for (byte, data) in device1.iter_mut().zip(dev.as_bytes()) {
+        *byte = *data as libc::c_char;
+    }
+    Ok(vlan_ioctl_args {
+        cmd,
+        device1,
+        u: vlan_ioctl_args_u {
+            vid: vid.unwrap_or(0) as libc::c_int,
+        },
+        vlan_qos: 0,
+    })
+}
+
+// build the ip_tunnel_parm of the IPv4 tunnel `name` carrying `protocol`, `link` being the index of its parent or 0
+pub fn tunnel_parm(name: &str, protocol: u8, options: &TunnelOptions, link: libc::c_int) -> Result<ip_tunnel_parm> {
+    // in_addr fields hold the octets in network order
+    let in_addr = |addr: &Option<IpAddr>| match addr {
+        Some(IpAddr::V4(addr)) => Ok(u32::from_ne_bytes(addr.octets())),
+        Some(IpAddr::V6(addr)) => bail!("IPv6 endpoint {} of the IPv4 tunnel '{}'", addr, name),
+        None => Ok(0),
+    };
+    let mut parm = ip_tunnel_parm {
+        name: ifname(name)?,
+        link,
+        iph: iphdr {
+            version_ihl: 0x45,
+            ttl: options.ttl.unwrap_or(0),
+            protocol,
+            saddr: in_addr(&options.local)?,
+            daddr: in_addr(&options.remote)?,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    if options.pmtudisc != Some(false) {
+        parm.iph.frag_off = IP_DF.to_be();
+    }
+    if let Some(key) = options.key {
+        if i32::from(protocol) != libc::IPPROTO_GRE {
+            bail!("Keys only apply to GRE tunnels");
+        }
+        parm.i_flags = GRE_KEY.to_be();
+        parm.o_flags = GRE_KEY.to_be();
+        parm.i_key = key.to_be();
+        parm.o_key = key.to_be();
+    }
+    Ok(parm)
+}
@@ -0,0 +1,54 @@
+//! Linux supports some standard ioctls to configure network devices.
+//! They can be used on any socket's file descriptor regardless of
+//! the family or type. Most of them pass an ifreq structure.
+//! Source: netdevice(7)
+//!
+//! This crate wraps those ioctls behind a safe [`Interface`] handle:
+//!
+//! ```no_run
+//! use network_config::{Cidr, Interface};
+//!
+//! let eth0 = Interface::new("eth0")?;
+//! eth0.set_address(&"10.1.2.3".parse::<Cidr>()?)?;
+//! println!("{}", eth0.address()?);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+pub mod addr;
+mod backend;
+mod bond;
+mod bridge;
+mod error;
+mod flags;
+mod interface;
+mod ioctl;
+mod link;
+mod mac;
+mod neigh;
+mod netlink;
+mod netns;
+mod overlay;
+mod route;
+mod rule;
+mod socket;
+mod tun;
+mod tunnel;
+mod wireguard;
+
+pub use addr::{Address, AddressFlags, Cidr, Family, Scope};
+pub use backend::{default_backend, Backend, IoctlBackend, NetlinkBackend};
+pub use bond::{BondInfo, BondMode, BondOptions, BondSlave, LacpRate, XmitHashPolicy};
+pub use bridge::{BridgeOptions, BridgeVlan, FdbEntry};
+pub use error::MtuError;
+pub use flags::InterfaceFlags;
+pub use interface::{Interface, InterfaceInfo, Ipv4Info};
+pub use link::{IpvlanMode, Link, LinkKind, MacvlanMode, VlanProtocol};
+pub use mac::{HwAddress, LinkType, MacAddr};
+pub use neigh::{Neighbour, NeighbourState};
+pub use netns::Netns;
+pub use overlay::{GeneveOptions, VxlanOptions};
+pub use route::{Route, RouteType, Table};
+pub use rule::{Rule, RuleAction};
+pub use socket::ControlSocket;
+pub use tun::TunOptions;
+pub use tunnel::TunnelOptions;
+pub use wireguard::{WireguardConfig, WireguardDevice, WireguardKey, WireguardPeer, WireguardPeerConfig};
@@ -0,0 +1,440 @@
+//! Virtual links, created and deleted rather than found on the machine
+use crate::bond::BondOptions;
+use crate::bridge::BridgeOptions;
+use crate::mac::MacAddr;
+use crate::overlay::{GeneveOptions, VxlanOptions};
+use crate::tun::TunOptions;
+use crate::tunnel::TunnelOptions;
+use anyhow::{bail, Result};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::str::FromStr;
+
+/// Tag protocol of a VLAN
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VlanProtocol {
+    /// 802.1Q, the usual customer tag
+    Ieee8021Q,
+    /// 802.1ad, the service tag of stacked VLANs
+    Ieee8021Ad,
+}
+
+impl VlanProtocol {
+    /// Ethertype of the tag
+    pub fn ethertype(self) -> u16 {
+        match self {
+            VlanProtocol::Ieee8021Q => 0x8100,
+            VlanProtocol::Ieee8021Ad => 0x88a8,
+        }
+    }
+}
+
+/// Formats as iproute2 does, `802.1Q` or `802.1ad`
+impl fmt::Display for VlanProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VlanProtocol::Ieee8021Q => write!(f, "802.1Q"),
+            VlanProtocol::Ieee8021Ad => write!(f, "802.1ad"),
+        }
+    }
+}
+
+/// Parses `802.1Q` or `802.1ad`, case insensitive
+impl FromStr for VlanProtocol {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "802.1q" => Ok(VlanProtocol::Ieee8021Q),
+            "802.1ad" => Ok(VlanProtocol::Ieee8021Ad),
+            _ => bail!("Unknown VLAN protocol '{}'", s),
+        }
+    }
+}
+
+/// Serializes as its iproute2 name, e.g. `"802.1Q"`
+impl Serialize for VlanProtocol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// How macvlan and macvtap links reach the other children of their parent (MACVLAN_MODE_*)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacvlanMode {
+    /// Every frame leaves through the parent, including those between children
+    Private,
+    /// Frames between children go through the parent and back through an 802.1Qbg capable switch
+    Vepa,
+    /// Frames between children are switched directly
+    Bridge,
+    /// Single child taking over the parent, e.g. to hand it to a virtual machine
+    Passthru,
+}
+
+impl From<MacvlanMode> for u32 {
+    fn from(mode: MacvlanMode) -> u32 {
+        match mode {
+            MacvlanMode::Private => 1,
+            MacvlanMode::Vepa => 2,
+            MacvlanMode::Bridge => 4,
+            MacvlanMode::Passthru => 8,
+        }
+    }
+}
+
+/// Formats as iproute2 does, e.g. `bridge`
+impl fmt::Display for MacvlanMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacvlanMode::Private => write!(f, "private"),
+            MacvlanMode::Vepa => write!(f, "vepa"),
+            MacvlanMode::Bridge => write!(f, "bridge"),
+            MacvlanMode::Passthru => write!(f, "passthru"),
+        }
+    }
+}
+
+impl FromStr for MacvlanMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "private" => Ok(MacvlanMode::Private),
+            "vepa" => Ok(MacvlanMode::Vepa),
+            "bridge" => Ok(MacvlanMode::Bridge),
+            "passthru" => Ok(MacvlanMode::Passthru),
+            _ => bail!("Unknown macvlan mode '{}'", s),
+        }
+    }
+}
+
+/// Layer at which ipvlan links share the MAC address of their parent (IPVLAN_MODE_*)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpvlanMode {
+    /// Frames are switched by MAC address, then by IP address
+    L2,
+    /// Packets are routed by IP address, without broadcasts nor multicasts
+    L3,
+    /// As L3, going through the netfilter hooks of the parent's namespace
+    L3s,
+}
+
+impl From<IpvlanMode> for u16 {
+    fn from(mode: IpvlanMode) -> u16 {
+        match mode {
+            IpvlanMode::L2 => 0,
+            IpvlanMode::L3 => 1,
+            IpvlanMode::L3s => 2,
+        }
+    }
+}
+
+/// Formats as iproute2 does, e.g. `l3s`
+impl fmt::Display for IpvlanMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpvlanMode::L2 => write!(f, "l2"),
+            IpvlanMode::L3 => write!(f, "l3"),
+            IpvlanMode::L3s => write!(f, "l3s"),
+        }
+    }
+}
+
+/// Parses `l2`, `l3` or `l3s`, case insensitive
+impl FromStr for IpvlanMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "l2" => Ok(IpvlanMode::L2),
+            "l3" => Ok(IpvlanMode::L3),
+            "l3s" => Ok(IpvlanMode::L3s),
+            _ => bail!("Unknown ipvlan mode '{}'", s),
+        }
+    }
+}
+
+/// Type of a virtual link along with what it needs to be created
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkKind {
+    /// Interface dropping whatever is sent through it
+    Dummy,
+    /// Pair of interfaces connected back to back, created and deleted together
+    Veth {
+        /// Name of the other end
+        peer: String,
+    },
+    /// Ethernet bridge, without ports when created
+    Bridge(BridgeOptions),
+    /// Bond aggregating the links enslaved to it
+    Bond(BondOptions),
+    /// VLAN tagging the packets of another interface
+    Vlan {
+        /// Interface carrying the tagged packets
+        parent: String,
+        id: u16,
+        protocol: VlanProtocol,
+    },
+    /// VXLAN overlay, see [`VxlanOptions`]
+    Vxlan(VxlanOptions),
+    /// GENEVE overlay, see [`GeneveOptions`]
+    Geneve(GeneveOptions),
+    /// Persistent TUN device, exchanging IP packets with the program attached to it
+    Tun(TunOptions),
+    /// Persistent TAP device, exchanging Ethernet frames with the program attached to it
+    Tap(TunOptions),
+    /// GRE tunnel carrying IPv4 and IPv6 packets over IPv4
+    Gre(TunnelOptions),
+    /// IPIP tunnel carrying IPv4 packets over IPv4
+    Ipip(TunnelOptions),
+    /// SIT tunnel carrying IPv6 packets over IPv4
+    Sit(TunnelOptions),
+    /// ip6tnl tunnel carrying IPv4 and IPv6 packets over IPv6
+    Ip6tnl(TunnelOptions),
+    /// Child of an Ethernet interface with a MAC address of its own
+    Macvlan { parent: String, mode: MacvlanMode },
+    /// Macvlan whose frames are also handed to a program through /dev/tapN
+    Macvtap { parent: String, mode: MacvlanMode },
+    /// Child of an Ethernet interface sharing its MAC address, told apart by IP address
+    Ipvlan { parent: String, mode: IpvlanMode },
+    /// WireGuard tunnel, its keys and peers being set once created
+    Wireguard,
+}
+
+impl LinkKind {
+    /// Options of the point-to-point tunnels, GRE, IPIP, SIT and ip6tnl
+    pub fn tunnel_options(&self) -> Option<&TunnelOptions> {
+        match self {
+            LinkKind::Gre(options) | LinkKind::Ipip(options) | LinkKind::Sit(options) | LinkKind::Ip6tnl(options) => {
+                Some(options)
+            }
+            _ => None,
+        }
+    }
+
+    /// Interface the link is stacked on, for VLANs, macvlans, macvtaps and ipvlans
+    pub fn parent(&self) -> Option<&str> {
+        match self {
+            LinkKind::Vlan { parent, .. }
+            | LinkKind::Macvlan { parent, .. }
+            | LinkKind::Macvtap { parent, .. }
+            | LinkKind::Ipvlan { parent, .. } => Some(parent),
+            _ => None,
+        }
+    }
+}
+
+/// Formats as the kind iproute2 and IFLA_INFO_KIND use, e.g. `veth`
+impl fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkKind::Dummy => write!(f, "dummy"),
+            LinkKind::Veth { .. } => write!(f, "veth"),
+            LinkKind::Bridge(_) => write!(f, "bridge"),
+            LinkKind::Bond(_) => write!(f, "bond"),
+            LinkKind::Vlan { .. } => write!(f, "vlan"),
+            LinkKind::Vxlan(_) => write!(f, "vxlan"),
+            LinkKind::Geneve(_) => write!(f, "geneve"),
+            LinkKind::Tun(_) => write!(f, "tun"),
+            LinkKind::Tap(_) => write!(f, "tap"),
+            LinkKind::Gre(_) => write!(f, "gre"),
+            LinkKind::Ipip(_) => write!(f, "ipip"),
+            LinkKind::Sit(_) => write!(f, "sit"),
+            LinkKind::Ip6tnl(_) => write!(f, "ip6tnl"),
+            LinkKind::Macvlan { .. } => write!(f, "macvlan"),
+            LinkKind::Macvtap { .. } => write!(f, "macvtap"),
+            LinkKind::Ipvlan { .. } => write!(f, "ipvlan"),
+            LinkKind::Wireguard => write!(f, "wireguard"),
+        }
+    }
+}
+
+/// Virtual link to create
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub name: String,
+    pub kind: LinkKind,
+    /// Left to the kernel when unset, usually 1500 or the MTU of the parent
+    pub mtu: Option<u32>,
+    /// Random when unset
+    pub mac: Option<MacAddr>,
+}
+
+impl Link {
+    pub fn new(name: &str, kind: LinkKind) -> Link {
+        Link {
+            name: name.to_string(),
+            kind,
+            mtu: None,
+            mac: None,
+        }
+    }
+}
+
+/// Formats as iproute2 does, e.g. `eth0.5 type vlan link eth0 id 5`
+impl fmt::Display for Link {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} type {}", self.name, self.kind)?;
+        match &self.kind {
+            LinkKind::Veth { peer } => write!(f, " peer {}", peer)?,
+            LinkKind::Bond(options) => write!(f, " {}", options)?,
+            LinkKind::Vlan { parent, id, protocol } => {
+                write!(f, " link {} id {}", parent, id)?;
+                if *protocol != VlanProtocol::Ieee8021Q {
+                    write!(f, " protocol {}", protocol)?;
+                }
+            }
+            LinkKind::Macvlan { parent, mode } | LinkKind::Macvtap { parent, mode } => {
+                write!(f, " link {} mode {}", parent, mode)?
+            }
+            LinkKind::Ipvlan { parent, mode } => write!(f, " link {} mode {}", parent, mode)?,
+            LinkKind::Vxlan(options) => write!(f, " {}", options)?,
+            LinkKind::Geneve(options) => write!(f, " {}", options)?,
+            LinkKind::Bridge(options) if !options.is_empty() => write!(f, " {}", options)?,
+            LinkKind::Tun(options) | LinkKind::Tap(options) if *options != TunOptions::default() => {
+                write!(f, " {}", options)?
+            }
+            LinkKind::Gre(options) | LinkKind::Ipip(options) | LinkKind::Sit(options) | LinkKind::Ip6tnl(options)
+                if *options != TunnelOptions::default() =>
+            {
+                write!(f, " {}", options)?
+            }
+            _ => {}
+        }
+        if let Some(mtu) = self.mtu {
+            write!(f, " mtu {}", mtu)?;
+        }
+        if let Some(mac) = self.mac {
+            write!(f, " address {}", mac)?;
+        }
+        Ok(())
+    }
+}
+
+/// VLANs created by the 8021q module along with their id and parent, from procfs
+///
+/// /proc/net/vlan/config starts with two header lines, then holds one
+/// `name | id | parent` line per VLAN. It only exists once the module is loaded.
+pub(crate) fn vlans() -> Result<Vec<(String, u16, String)>> {
+    let content = match fs::read_to_string("/proc/net/vlan/config") {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => bail!("Failed to read /proc/net/vlan/config: {}", e),
+    };
+
+    let mut vlans = Vec::new();
+    for line in content.lines().skip(2) {
+        let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+        if fields.len() != 3 {
+            bail!("Unexpected line in /proc/net/vlan/config: '{}'", line);
+        }
+        vlans.push((fields[0].to_string(), fields[1].parse()?, fields[2].to_string()));
+    }
+    Ok(vlans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bond::BondMode;
+
+    #[test]
+    fn vlan_protocol() {
+        let protocols = [("802.1Q", VlanProtocol::Ieee8021Q, 0x8100), ("802.1ad", VlanProtocol::Ieee8021Ad, 0x88a8)];
+        for (name, protocol, ethertype) in protocols {
+            assert_eq!(name.parse::<VlanProtocol>().unwrap(), protocol);
+            assert_eq!(name.to_uppercase().parse::<VlanProtocol>().unwrap(), protocol);
+            assert_eq!(protocol.to_string(), name);
+            assert_eq!(protocol.ethertype(), ethertype);
+        }
+        assert!("802.1x".parse::<VlanProtocol>().is_err());
+        assert!("0x8100".parse::<VlanProtocol>().is_err());
+    }
+
+    #[test]
+    fn display() {
+        let mut dummy = Link::new("dummy0", LinkKind::Dummy);
+        assert_eq!(dummy.to_string(), "dummy0 type dummy");
+        dummy.mtu = Some(9000);
+        dummy.mac = Some("02:00:5e:10:00:01".parse().unwrap());
+        assert_eq!(dummy.to_string(), "dummy0 type dummy mtu 9000 address 02:00:5e:10:00:01");
+
+        let veth = Link::new("veth0", LinkKind::Veth { peer: "veth1".to_string() });
+        assert_eq!(veth.to_string(), "veth0 type veth peer veth1");
+        let mut bridge = BridgeOptions::default();
+        assert_eq!(Link::new("br0", LinkKind::Bridge(bridge.clone())).to_string(), "br0 type bridge");
+        bridge.stp = Some(true);
+        assert_eq!(Link::new("br0", LinkKind::Bridge(bridge)).to_string(), "br0 type bridge stp on");
+        let bond = LinkKind::Bond(BondOptions::new(BondMode::ActiveBackup));
+        assert_eq!(Link::new("bond0", bond).to_string(), "bond0 type bond mode active-backup");
+
+        let vxlan = LinkKind::Vxlan(VxlanOptions::new(42));
+        assert_eq!(Link::new("vxlan0", vxlan).to_string(), "vxlan0 type vxlan id 42");
+        let geneve = LinkKind::Geneve(GeneveOptions::new(42, [10, 0, 0, 2].into()));
+        assert_eq!(Link::new("gnv0", geneve).to_string(), "gnv0 type geneve id 42 remote 10.0.0.2");
+
+        assert_eq!(Link::new("tun0", LinkKind::Tun(TunOptions::default())).to_string(), "tun0 type tun");
+        let tap = TunOptions {
+            owner: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(Link::new("tap0", LinkKind::Tap(tap)).to_string(), "tap0 type tap user 1000");
+
+        let gre = TunnelOptions {
+            remote: Some([10, 0, 0, 2].into()),
+            key: Some(42),
+            ..Default::default()
+        };
+        assert_eq!(Link::new("gre1", LinkKind::Gre(gre)).to_string(), "gre1 type gre remote 10.0.0.2 key 42");
+        let ip6tnl = LinkKind::Ip6tnl(TunnelOptions::default());
+        assert!(ip6tnl.tunnel_options().is_some());
+        assert_eq!(Link::new("ip6tnl1", ip6tnl).to_string(), "ip6tnl1 type ip6tnl");
+        assert!(LinkKind::Dummy.tunnel_options().is_none());
+
+        let vlan = |protocol| LinkKind::Vlan { parent: "eth0".to_string(), id: 5, protocol };
+        assert_eq!(Link::new("eth0.5", vlan(VlanProtocol::Ieee8021Q)).to_string(), "eth0.5 type vlan link eth0 id 5");
+        assert_eq!(
+            Link::new("eth0.5", vlan(VlanProtocol::Ieee8021Ad)).to_string(),
+            "eth0.5 type vlan link eth0 id 5 protocol 802.1ad"
+        );
+
+        let macvtap = LinkKind::Macvtap {
+            parent: "eth0".to_string(),
+            mode: MacvlanMode::Bridge,
+        };
+        assert_eq!(macvtap.parent(), Some("eth0"));
+        assert_eq!(Link::new("macvtap0", macvtap).to_string(), "macvtap0 type macvtap link eth0 mode bridge");
+        let ipvlan = LinkKind::Ipvlan {
+            parent: "eth0".to_string(),
+            mode: IpvlanMode::L3s,
+        };
+        assert_eq!(Link::new("ipvl0", ipvlan).to_string(), "ipvl0 type ipvlan link eth0 mode l3s");
+        assert_eq!(LinkKind::Dummy.parent(), None);
+    }
+
+    #[test]
+    fn macvlan_mode() {
+        let modes = [("private", 1), ("vepa", 2), ("bridge", 4), ("passthru", 8)];
+        for (name, number) in modes {
+            let mode = name.parse::<MacvlanMode>().unwrap();
+            assert_eq!(u32::from(mode), number);
+            assert_eq!(mode.to_string(), name);
+        }
+        assert!("source".parse::<MacvlanMode>().is_err());
+        assert!("Bridge".parse::<MacvlanMode>().is_err());
+    }
+
+    #[test]
+    fn ipvlan_mode() {
+        for (number, name) in ["l2", "l3", "l3s"].iter().enumerate() {
+            let mode = name.parse::<IpvlanMode>().unwrap();
+            assert_eq!(u16::from(mode), number as u16);
+            assert_eq!(mode.to_string(), *name);
+            assert_eq!(name.to_uppercase().parse::<IpvlanMode>().unwrap(), mode);
+        }
+        assert!("l4".parse::<IpvlanMode>().is_err());
+    }
+}
@@ -0,0 +1,252 @@
+//! Hardware addresses carried in `ifr_hwaddr`
+use anyhow::{bail, Result};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::str::FromStr;
+
+/// 48 bit Ethernet MAC address, e.g. `02:00:5e:10:00:01`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MacAddr([u8; 6]);
+
+impl MacAddr {
+    /// MAC address from its bytes
+    pub const fn new(octets: [u8; 6]) -> MacAddr {
+        MacAddr(octets)
+    }
+
+    /// Random locally administered unicast MAC address
+    pub fn random() -> Result<MacAddr> {
+        let mut octets = [0u8; 6];
+        File::open("/dev/urandom")?.read_exact(&mut octets)?;
+        // Set the locally administered bit, clear the multicast bit
+        octets[0] = (octets[0] | 0x02) & !0x01;
+        Ok(MacAddr(octets))
+    }
+
+    /// Bytes of the MAC address
+    pub fn octets(&self) -> [u8; 6] {
+        self.0
+    }
+
+    /// Whether the address is locally administered rather than assigned by a vendor
+    pub fn is_local(&self) -> bool {
+        self.0[0] & 0x02 != 0
+    }
+
+    /// Whether the address is a multicast (or broadcast) address
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+}
+
+impl From<[u8; 6]> for MacAddr {
+    fn from(octets: [u8; 6]) -> MacAddr {
+        MacAddr(octets)
+    }
+}
+
+/// Formats as lowercase, colon separated hex
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let o = &self.0;
+        write!(f, "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}", o[0], o[1], o[2], o[3], o[4], o[5])
+    }
+}
+
+/// Parses six hex bytes separated by `:` or `-`
+impl FromStr for MacAddr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut octets = [0u8; 6];
+        let mut parts = s.split([':', '-']);
+        for octet in octets.iter_mut() {
+            match parts.next().map(|part| (part.len(), u8::from_str_radix(part, 16))) {
+                Some((1..=2, Ok(byte))) => *octet = byte,
+                _ => bail!("Invalid MAC address '{}'", s),
+            }
+        }
+        if parts.next().is_some() {
+            bail!("Invalid MAC address '{}'", s);
+        }
+        Ok(MacAddr(octets))
+    }
+}
+
+// Missing from libc
+const ARPHRD_IP6GRE: u16 = 823;
+
+/// Serializes as its string form, e.g. `"02:00:5e:10:00:01"`
+impl Serialize for MacAddr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Link layer type of a device, the ARPHRD_* family of its hardware address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkType {
+    Ether,
+    Loopback,
+    Ieee80211,
+    Ipip,
+    Tunnel6,
+    Sit,
+    Gre,
+    Gre6,
+    /// Devices without a link layer, such as tun
+    None,
+    Void,
+    Other(u16),
+}
+
+impl LinkType {
+    /// Whether devices of this type use a 6 byte MAC address
+    pub fn has_mac(&self) -> bool {
+        matches!(self, LinkType::Ether | LinkType::Loopback | LinkType::Ieee80211)
+    }
+}
+
+impl From<u16> for LinkType {
+    fn from(family: u16) -> LinkType {
+        match family {
+            libc::ARPHRD_ETHER => LinkType::Ether,
+            libc::ARPHRD_LOOPBACK => LinkType::Loopback,
+            libc::ARPHRD_IEEE80211 => LinkType::Ieee80211,
+            libc::ARPHRD_TUNNEL => LinkType::Ipip,
+            libc::ARPHRD_TUNNEL6 => LinkType::Tunnel6,
+            libc::ARPHRD_SIT => LinkType::Sit,
+            libc::ARPHRD_IPGRE => LinkType::Gre,
+            ARPHRD_IP6GRE => LinkType::Gre6,
+            libc::ARPHRD_NONE => LinkType::None,
+            libc::ARPHRD_VOID => LinkType::Void,
+            _ => LinkType::Other(family),
+        }
+    }
+}
+
+impl From<LinkType> for u16 {
+    fn from(link_type: LinkType) -> u16 {
+        match link_type {
+            LinkType::Ether => libc::ARPHRD_ETHER,
+            LinkType::Loopback => libc::ARPHRD_LOOPBACK,
+            LinkType::Ieee80211 => libc::ARPHRD_IEEE80211,
+            LinkType::Ipip => libc::ARPHRD_TUNNEL,
+            LinkType::Tunnel6 => libc::ARPHRD_TUNNEL6,
+            LinkType::Sit => libc::ARPHRD_SIT,
+            LinkType::Gre => libc::ARPHRD_IPGRE,
+            LinkType::Gre6 => ARPHRD_IP6GRE,
+            LinkType::None => libc::ARPHRD_NONE,
+            LinkType::Void => libc::ARPHRD_VOID,
+            LinkType::Other(family) => family,
+        }
+    }
+}
+
+/// Formats with the names iproute2 uses, e.g. `ether`
+impl fmt::Display for LinkType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkType::Ether => write!(f, "ether"),
+            LinkType::Loopback => write!(f, "loopback"),
+            LinkType::Ieee80211 => write!(f, "ieee802.11"),
+            LinkType::Ipip => write!(f, "ipip"),
+            LinkType::Tunnel6 => write!(f, "tunnel6"),
+            LinkType::Sit => write!(f, "sit"),
+            LinkType::Gre => write!(f, "gre"),
+            LinkType::Gre6 => write!(f, "gre6"),
+            LinkType::None => write!(f, "none"),
+            LinkType::Void => write!(f, "void"),
+            LinkType::Other(family) => write!(f, "{}", family),
+        }
+    }
+}
+
+/// Serializes as its iproute2 name, e.g. `"ether"`
+impl Serialize for LinkType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Hardware address of a device along with its link layer type
+///
+/// `mac` is only set for link types using MAC addresses, tunnels and
+/// devices without a link layer report their type alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct HwAddress {
+    pub link_type: LinkType,
+    pub mac: Option<MacAddr>,
+}
+
+/// Formats like iproute2, e.g. `link/ether 02:00:5e:10:00:01`
+impl fmt::Display for HwAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mac {
+            Some(mac) => write!(f, "link/{} {}", self.link_type, mac),
+            None => write!(f, "link/{}", self.link_type),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_mac() {
+        let mac = MacAddr::new([0x02, 0x00, 0x5e, 0x10, 0x00, 0x01]);
+        assert_eq!("02:00:5e:10:00:01".parse::<MacAddr>().unwrap(), mac);
+        assert_eq!("02-00-5E-10-00-01".parse::<MacAddr>().unwrap(), mac);
+        assert_eq!("2:0:5e:10:0:1".parse::<MacAddr>().unwrap(), mac);
+        assert_eq!(mac.to_string(), "02:00:5e:10:00:01");
+    }
+
+    #[test]
+    fn parse_invalid_mac() {
+        for s in ["", "02:00:5e:10:00", "02:00:5e:10:00:01:02", "02:00:5e:10:00:1g", "2:0:5e::0:1", "002:0:0:0:0:1"] {
+            assert!(s.parse::<MacAddr>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn random_mac() {
+        let mac = MacAddr::random().unwrap();
+        assert!(mac.is_local());
+        assert!(!mac.is_multicast());
+    }
+
+    #[test]
+    fn link_type() {
+        for family in [libc::ARPHRD_ETHER, libc::ARPHRD_LOOPBACK, libc::ARPHRD_NONE, ARPHRD_IP6GRE, 0xfff] {
+            assert_eq!(u16::from(LinkType::from(family)), family);
+        }
+        assert_eq!(LinkType::from(libc::ARPHRD_IEEE80211).to_string(), "ieee802.11");
+        assert_eq!(LinkType::from(0xfff), LinkType::Other(0xfff));
+        let hw_address = HwAddress {
+            link_type: LinkType::Ether,
+            mac: Some(MacAddr::new([0x02, 0x00, 0x5e, 0x10, 0x00, 0x01])),
+        };
+        assert_eq!(hw_address.to_string(), "link/ether 02:00:5e:10:00:01");
+    }
+
+    #[test]
+    fn serialize_hw_address() {
+        let hw_address = HwAddress {
+            link_type: LinkType::Ether,
+            mac: Some(MacAddr::new([0x02, 0x00, 0x5e, 0x10, 0x00, 0x01])),
+        };
+        assert_eq!(
+            serde_json::to_value(hw_address).unwrap(),
+            serde_json::json!({"link_type": "ether", "mac": "02:00:5e:10:00:01"})
+        );
+        let hw_address = HwAddress {
+            link_type: LinkType::from(libc::ARPHRD_NONE),
+            mac: None,
+        };
+        let expected = serde_json::json!({"link_type": "none", "mac": null});
+        assert_eq!(serde_json::to_value(hw_address).unwrap(), expected);
+    }
+}
@@ -1,106 +1,366 @@
-//! Linux supports some standard ioctls to configure network devices.
-//! They can be used on any socket's file descriptor regardless of
-//! the family or type. Most of them pass an ifreq structure.
-//! Source: netdevice(7)
-use std::os::unix::prelude::RawFd;
+mod cli;
+
 use anyhow::{bail, Result};
-use ifstructs::ifreq;
-use nix::libc::{SIOCGIFADDR, SIOCSIFADDR};
-use nix::sys::socket::{socket, AddressFamily, SockFlag, SockProtocol, SockType};
-use nix::{ioctl_read_bad, ioctl_write_ptr_bad};
+use cli::addr::AddrCommand;
+use cli::backend::BackendKind;
+use cli::bridge::BridgeCommand;
+use cli::link::LinkCommand;
+use cli::neigh::NeighCommand;
+use cli::netns::NetnsCommand;
+use cli::output::OutputFormat;
+use cli::route::RouteCommand;
+use cli::rule::RuleCommand;
+use cli::wg::WgCommand;
+use network_config::{BondInfo, Cidr, HwAddress, Interface, InterfaceFlags, InterfaceInfo, Ipv4Info, MacAddr, Netns};
+use serde::Serialize;
 use simple_logger::SimpleLogger;
-use std::net::{IpAddr, Ipv4Addr};
+use std::net::Ipv4Addr;
 use structopt::StructOpt;
 use log::info;
 
-/// Create an endpoint for communication
-fn crate_sock<T: Into<Option<SockProtocol>>>(
-    domain: AddressFamily,
-    ty: SockType,
-    flags: SockFlag,
-    protocol: T,
-) -> Result<RawFd> {
-    Ok(socket(domain, ty, flags, protocol)?)
-}
-
-// Creation of icotl functions needed
-ioctl_read_bad!(get_interface_ip, SIOCGIFADDR, ifreq);
-ioctl_write_ptr_bad!(set_interface_ip, SIOCSIFADDR, ifreq);
-
-/// Get `IpAddr` from sockaddr
-pub fn ip_from_sockaddr(sock_addr: &libc::sockaddr) -> Result<IpAddr> {
-    match sock_addr.sa_family as i32 {
-        // IPV4
-        libc::AF_INET => {
-            let mut arr = [0u8; 4];
-            for i in 0..arr.len() {
-                arr[i] = sock_addr.sa_data[i + 2] as u8;
-            }
-            Ok(IpAddr::from(Ipv4Addr::from(arr)))
-        }
-        // IPV6
-        libc::AF_INET6 => bail!("IPv6 is not supported at the time"),
-        _ => bail!("Received unknown sa_family"),
+#[derive(Debug, StructOpt)]
+struct SetArgs {
+    /// Interface to set IP
+    interface: String,
+
+    /// IPv4 or IPv6 to set, with an optional prefix length (e.g. `10.1.2.3/24`)
+    ip: Cidr,
+
+    /// IPv4 broadcast address, derived from the prefix length when omitted
+    #[structopt(long)]
+    broadcast: Option<Ipv4Addr>,
+
+    /// IPv4 peer address of a point-to-point interface
+    #[structopt(long)]
+    peer: Option<Ipv4Addr>,
+}
+
+/// Flags which can be toggled with the `flag` subcommand
+const TOGGLE_FLAGS: InterfaceFlags = InterfaceFlags::from_bits_truncate(
+    libc::IFF_PROMISC | libc::IFF_ALLMULTI | libc::IFF_NOARP | libc::IFF_MULTICAST | libc::IFF_DYNAMIC,
+);
+
+fn parse_mac(s: &str) -> Result<MacAddr> {
+    match s {
+        "random" => MacAddr::random(),
+        _ => s.parse(),
     }
 }
 
-/// Get `sockaddr` from IpAddr
-pub fn sockaddr_from_ip(ip_addr: &IpAddr) -> Result<libc::sockaddr> {
-    let sa_family: libc::sa_family_t;
-    let mut sa_data = [0i8; 14];
+fn parse_on_off(s: &str) -> Result<bool> {
+    match s {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => bail!("Expected 'on' or 'off', got '{}'", s),
+    }
+}
 
-    match ip_addr {
-        IpAddr::V4(ip) => {
-            sa_family = libc::AF_INET as libc::sa_family_t;
-            let data = ip.octets();
-            for i in 0..data.len() {
-                sa_data[i + 2] = data[i] as i8;
-            }
-        }
-        _ => bail!("IPv6 is not supported at the time")
-    };
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Print the flags, hardware address and IPv4 address, prefix length, broadcast and peer of an interface
+    Get {
+        /// Interface to query
+        interface: String,
+    },
+    /// Set the IP address of an interface
+    Set(SetArgs),
+    /// List all interfaces with their index, flags, MTU, hardware address and addresses
+    List,
+    /// Bring an interface up
+    Up {
+        /// Interface to bring up
+        interface: String,
+    },
+    /// Bring an interface down
+    Down {
+        /// Interface to bring down
+        interface: String,
+    },
+    /// Turn a flag of an interface on or off
+    Flag {
+        /// Interface to change
+        interface: String,
+
+        /// One of promisc, allmulti, noarp, multicast or dynamic
+        flag: InterfaceFlags,
+
+        /// `on` or `off`
+        #[structopt(parse(try_from_str = parse_on_off))]
+        state: bool,
+    },
+    /// Print the MTU of an interface, or set it when given
+    Mtu {
+        /// Interface to query or change
+        interface: String,
 
-    Ok(libc::sockaddr {
-        sa_family,
-        sa_data
+        /// MTU to set
+        mtu: Option<u32>,
+    },
+    /// Print the hardware address of an interface, or set its MAC address when given
+    Mac {
+        /// Interface to query or change
+        interface: String,
+
+        /// MAC address to set, or `random` for a random locally administered one
+        #[structopt(parse(try_from_str = parse_mac))]
+        mac: Option<MacAddr>,
+    },
+    /// Create, change or delete virtual links and tunnels, enslave them to bonds and bridges or move them to namespaces
+    Link(LinkCommand),
+    /// Change bridge options, manage the VLANs of bridge ports and list forwarding databases
+    Bridge(BridgeCommand),
+    /// Show, add, replace, remove or flush the addresses of interfaces
+    Addr(AddrCommand),
+    /// List, look up, add or remove routes
+    Route(RouteCommand),
+    /// List, add or remove policy routing rules
+    Rule(RuleCommand),
+    /// List, add, replace, remove or flush ARP and NDP neighbours
+    Neigh(NeighCommand),
+    /// Show WireGuard devices, and set their keys, port and peers
+    Wg(WgCommand),
+    /// List, add or delete network namespaces, and run commands in them
+    Netns(NetnsCommand),
+    // `network_config <interface> <ip>` is kept as a shorthand for `set`
+    #[structopt(external_subcommand)]
+    Shorthand(Vec<String>),
+}
+
+/// Configure network interfaces through the kernel's netdevice ioctls.
+///
+/// `network_config <interface> <ip>` is a shorthand for `network_config set <interface> <ip>`.
+#[derive(Debug, StructOpt)]
+struct Args {
+    /// Format of printed results: table, json or yaml
+    #[structopt(short, long, global = true, default_value = "table")]
+    output: OutputFormat,
+
+    /// Backend managing links, bridges, addresses, routes, rules, neighbours and WireGuard devices: netlink, ioctl, or auto for netlink with an ioctl fallback
+    #[structopt(long, global = true, default_value = "auto")]
+    backend: BackendKind,
+
+    /// Network namespace to operate in, as created by `netns add`
+    #[structopt(long, global = true)]
+    netns: Option<String>,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+/// Result of `get`
+#[derive(Debug, Serialize)]
+struct GetOutput {
+    name: String,
+    flags: InterfaceFlags,
+    hw_address: HwAddress,
+    inet: Ipv4Info,
+    bond: Option<BondInfo>,
+}
+
+/// Result of `mtu` without a value
+#[derive(Debug, Serialize)]
+struct MtuOutput {
+    name: String,
+    mtu: u32,
+}
+
+/// Result of `mac` without a value
+#[derive(Debug, Serialize)]
+struct MacOutput {
+    name: String,
+    hw_address: HwAddress,
+}
+
+fn print_bond(bond: &BondInfo) {
+    println!("    {}", bond);
+    for slave in &bond.slaves {
+        println!("        {}", slave);
+    }
+}
+
+fn get(interface: &str, output: OutputFormat) -> Result<()> {
+    let interface = Interface::new(interface)?;
+    let result = GetOutput {
+        inet: interface.ipv4_info()?,
+        name: interface.name().to_string(),
+        flags: interface.flags()?,
+        hw_address: interface.hw_address()?,
+        bond: interface.bond()?,
+    };
+    output.print(&result, |result| {
+        let mut line = format!("inet {}", result.inet.address);
+        if let Some(broadcast) = result.inet.broadcast {
+            line += &format!(" brd {}", broadcast);
+        }
+        if let Some(peer) = result.inet.peer {
+            line += &format!(" peer {}", peer);
+        }
+        println!("{}: <{}>", result.name, result.flags);
+        println!("    {}", result.hw_address);
+        println!("    {}", line);
+        if let Some(bond) = &result.bond {
+            print_bond(bond);
+        }
     })
 }
 
-// get the ip of interface
-pub fn get_ip(ifr: &ifreq) -> Result<IpAddr> {
-    ip_from_sockaddr(unsafe { &ifr.ifr_ifru.ifr_addr })
+fn set(args: SetArgs) -> Result<()> {
+    let interface = Interface::new(&args.interface)?;
+    if args.ip.addr.is_ipv6() && (args.broadcast.is_some() || args.peer.is_some()) {
+        bail!("Broadcast and peer addresses only apply to IPv4");
+    }
+
+    interface.set_address(&args.ip)?;
+    if let Some(broadcast) = args.broadcast {
+        interface.set_broadcast(&broadcast)?;
+    }
+    if let Some(peer) = args.peer {
+        interface.set_destination(&peer)?;
+    }
+    info!("Interface '{}' set to ip address '{}' succesfully!", args.interface, args.ip.addr);
+    Ok(())
 }
 
-// set the ip of interface
-pub fn set_ip(ifr: &mut ifreq, ip_addr: &IpAddr) -> Result<()> {
-    Ok(ifr.ifr_ifru.ifr_addr = sockaddr_from_ip(ip_addr)?)
+fn flag(interface: &str, flag: InterfaceFlags, state: bool) -> Result<()> {
+    if flag.is_empty() || !TOGGLE_FLAGS.contains(flag) {
+        bail!("Only the {} flags can be toggled", TOGGLE_FLAGS);
+    }
+
+    let interface = Interface::new(interface)?;
+    if state {
+        interface.update_flags(flag, InterfaceFlags::empty())?;
+    } else {
+        interface.update_flags(InterfaceFlags::empty(), flag)?;
+    }
+    info!("Interface '{}' flags set to <{}>", interface.name(), interface.flags()?);
+    Ok(())
 }
 
-#[derive(Debug, StructOpt)]
-struct Args {
-    /// Interface to set IP
-    interface: String,
+fn mtu(interface: &str, mtu: Option<u32>, output: OutputFormat) -> Result<()> {
+    let interface = Interface::new(interface)?;
+    match mtu {
+        Some(mtu) => {
+            interface.set_mtu(mtu)?;
+            info!("Interface '{}' mtu set to {}", interface.name(), mtu);
+        }
+        None => {
+            let result = MtuOutput {
+                name: interface.name().to_string(),
+                mtu: interface.mtu()?,
+            };
+            output.print(&result, |result| println!("{}", result.mtu))?;
+        }
+    }
+    Ok(())
+}
+
+fn mac(interface: &str, mac: Option<MacAddr>, output: OutputFormat) -> Result<()> {
+    let interface = Interface::new(interface)?;
+    match mac {
+        Some(mac) => {
+            interface.set_mac_address(&mac)?;
+            info!("Interface '{}' MAC address set to {}", interface.name(), mac);
+        }
+        None => {
+            let result = MacOutput {
+                name: interface.name().to_string(),
+                hw_address: interface.hw_address()?,
+            };
+            output.print(&result, |result| println!("{}", result.hw_address))?;
+        }
+    }
+    Ok(())
+}
 
-    /// IPv4 to set
-    ip: Ipv4Addr,
+fn list(output: OutputFormat) -> Result<()> {
+    let infos = Interface::list()?
+        .iter()
+        .map(Interface::info)
+        .collect::<Result<Vec<InterfaceInfo>>>()?;
+    output.print(&infos, |infos| {
+        for info in infos {
+            println!("{}: {}: <{}> mtu {}", info.index, info.name, info.flags, info.mtu);
+            println!("    {}", info.hw_address);
+            for addr in &info.addresses {
+                let family = if addr.addr.is_ipv4() { "inet" } else { "inet6" };
+                println!("    {} {}", family, addr);
+            }
+            if let Some(bond) = &info.bond {
+                print_bond(bond);
+            }
+            if !info.children.is_empty() {
+                println!("    children {}", info.children.join(" "));
+            }
+        }
+    })
 }
 
 fn main() -> Result<()> {
     SimpleLogger::new().init()?;
     let args = Args::from_args();
+    // Before any socket is opened
+    if let Some(netns) = &args.netns {
+        Netns::open(netns)?.enter()?;
+    }
 
-    info!("Opening socket to kernel...");
-    let sock_fd = crate_sock(
-        AddressFamily::Inet,
-        SockType::Datagram,
-        SockFlag::empty(),
-        None,
-    )?;
-
-    let mut ifreq = ifreq::from_name(&args.interface)?;
-    let new_addr = IpAddr::from(args.ip);
-    set_ip(&mut ifreq, &new_addr)?;
-    unsafe { set_interface_ip(sock_fd, &mut ifreq)? };
-    info!("Interface '{}' set to ip address '{}' succesfully!", args.interface, new_addr);
-    Ok(())
+    match args.command {
+        Command::Get { interface } => get(&interface, args.output),
+        Command::Set(args) => set(args),
+        Command::List => list(args.output),
+        Command::Up { interface } => Interface::new(&interface)?.up(),
+        Command::Down { interface } => Interface::new(&interface)?.down(),
+        Command::Flag { interface, flag: f, state } => flag(&interface, f, state),
+        Command::Mtu { interface, mtu: m } => mtu(&interface, m, args.output),
+        Command::Mac { interface, mac: m } => mac(&interface, m, args.output),
+        Command::Link(command) => cli::link::run(command, args.backend.open()?.as_ref()),
+        Command::Bridge(command) => cli::bridge::run(command, args.backend.open()?.as_ref(), args.output),
+        Command::Addr(command) => cli::addr::run(command, args.backend.open()?.as_ref(), args.output),
+        Command::Route(command) => cli::route::run(command, args.backend.open()?.as_ref(), args.output),
+        Command::Rule(command) => cli::rule::run(command, args.backend.open()?.as_ref(), args.output),
+        Command::Neigh(command) => cli::neigh::run(command, args.backend.open()?.as_ref(), args.output),
+        Command::Wg(command) => cli::wg::run(command, args.backend.open()?.as_ref(), args.output),
+        Command::Netns(command) => cli::netns::run(command, args.output),
+        Command::Shorthand(args) => {
+            let bin = std::env::args().next().unwrap_or_default();
+            set(SetArgs::from_iter(std::iter::once(bin).chain(args)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_config::LinkType;
+
+    #[test]
+    fn serialize_get() {
+        let result = GetOutput {
+            name: "eth0".to_string(),
+            flags: "up,broadcast,running,multicast".parse().unwrap(),
+            hw_address: HwAddress {
+                link_type: LinkType::Ether,
+                mac: Some(MacAddr::new([0x02, 0xfc, 0, 0, 0, 0x01])),
+            },
+            inet: Ipv4Info {
+                address: "192.0.2.2/24".parse().unwrap(),
+                broadcast: Some(Ipv4Addr::new(192, 0, 2, 255)),
+                peer: None,
+            },
+            bond: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            serde_json::json!({
+                "name": "eth0",
+                "flags": ["UP", "BROADCAST", "RUNNING", "MULTICAST"],
+                "hw_address": {"link_type": "ether", "mac": "02:fc:00:00:00:01"},
+                "inet": {
+                    "address": {"addr": "192.0.2.2", "prefix_len": 24},
+                    "broadcast": "192.0.2.255",
+                    "peer": null
+                },
+                "bond": null
+            })
+        );
+    }
 }
@@ -0,0 +1,263 @@
+//! Neighbour entries, the ARP table for IPv4 and the NDP table for IPv6
+use crate::mac::MacAddr;
+use anyhow::{bail, Result};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+// ATF_* flags of /proc/net/arp
+const ATF_COM: u32 = 0x02;
+const ATF_PERM: u32 = 0x04;
+
+/// State of a neighbour entry (NUD_*)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeighbourState {
+    /// Resolution in progress
+    Incomplete,
+    /// Confirmed recently
+    Reachable,
+    /// Valid but to be confirmed on its next use
+    Stale,
+    Delay,
+    Probe,
+    /// Resolution failed
+    Failed,
+    /// Valid without resolution, e.g. on point-to-point links
+    Noarp,
+    /// Static entry, never expiring
+    Permanent,
+    Other(u16),
+}
+
+impl From<u16> for NeighbourState {
+    fn from(state: u16) -> NeighbourState {
+        match state {
+            0x01 => NeighbourState::Incomplete,
+            0x02 => NeighbourState::Reachable,
+            0x04 => NeighbourState::Stale,
+            0x08 => NeighbourState::Delay,
+            0x10 => NeighbourState::Probe,
+            0x20 => NeighbourState::Failed,
+            0x40 => NeighbourState::Noarp,
+            0x80 => NeighbourState::Permanent,
+            _ => NeighbourState::Other(state),
+        }
+    }
+}
+
+impl From<NeighbourState> for u16 {
+    fn from(state: NeighbourState) -> u16 {
+        match state {
+            NeighbourState::Incomplete => 0x01,
+            NeighbourState::Reachable => 0x02,
+            NeighbourState::Stale => 0x04,
+            NeighbourState::Delay => 0x08,
+            NeighbourState::Probe => 0x10,
+            NeighbourState::Failed => 0x20,
+            NeighbourState::Noarp => 0x40,
+            NeighbourState::Permanent => 0x80,
+            NeighbourState::Other(state) => state,
+        }
+    }
+}
+
+/// Formats with the names iproute2 uses, e.g. `PERMANENT`
+impl fmt::Display for NeighbourState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NeighbourState::Incomplete => write!(f, "INCOMPLETE"),
+            NeighbourState::Reachable => write!(f, "REACHABLE"),
+            NeighbourState::Stale => write!(f, "STALE"),
+            NeighbourState::Delay => write!(f, "DELAY"),
+            NeighbourState::Probe => write!(f, "PROBE"),
+            NeighbourState::Failed => write!(f, "FAILED"),
+            NeighbourState::Noarp => write!(f, "NOARP"),
+            NeighbourState::Permanent => write!(f, "PERMANENT"),
+            NeighbourState::Other(state) => write!(f, "{:#x}", state),
+        }
+    }
+}
+
+/// Parses a state name, case insensitive
+impl FromStr for NeighbourState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "incomplete" => Ok(NeighbourState::Incomplete),
+            "reachable" => Ok(NeighbourState::Reachable),
+            "stale" => Ok(NeighbourState::Stale),
+            "delay" => Ok(NeighbourState::Delay),
+            "probe" => Ok(NeighbourState::Probe),
+            "failed" => Ok(NeighbourState::Failed),
+            "noarp" => Ok(NeighbourState::Noarp),
+            "permanent" => Ok(NeighbourState::Permanent),
+            _ => bail!("Unknown neighbour state '{}'", s),
+        }
+    }
+}
+
+/// Serializes as its iproute2 name, e.g. `"PERMANENT"`
+impl Serialize for NeighbourState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Entry of the neighbour table, mapping an IP address to a link layer address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Neighbour {
+    pub address: IpAddr,
+    /// Name of the interface the neighbour is reached through
+    pub interface: String,
+    /// Unset while unresolved
+    pub mac: Option<MacAddr>,
+    pub state: NeighbourState,
+    /// Whether the neighbour is an IPv6 router
+    pub router: bool,
+}
+
+impl Neighbour {
+    /// Permanent entry mapping `address` to `mac` on `interface`
+    pub fn new(address: IpAddr, interface: &str, mac: MacAddr) -> Neighbour {
+        Neighbour {
+            address,
+            interface: interface.to_string(),
+            mac: Some(mac),
+            state: NeighbourState::Permanent,
+            router: false,
+        }
+    }
+
+    /// Whether the entry was configured rather than learned
+    pub fn is_static(&self) -> bool {
+        matches!(self.state, NeighbourState::Permanent | NeighbourState::Noarp)
+    }
+}
+
+/// Formats as iproute2 does, e.g. `10.0.0.1 dev eth0 lladdr 02:00:5e:10:00:01 PERMANENT`
+impl fmt::Display for Neighbour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} dev {}", self.address, self.interface)?;
+        if let Some(mac) = self.mac {
+            write!(f, " lladdr {}", mac)?;
+        }
+        if self.router {
+            write!(f, " router")?;
+        }
+        write!(f, " {}", self.state)
+    }
+}
+
+/// IPv4 neighbours, from procfs
+pub(crate) fn arp_entries() -> Result<Vec<Neighbour>> {
+    match fs::read_to_string("/proc/net/arp") {
+        Ok(content) => parse_arp(&content),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => bail!("Failed to read /proc/net/arp: {}", e),
+    }
+}
+
+/// Parses the content of /proc/net/arp
+///
+/// Each line holds the address, hardware type, flags, hardware address, mask
+/// and interface. Entries being resolved have no flags.
+fn parse_arp(content: &str) -> Result<Vec<Neighbour>> {
+    let mut neighbours = Vec::new();
+    for line in content.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 6 {
+            bail!("Unexpected line in /proc/net/arp: '{}'", line);
+        }
+        let flags = u32::from_str_radix(fields[2].trim_start_matches("0x"), 16)?;
+        let state = if flags & ATF_PERM != 0 {
+            NeighbourState::Permanent
+        } else if flags & ATF_COM != 0 {
+            NeighbourState::Reachable
+        } else {
+            NeighbourState::Incomplete
+        };
+        neighbours.push(Neighbour {
+            address: fields[0].parse()?,
+            interface: fields[5].to_string(),
+            mac: if flags & ATF_COM != 0 { Some(fields[3].parse()?) } else { None },
+            state,
+            router: false,
+        });
+    }
+    Ok(neighbours)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn state() {
+        let names = ["INCOMPLETE", "REACHABLE", "STALE", "DELAY", "PROBE", "FAILED", "NOARP", "PERMANENT"];
+        for (i, name) in names.iter().enumerate() {
+            let state = NeighbourState::from(1 << i);
+            assert_eq!(u16::from(state), 1 << i);
+            assert_eq!(state.to_string(), *name);
+            assert_eq!(name.parse::<NeighbourState>().unwrap(), state);
+            assert_eq!(name.to_lowercase().parse::<NeighbourState>().unwrap(), state);
+        }
+        assert_eq!(NeighbourState::from(0x100), NeighbourState::Other(0x100));
+        assert_eq!(NeighbourState::Other(0x100).to_string(), "0x100");
+        assert!("none".parse::<NeighbourState>().is_err());
+        assert!("0x100".parse::<NeighbourState>().is_err());
+    }
+
+    #[test]
+    fn display() {
+        let mac = "02:00:5e:10:00:01".parse().unwrap();
+        let mut neighbour = Neighbour::new(IpAddr::from([10, 0, 0, 1]), "eth0", mac);
+        assert!(neighbour.is_static());
+        assert_eq!(neighbour.to_string(), "10.0.0.1 dev eth0 lladdr 02:00:5e:10:00:01 PERMANENT");
+
+        neighbour.address = "fe80::1".parse().unwrap();
+        neighbour.state = NeighbourState::Stale;
+        neighbour.router = true;
+        assert!(!neighbour.is_static());
+        assert_eq!(neighbour.to_string(), "fe80::1 dev eth0 lladdr 02:00:5e:10:00:01 router STALE");
+
+        neighbour.mac = None;
+        neighbour.router = false;
+        neighbour.state = NeighbourState::Failed;
+        assert_eq!(neighbour.to_string(), "fe80::1 dev eth0 FAILED");
+    }
+
+    #[test]
+    fn proc_arp() {
+        let content = "\
+IP address       HW type     Flags       HW address            Mask     Device
+10.0.0.1         0x1         0x2         02:00:5e:10:00:01     *        eth0
+10.0.0.2         0x1         0x6         02:00:5e:10:00:02     *        eth0
+10.0.0.3         0x1         0x0         00:00:00:00:00:00     *        eth1
+";
+        let mac = |s: &str| Some(s.parse().unwrap());
+        let neighbours = parse_arp(content).unwrap();
+        let expected = [
+            (Ipv4Addr::new(10, 0, 0, 1), "eth0", mac("02:00:5e:10:00:01"), NeighbourState::Reachable),
+            (Ipv4Addr::new(10, 0, 0, 2), "eth0", mac("02:00:5e:10:00:02"), NeighbourState::Permanent),
+            (Ipv4Addr::new(10, 0, 0, 3), "eth1", None, NeighbourState::Incomplete),
+        ];
+        assert_eq!(neighbours.len(), expected.len());
+        for (neighbour, (address, interface, mac, state)) in neighbours.iter().zip(expected) {
+            assert_eq!(neighbour.address, address);
+            assert_eq!(neighbour.interface, interface);
+            assert_eq!(neighbour.mac, mac);
+            assert_eq!(neighbour.state, state);
+            assert!(!neighbour.router);
+        }
+
+        assert!(parse_arp("IP address\n").unwrap().is_empty());
+        assert!(parse_arp("IP address\n10.0.0.1 0x1 0x2 02:00:5e:10:00:01 *\n").is_err());
+        assert!(parse_arp("IP address\n10.0.0.1 0x1 0xz 02:00:5e:10:00:01 * eth0\n").is_err());
+        assert!(parse_arp("IP address\n10.0.0.1 0x1 0x2 02:00:5e:10:00 * eth0\n").is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! RTM_NEWADDR, RTM_DELADDR and RTM_GETADDR, see rtnetlink(7)
+use super::*;
+use crate::addr::{Address, AddressFlags, Cidr, Scope};
+
+const RTM_NEWADDR: u16 = 20;
+const RTM_DELADDR: u16 = 21;
+const RTM_GETADDR: u16 = 22;
+
+const IFA_ADDRESS: u16 = 1;
+const IFA_LOCAL: u16 = 2;
+const IFA_LABEL: u16 = 3;
+const IFA_BROADCAST: u16 = 4;
+const IFA_CACHEINFO: u16 = 6;
+const IFA_FLAGS: u16 = 8;
+
+/// Lifetime of addresses which never expire
+const INFINITY_LIFE_TIME: u32 = u32::MAX;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct ifaddrmsg {
+    ifa_family: u8,
+    ifa_prefixlen: u8,
+    ifa_flags: u8,
+    ifa_scope: u8,
+    ifa_index: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct ifa_cacheinfo {
+    ifa_prefered: u32,
+    ifa_valid: u32,
+    cstamp: u32,
+    tstamp: u32,
+}
+
+fn family(addr: &IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => libc::AF_INET as u8,
+        IpAddr::V6(_) => libc::AF_INET6 as u8,
+    }
+}
+
+fn lifetime(lifetime: u32) -> Option<u32> {
+    match lifetime {
+        INFINITY_LIFE_TIME => None,
+        lifetime => Some(lifetime),
+    }
+}
+
+/// Build the message adding or removing `address` of the interface `index`
+fn address_message(ty: u16, flags: u16, index: u32, address: &Address) -> Message {
+    let header = ifaddrmsg {
+        ifa_family: family(&address.cidr.addr),
+        ifa_prefixlen: address.prefix_len(),
+        ifa_flags: address.flags.bits() as u8,
+        ifa_scope: u8::from(address.scope),
+        ifa_index: index,
+    };
+    let mut msg = Message::new(ty, flags, &header);
+
+    // IFA_LOCAL is the address itself, IFA_ADDRESS the peer on point-to-point links
+    msg.attr_ip(IFA_LOCAL, &address.cidr.addr);
+    msg.attr_ip(IFA_ADDRESS, address.peer.as_ref().unwrap_or(&address.cidr.addr));
+    if let Some(broadcast) = address.broadcast {
+        msg.attr_ip(IFA_BROADCAST, &IpAddr::from(broadcast));
+    }
+    if let Some(label) = &address.label {
+        msg.attr_str(IFA_LABEL, label);
+    }
+    if address.flags.bits() > u8::MAX as u32 {
+        msg.attr_u32(IFA_FLAGS, address.flags.bits());
+    }
+    if address.valid_lifetime.is_some() || address.preferred_lifetime.is_some() {
+        let cacheinfo = ifa_cacheinfo {
+            ifa_prefered: address.preferred_lifetime.unwrap_or(INFINITY_LIFE_TIME),
+            ifa_valid: address.valid_lifetime.unwrap_or(INFINITY_LIFE_TIME),
+            ..Default::default()
+        };
+        msg.attr_struct(IFA_CACHEINFO, &cacheinfo);
+    }
+    msg
+}
+
+/// Add `address` to the interface `index`, replacing an existing one when `replace` is set
+pub fn add(sock: &NetlinkSocket, index: u32, address: &Address, replace: bool) -> Result<()> {
+    let flags = NLM_F_CREATE | if replace { NLM_F_REPLACE } else { NLM_F_EXCL };
+    sock.request(address_message(RTM_NEWADDR, flags, index, address))
+        .map_err(|e| e.context(format!("Failed to add address '{}'", address.cidr)))
+}
+
+/// Remove `address` from the interface `index`
+pub fn del(sock: &NetlinkSocket, index: u32, address: &Address) -> Result<()> {
+    sock.request(address_message(RTM_DELADDR, 0, index, address))
+        .map_err(|e| e.context(format!("Failed to remove address '{}'", address.cidr)))
+}
+
+/// Parse an RTM_NEWADDR message into the index of its interface and the address, if it has one
+fn parse_address(payload: &[u8]) -> Result<Option<(u32, Address)>> {
+    let (header, data) = parse_header::<ifaddrmsg>(payload)?;
+    let mut local = None;
+    let mut address = None;
+    let mut parsed = Address::new(Cidr {
+        addr: IpAddr::from([0, 0, 0, 0]),
+        prefix_len: Some(header.ifa_prefixlen),
+    });
+    parsed.scope = Scope::from(header.ifa_scope);
+    parsed.flags = AddressFlags::from_bits_truncate(header.ifa_flags as u32);
+
+    for (ty, data) in attrs(data) {
+        match ty {
+            IFA_LOCAL => local = Some(parse_ip(data)?),
+            IFA_ADDRESS => address = Some(parse_ip(data)?),
+            IFA_BROADCAST => {
+                if let IpAddr::V4(broadcast) = parse_ip(data)? {
+                    parsed.broadcast = Some(broadcast);
+                }
+            }
+            IFA_LABEL => parsed.label = Some(parse_str(data)),
+            IFA_FLAGS => parsed.flags = AddressFlags::from_bits_truncate(parse_u32(data)?),
+            IFA_CACHEINFO => {
+                let (cacheinfo, _) = parse_header::<ifa_cacheinfo>(data)?;
+                parsed.valid_lifetime = lifetime(cacheinfo.ifa_valid);
+                parsed.preferred_lifetime = lifetime(cacheinfo.ifa_prefered);
+            }
+            _ => {}
+        }
+    }
+
+    // IPv6 addresses without a peer only carry IFA_ADDRESS
+    parsed.cidr.addr = match (local, address) {
+        (Some(local), Some(address)) => {
+            if local != address {
+                parsed.peer = Some(address);
+            }
+            local
+        }
+        (Some(addr), None) | (None, Some(addr)) => addr,
+        (None, None) => return Ok(None),
+    };
+    Ok(Some((header.ifa_index, parsed)))
+}
+
+/// Addresses of all interfaces along with their index
+pub fn list(sock: &NetlinkSocket) -> Result<Vec<(u32, Address)>> {
+    let header = ifaddrmsg::default();
+    let mut addresses = Vec::new();
+    for payload in sock.dump(Message::new(RTM_GETADDR, 0, &header))? {
+        addresses.extend(parse_address(&payload)?);
+    }
+    Ok(addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    /// Build the message adding `address` to the interface 3, then parse it as the kernel would send it back
+    fn round_trip(address: &Address) -> Option<(u32, Address)> {
+        let buf = address_message(RTM_NEWADDR, NLM_F_CREATE, 3, address).finish(1);
+        let (nlh, payload) = parse_header::<nlmsghdr>(&buf).unwrap();
+        assert_eq!(nlh.nlmsg_len as usize, buf.len());
+        assert_eq!(nlh.nlmsg_type, RTM_NEWADDR);
+        assert_eq!(buf.len() % 4, 0);
+        parse_address(payload).unwrap()
+    }
+
+    #[test]
+    fn ipv4_address() {
+        let mut address = Address::new("10.1.2.3/24".parse().unwrap());
+        address.broadcast = Some(Ipv4Addr::new(10, 1, 2, 255));
+        address.label = Some("eth0:1".to_string());
+        address.flags = AddressFlags::SECONDARY | AddressFlags::PERMANENT;
+        address.valid_lifetime = Some(600);
+        assert_eq!(round_trip(&address), Some((3, address)));
+    }
+
+    #[test]
+    fn ipv6_address() {
+        let mut address = Address::new("2001:db8::5/64".parse().unwrap());
+        address.scope = Scope::Link;
+        // Past the 8 bits of ifa_flags, carried by IFA_FLAGS
+        address.flags = AddressFlags::NODAD | AddressFlags::NOPREFIXROUTE;
+        address.preferred_lifetime = Some(0);
+        assert_eq!(round_trip(&address), Some((3, address)));
+    }
+
+    #[test]
+    fn peer_address() {
+        let mut address = Address::new("10.0.0.1/32".parse().unwrap());
+        address.peer = Some(IpAddr::from([10, 0, 0, 2]));
+        assert_eq!(round_trip(&address), Some((3, address)));
+    }
+}
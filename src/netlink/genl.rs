@@ -0,0 +1,43 @@
+//! Generic netlink, carrying the families registered by modules such as wireguard over NETLINK_GENERIC
+//!
+//! The message type of a family is an id allocated when it registers, which
+//! the `nlctrl` family resolves from its name.
+use super::*;
+
+const GENL_ID_CTRL: u16 = 0x10;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct genlmsghdr {
+    pub cmd: u8,
+    pub version: u8,
+    pub reserved: u16,
+}
+
+impl genlmsghdr {
+    pub fn new(cmd: u8, version: u8) -> genlmsghdr {
+        genlmsghdr {
+            cmd,
+            version,
+            reserved: 0,
+        }
+    }
+}
+
+/// Message type of the family `name`, failing when its module isn't loaded
+pub fn family(sock: &NetlinkSocket, name: &str) -> Result<u16> {
+    let mut msg = Message::new(GENL_ID_CTRL, 0, &genlmsghdr::new(CTRL_CMD_GETFAMILY, 1));
+    msg.attr_str(CTRL_ATTR_FAMILY_NAME, name);
+    let (_, payload) = sock.get(msg).map_err(|e| match e.downcast_ref::<Errno>() {
+        Some(Errno::ENOENT) => anyhow!("Generic netlink family '{}' not found, is the module loaded?", name),
+        _ => e.context(format!("Failed to resolve generic netlink family '{}'", name)),
+    })?;
+    let (_, data) = parse_header::<genlmsghdr>(&payload)?;
+    match attrs(data).find(|(ty, _)| *ty == CTRL_ATTR_FAMILY_ID) {
+        Some((_, id)) => parse_u16(id),
+        None => bail!("Kernel sent no id for generic netlink family '{}'", name),
+    }
+}
@@ -0,0 +1,457 @@
+//! RTM_NEWLINK, RTM_DELLINK, RTM_GETLINK and RTM_SETLINK, see rtnetlink(7)
+//!
+//! Bridge VLANs are managed with the same messages in the AF_BRIDGE family.
+use super::*;
+use crate::bridge::{BridgeOptions, BridgeVlan};
+use crate::interface::Interface;
+use crate::link::{Link, LinkKind};
+use crate::overlay::{GeneveOptions, VxlanOptions};
+use crate::tunnel::TunnelOptions;
+use std::os::unix::io::RawFd;
+
+const RTM_NEWLINK: u16 = 16;
+const RTM_DELLINK: u16 = 17;
+const RTM_GETLINK: u16 = 18;
+const RTM_SETLINK: u16 = 19;
+
+const IFLA_ADDRESS: u16 = 1;
+const IFLA_IFNAME: u16 = 3;
+const IFLA_MTU: u16 = 4;
+const IFLA_LINK: u16 = 5;
+const IFLA_MASTER: u16 = 10;
+const IFLA_LINKINFO: u16 = 18;
+const IFLA_AF_SPEC: u16 = 26;
+const IFLA_NET_NS_FD: u16 = 28;
+const IFLA_EXT_MASK: u16 = 29;
+const IFLA_MIN_MTU: u16 = 50;
+const IFLA_MAX_MTU: u16 = 51;
+
+/// IFLA_EXT_MASK asking for the VLANs of bridge ports
+const RTEXT_FILTER_BRVLAN: u32 = 1 << 1;
+
+/// Clock ticks per second of the times of bridges (USER_HZ)
+const USER_HZ: u32 = 100;
+
+// Attributes nested in IFLA_LINKINFO
+const IFLA_INFO_KIND: u16 = 1;
+const IFLA_INFO_DATA: u16 = 2;
+
+// IFLA_INFO_DATA of veth links
+const VETH_INFO_PEER: u16 = 1;
+
+// IFLA_INFO_DATA of bridge links
+const IFLA_BR_FORWARD_DELAY: u16 = 1;
+const IFLA_BR_AGEING_TIME: u16 = 4;
+const IFLA_BR_STP_STATE: u16 = 5;
+const IFLA_BR_VLAN_FILTERING: u16 = 7;
+
+// IFLA_INFO_DATA of bond links
+const IFLA_BOND_MODE: u16 = 1;
+const IFLA_BOND_MIIMON: u16 = 3;
+const IFLA_BOND_XMIT_HASH_POLICY: u16 = 14;
+const IFLA_BOND_AD_LACP_RATE: u16 = 21;
+
+// IFLA_INFO_DATA of vlan links
+const IFLA_VLAN_ID: u16 = 1;
+const IFLA_VLAN_PROTOCOL: u16 = 5;
+
+// IFLA_INFO_DATA of macvlan and macvtap links
+const IFLA_MACVLAN_MODE: u16 = 1;
+
+// IFLA_INFO_DATA of ipvlan links
+const IFLA_IPVLAN_MODE: u16 = 1;
+
+// IFLA_INFO_DATA of vxlan links, the remote VTEP being given as the group
+const IFLA_VXLAN_ID: u16 = 1;
+const IFLA_VXLAN_GROUP: u16 = 2;
+const IFLA_VXLAN_LINK: u16 = 3;
+const IFLA_VXLAN_LOCAL: u16 = 4;
+const IFLA_VXLAN_LEARNING: u16 = 7;
+const IFLA_VXLAN_PORT: u16 = 15;
+const IFLA_VXLAN_GROUP6: u16 = 16;
+const IFLA_VXLAN_LOCAL6: u16 = 17;
+
+// IFLA_INFO_DATA of geneve links
+const IFLA_GENEVE_ID: u16 = 1;
+const IFLA_GENEVE_REMOTE: u16 = 2;
+const IFLA_GENEVE_PORT: u16 = 5;
+const IFLA_GENEVE_REMOTE6: u16 = 7;
+
+// IFLA_INFO_DATA of ipip, sit and ip6tnl links
+const IFLA_IPTUN_LINK: u16 = 1;
+const IFLA_IPTUN_LOCAL: u16 = 2;
+const IFLA_IPTUN_REMOTE: u16 = 3;
+const IFLA_IPTUN_TTL: u16 = 4;
+const IFLA_IPTUN_PMTUDISC: u16 = 10;
+
+// IFLA_INFO_DATA of gre links
+const IFLA_GRE_LINK: u16 = 1;
+const IFLA_GRE_IFLAGS: u16 = 2;
+const IFLA_GRE_OFLAGS: u16 = 3;
+const IFLA_GRE_IKEY: u16 = 4;
+const IFLA_GRE_OKEY: u16 = 5;
+const IFLA_GRE_LOCAL: u16 = 6;
+const IFLA_GRE_REMOTE: u16 = 7;
+const IFLA_GRE_TTL: u16 = 8;
+const IFLA_GRE_PMTUDISC: u16 = 10;
+
+/// IFLA_GRE_IFLAGS and IFLA_GRE_OFLAGS telling a key is used
+const GRE_KEY: u16 = 0x2000;
+
+// IFLA_AF_SPEC of AF_BRIDGE messages
+const IFLA_BRIDGE_VLAN_INFO: u16 = 2;
+
+// flags of a bridge_vlan_info
+const BRIDGE_VLAN_INFO_PVID: u16 = 1 << 1;
+const BRIDGE_VLAN_INFO_UNTAGGED: u16 = 1 << 2;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct ifinfomsg {
+    ifi_family: u8,
+    ifi_pad: u8,
+    ifi_type: u16,
+    ifi_index: i32,
+    ifi_flags: u32,
+    ifi_change: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct bridge_vlan_info {
+    flags: u16,
+    vid: u16,
+}
+
+/// Clock ticks of a bridge time of `seconds`
+fn ticks(seconds: u32, name: &str) -> Result<u32> {
+    seconds
+        .checked_mul(USER_HZ)
+        .ok_or_else(|| anyhow!("Bridge {} of {} seconds is too long", name, seconds))
+}
+
+/// Append the IFLA_INFO_DATA of a bridge, if any option is set
+fn bridge_data(msg: &mut Message, options: &BridgeOptions) -> Result<()> {
+    if options.is_empty() {
+        return Ok(());
+    }
+    let data = msg.nest_begin(IFLA_INFO_DATA);
+    if let Some(stp) = options.stp {
+        msg.attr_u32(IFLA_BR_STP_STATE, stp as u32);
+    }
+    if let Some(forward_delay) = options.forward_delay {
+        msg.attr_u32(IFLA_BR_FORWARD_DELAY, ticks(forward_delay, "forward delay")?);
+    }
+    if let Some(ageing_time) = options.ageing_time {
+        msg.attr_u32(IFLA_BR_AGEING_TIME, ticks(ageing_time, "ageing time")?);
+    }
+    if let Some(vlan_filtering) = options.vlan_filtering {
+        msg.attr_u8(IFLA_BR_VLAN_FILTERING, vlan_filtering as u8);
+    }
+    msg.nest_end(data);
+    Ok(())
+}
+
+/// Append the IFLA_INFO_DATA of a vxlan
+fn vxlan_data(msg: &mut Message, options: &VxlanOptions) -> Result<()> {
+    let data = msg.nest_begin(IFLA_INFO_DATA);
+    msg.attr_u32(IFLA_VXLAN_ID, options.vni);
+    if let Some(address) = options.remote.as_ref().or(options.group.as_ref()) {
+        let ty = if address.is_ipv4() { IFLA_VXLAN_GROUP } else { IFLA_VXLAN_GROUP6 };
+        msg.attr_ip(ty, address);
+    }
+    if let Some(local) = &options.local {
+        let ty = if local.is_ipv4() { IFLA_VXLAN_LOCAL } else { IFLA_VXLAN_LOCAL6 };
+        msg.attr_ip(ty, local);
+    }
+    if let Some(parent) = &options.parent {
+        msg.attr_u32(IFLA_VXLAN_LINK, Interface::new(parent)?.index()? as u32);
+    }
+    if let Some(port) = options.port {
+        msg.attr_be16(IFLA_VXLAN_PORT, port);
+    }
+    if let Some(learning) = options.learning {
+        msg.attr_u8(IFLA_VXLAN_LEARNING, learning as u8);
+    }
+    msg.nest_end(data);
+    Ok(())
+}
+
+/// Append the IFLA_INFO_DATA of a geneve
+fn geneve_data(msg: &mut Message, options: &GeneveOptions) {
+    let data = msg.nest_begin(IFLA_INFO_DATA);
+    msg.attr_u32(IFLA_GENEVE_ID, options.vni);
+    let ty = if options.remote.is_ipv4() { IFLA_GENEVE_REMOTE } else { IFLA_GENEVE_REMOTE6 };
+    msg.attr_ip(ty, &options.remote);
+    if let Some(port) = options.port {
+        msg.attr_be16(IFLA_GENEVE_PORT, port);
+    }
+    msg.nest_end(data);
+}
+
+/// Append the IFLA_INFO_DATA of a tunnel, with the IFLA_GRE_* attributes when `gre` is set
+fn tunnel_data(msg: &mut Message, options: &TunnelOptions, gre: bool) -> Result<()> {
+    // The IFLA_GRE_* key attributes are IFLA_IPTUN_* addresses and hop limits of the other tunnels
+    if options.key.is_some() && !gre {
+        bail!("Keys only apply to GRE tunnels");
+    }
+    // The attributes of both sets match, but for the keys which only GRE has
+    let (link, local, remote, ttl, pmtudisc) = if gre {
+        (IFLA_GRE_LINK, IFLA_GRE_LOCAL, IFLA_GRE_REMOTE, IFLA_GRE_TTL, IFLA_GRE_PMTUDISC)
+    } else {
+        (IFLA_IPTUN_LINK, IFLA_IPTUN_LOCAL, IFLA_IPTUN_REMOTE, IFLA_IPTUN_TTL, IFLA_IPTUN_PMTUDISC)
+    };
+    let data = msg.nest_begin(IFLA_INFO_DATA);
+    if let Some(parent) = &options.parent {
+        msg.attr_u32(link, Interface::new(parent)?.index()? as u32);
+    }
+    if let Some(address) = &options.local {
+        msg.attr_ip(local, address);
+    }
+    if let Some(address) = &options.remote {
+        msg.attr_ip(remote, address);
+    }
+    if let Some(value) = options.ttl {
+        msg.attr_u8(ttl, value);
+    }
+    if let Some(value) = options.pmtudisc {
+        msg.attr_u8(pmtudisc, value as u8);
+    }
+    if let Some(key) = options.key {
+        msg.attr_be16(IFLA_GRE_IFLAGS, GRE_KEY);
+        msg.attr_be16(IFLA_GRE_OFLAGS, GRE_KEY);
+        msg.attr_be32(IFLA_GRE_IKEY, key);
+        msg.attr_be32(IFLA_GRE_OKEY, key);
+    }
+    msg.nest_end(data);
+    Ok(())
+}
+
+/// Append the IFLA_INFO_DATA of `kind`, if it has any
+fn info_data(msg: &mut Message, kind: &LinkKind) -> Result<()> {
+    match kind {
+        LinkKind::Veth { peer } => {
+            let data = msg.nest_begin(IFLA_INFO_DATA);
+            // The peer is described by a whole link message of its own
+            let info = msg.nest_header(VETH_INFO_PEER, &ifinfomsg::default());
+            msg.attr_str(IFLA_IFNAME, peer);
+            msg.nest_end(info);
+            msg.nest_end(data);
+        }
+        LinkKind::Bond(options) => {
+            let data = msg.nest_begin(IFLA_INFO_DATA);
+            // The mode comes first, other options depending on it
+            msg.attr_u8(IFLA_BOND_MODE, u8::from(options.mode));
+            if let Some(miimon) = options.miimon {
+                msg.attr_u32(IFLA_BOND_MIIMON, miimon);
+            }
+            if let Some(lacp_rate) = options.lacp_rate {
+                msg.attr_u8(IFLA_BOND_AD_LACP_RATE, u8::from(lacp_rate));
+            }
+            if let Some(policy) = options.xmit_hash_policy {
+                msg.attr_u8(IFLA_BOND_XMIT_HASH_POLICY, u8::from(policy));
+            }
+            msg.nest_end(data);
+        }
+        LinkKind::Vlan { id, protocol, .. } => {
+            let data = msg.nest_begin(IFLA_INFO_DATA);
+            msg.attr_u16(IFLA_VLAN_ID, *id);
+            msg.attr_be16(IFLA_VLAN_PROTOCOL, protocol.ethertype());
+            msg.nest_end(data);
+        }
+        LinkKind::Bridge(options) => bridge_data(msg, options)?,
+        LinkKind::Vxlan(options) => vxlan_data(msg, options)?,
+        LinkKind::Geneve(options) => geneve_data(msg, options),
+        LinkKind::Macvlan { mode, .. } | LinkKind::Macvtap { mode, .. } => {
+            let data = msg.nest_begin(IFLA_INFO_DATA);
+            msg.attr_u32(IFLA_MACVLAN_MODE, u32::from(*mode));
+            msg.nest_end(data);
+        }
+        LinkKind::Ipvlan { mode, .. } => {
+            let data = msg.nest_begin(IFLA_INFO_DATA);
+            msg.attr_u16(IFLA_IPVLAN_MODE, u16::from(*mode));
+            msg.nest_end(data);
+        }
+        LinkKind::Gre(options) => tunnel_data(msg, options, true)?,
+        LinkKind::Ipip(options) | LinkKind::Sit(options) => tunnel_data(msg, options, false)?,
+        LinkKind::Ip6tnl(options) => {
+            // ip6tnl ignores IFLA_IPTUN_PMTUDISC, its packets never being fragmented on the way
+            if options.pmtudisc.is_some() {
+                bail!("Path MTU discovery only applies to IPv4 tunnels");
+            }
+            tunnel_data(msg, options, false)?
+        }
+        LinkKind::Dummy | LinkKind::Tun(_) | LinkKind::Tap(_) | LinkKind::Wireguard => {}
+    }
+    Ok(())
+}
+
+/// Append the IFLA_LINKINFO telling the kind of a link and its parameters
+fn link_info(msg: &mut Message, kind: &LinkKind) -> Result<()> {
+    let info = msg.nest_begin(IFLA_LINKINFO);
+    msg.attr_str(IFLA_INFO_KIND, &kind.to_string());
+    info_data(msg, kind)?;
+    msg.nest_end(info);
+    Ok(())
+}
+
+/// Create `link`
+pub fn add(sock: &NetlinkSocket, link: &Link) -> Result<()> {
+    let header = ifinfomsg::default();
+    let mut msg = Message::new(RTM_NEWLINK, NLM_F_CREATE | NLM_F_EXCL, &header);
+    msg.attr_str(IFLA_IFNAME, &link.name);
+    if let Some(mtu) = link.mtu {
+        msg.attr_u32(IFLA_MTU, mtu);
+    }
+    if let Some(mac) = &link.mac {
+        msg.attr(IFLA_ADDRESS, &mac.octets());
+    }
+    if let Some(parent) = link.kind.parent() {
+        msg.attr_u32(IFLA_LINK, Interface::new(parent)?.index()? as u32);
+    }
+
+    link_info(&mut msg, &link.kind)?;
+
+    sock.request(msg)
+        .map_err(|e| e.context(format!("Failed to create {} link '{}'", link.kind, link.name)))
+}
+
+/// Delete the link `index`
+pub fn del(sock: &NetlinkSocket, index: i32) -> Result<()> {
+    let header = ifinfomsg {
+        ifi_index: index,
+        ..Default::default()
+    };
+    sock.request(Message::new(RTM_DELLINK, 0, &header))
+}
+
+/// Smallest and largest MTU of the link `index`, unset on kernels older than 4.19
+///
+/// A largest MTU of 0 stands for no limit.
+pub fn mtu_range(sock: &NetlinkSocket, index: i32) -> Result<Option<(u32, u32)>> {
+    let header = ifinfomsg {
+        ifi_index: index,
+        ..Default::default()
+    };
+    let (_, payload) = sock.get(Message::new(RTM_GETLINK, 0, &header))?;
+    let (_, data) = parse_header::<ifinfomsg>(&payload)?;
+    let (mut min, mut max) = (None, None);
+    for (ty, data) in attrs(data) {
+        match ty {
+            IFLA_MIN_MTU => min = Some(parse_u32(data)?),
+            IFLA_MAX_MTU => max = Some(parse_u32(data)?),
+            _ => {}
+        }
+    }
+    Ok(min.zip(max))
+}
+
+/// Enslave the link `index` to the bond or bridge `master`, or release it from its master when 0
+pub fn set_master(sock: &NetlinkSocket, index: i32, master: u32) -> Result<()> {
+    let header = ifinfomsg {
+        ifi_index: index,
+        ..Default::default()
+    };
+    let mut msg = Message::new(RTM_SETLINK, 0, &header);
+    msg.attr_u32(IFLA_MASTER, master);
+    sock.request(msg)
+}
+
+/// Move the link `index` into the network namespace open as `netns`
+pub fn set_netns(sock: &NetlinkSocket, index: i32, netns: RawFd) -> Result<()> {
+    let header = ifinfomsg {
+        ifi_index: index,
+        ..Default::default()
+    };
+    let mut msg = Message::new(RTM_SETLINK, 0, &header);
+    msg.attr_u32(IFLA_NET_NS_FD, netns as u32);
+    sock.request(msg)
+}
+
+/// Change the parameters of the link `index` to those of `kind`, which must be its kind
+pub fn change(sock: &NetlinkSocket, index: i32, kind: &LinkKind) -> Result<()> {
+    let header = ifinfomsg {
+        ifi_index: index,
+        ..Default::default()
+    };
+    let mut msg = Message::new(RTM_NEWLINK, 0, &header);
+    link_info(&mut msg, kind)?;
+    sock.request(msg)
+}
+
+/// Change the options of the bridge `index`
+pub fn set_bridge_options(sock: &NetlinkSocket, index: i32, options: &BridgeOptions) -> Result<()> {
+    change(sock, index, &LinkKind::Bridge(options.clone()))
+}
+
+/// Build the AF_BRIDGE message adding or removing `vlan` of the port `index`
+fn vlan_message(ty: u16, index: i32, vlan: &BridgeVlan) -> Message {
+    let header = ifinfomsg {
+        ifi_family: libc::AF_BRIDGE as u8,
+        ifi_index: index,
+        ..Default::default()
+    };
+    let mut info = bridge_vlan_info {
+        flags: 0,
+        vid: vlan.vid,
+    };
+    if vlan.pvid {
+        info.flags |= BRIDGE_VLAN_INFO_PVID;
+    }
+    if vlan.untagged {
+        info.flags |= BRIDGE_VLAN_INFO_UNTAGGED;
+    }
+    let mut msg = Message::new(ty, 0, &header);
+    let spec = msg.nest_begin(IFLA_AF_SPEC);
+    msg.attr_struct(IFLA_BRIDGE_VLAN_INFO, &info);
+    msg.nest_end(spec);
+    msg
+}
+
+/// Add the port `index` to `vlan`, or update its flags there
+pub fn add_vlan(sock: &NetlinkSocket, index: i32, vlan: &BridgeVlan) -> Result<()> {
+    sock.request(vlan_message(RTM_SETLINK, index, vlan))
+}
+
+/// Remove the port `index` from `vlan`
+pub fn del_vlan(sock: &NetlinkSocket, index: i32, vlan: &BridgeVlan) -> Result<()> {
+    sock.request(vlan_message(RTM_DELLINK, index, vlan))
+}
+
+/// VLANs of every bridge port, and of the bridges themselves
+pub fn vlans(sock: &NetlinkSocket) -> Result<Vec<BridgeVlan>> {
+    let header = ifinfomsg {
+        ifi_family: libc::AF_BRIDGE as u8,
+        ..Default::default()
+    };
+    let mut msg = Message::new(RTM_GETLINK, 0, &header);
+    msg.attr_u32(IFLA_EXT_MASK, RTEXT_FILTER_BRVLAN);
+
+    let mut vlans = Vec::new();
+    for payload in sock.dump(msg)? {
+        let (header, data) = parse_header::<ifinfomsg>(&payload)?;
+        let mut name = None;
+        let mut infos = Vec::new();
+        for (ty, data) in attrs(data) {
+            match ty {
+                IFLA_IFNAME => name = Some(parse_str(data)),
+                IFLA_AF_SPEC => {
+                    for (ty, data) in attrs(data) {
+                        if ty == IFLA_BRIDGE_VLAN_INFO {
+                            infos.push(parse_header::<bridge_vlan_info>(data)?.0);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let name = name.ok_or_else(|| anyhow!("Link {} has no name", header.ifi_index))?;
+        vlans.extend(infos.into_iter().map(|info| BridgeVlan {
+            interface: name.clone(),
+            vid: info.vid,
+            pvid: info.flags & BRIDGE_VLAN_INFO_PVID != 0,
+            untagged: info.flags & BRIDGE_VLAN_INFO_UNTAGGED != 0,
+        }));
+    }
+    Ok(vlans)
+}
@@ -0,0 +1,550 @@
+//! Minimal netlink client used by the rtnetlink backend, see netlink(7) and rtnetlink(7)
+//!
+//! Messages are built and parsed by hand: a `nlmsghdr`, a fixed family
+//! header such as `ifaddrmsg`, or the `genlmsghdr` of generic netlink
+//! families, then a list of 4 byte aligned attributes.
+#![allow(non_camel_case_types)]
+pub mod addr;
+pub mod genl;
+pub mod link;
+pub mod neigh;
+pub mod route;
+pub mod rule;
+pub mod wireguard;
+
+use anyhow::{anyhow, bail, Result};
+use nix::errno::Errno;
+use std::convert::TryFrom;
+use std::mem;
+use std::net::IpAddr;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+pub const NETLINK_ROUTE: libc::c_int = 0;
+pub const NETLINK_GENERIC: libc::c_int = 16;
+
+const SOL_NETLINK: libc::c_int = 270;
+const NETLINK_EXT_ACK: libc::c_int = 11;
+
+pub const NLM_F_REQUEST: u16 = 0x01;
+pub const NLM_F_MULTI: u16 = 0x02;
+pub const NLM_F_ACK: u16 = 0x04;
+pub const NLM_F_DUMP: u16 = 0x300;
+pub const NLM_F_REPLACE: u16 = 0x100;
+pub const NLM_F_EXCL: u16 = 0x200;
+pub const NLM_F_CREATE: u16 = 0x400;
+pub const NLM_F_APPEND: u16 = 0x800;
+const NLM_F_CAPPED: u16 = 0x100;
+const NLM_F_ACK_TLVS: u16 = 0x200;
+
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const NLMSGERR_ATTR_MSG: u16 = 1;
+
+/// Set on nested attributes by some families, ignored when parsing
+const NLA_F_NESTED: u16 = 1 << 15;
+const NLA_TYPE_MASK: u16 = !(NLA_F_NESTED | (1 << 14));
+
+const NLMSG_HDRLEN: usize = mem::size_of::<nlmsghdr>();
+const RECV_BUFFER_SIZE: usize = 64 * 1024;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct nlmsghdr {
+    pub nlmsg_len: u32,
+    pub nlmsg_type: u16,
+    pub nlmsg_flags: u16,
+    pub nlmsg_seq: u32,
+    pub nlmsg_pid: u32,
+}
+
+/// Round `len` up to the 4 byte alignment of netlink messages and attributes
+pub fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// Read a fixed size header from the start of `data`, returning it and what follows it
+pub fn parse_header<T: Copy>(data: &[u8]) -> Result<(T, &[u8])> {
+    if data.len() < mem::size_of::<T>() {
+        bail!("Netlink message is too short");
+    }
+    let header = unsafe { std::ptr::read_unaligned(data.as_ptr() as *const T) };
+    let rest = &data[align(mem::size_of::<T>()).min(data.len())..];
+    Ok((header, rest))
+}
+
+/// Netlink request being built
+pub struct Message {
+    buf: Vec<u8>,
+}
+
+impl Message {
+    /// Message of type `ty` followed by the family header `header`
+    ///
+    /// `header` must be a `repr(C)` struct without padding.
+    pub fn new<T: Copy>(ty: u16, flags: u16, header: &T) -> Message {
+        let mut msg = Message { buf: Vec::with_capacity(256) };
+        let nlh = nlmsghdr {
+            nlmsg_type: ty,
+            nlmsg_flags: flags,
+            ..Default::default()
+        };
+        msg.push(&nlh);
+        msg.push(header);
+        msg
+    }
+
+    fn push<T: Copy>(&mut self, value: &T) {
+        let bytes = unsafe { std::slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) };
+        self.buf.extend_from_slice(bytes);
+        self.buf.resize(align(self.buf.len()), 0);
+    }
+
+    /// Append the attribute `ty` holding `data`
+    pub fn attr(&mut self, ty: u16, data: &[u8]) -> &mut Message {
+        let len = 4 + data.len();
+        self.buf.extend_from_slice(&(len as u16).to_ne_bytes());
+        self.buf.extend_from_slice(&ty.to_ne_bytes());
+        self.buf.extend_from_slice(data);
+        self.buf.resize(align(self.buf.len()), 0);
+        self
+    }
+
+    /// Append the attribute `ty` holding a `repr(C)` struct without padding
+    pub fn attr_struct<T: Copy>(&mut self, ty: u16, value: &T) -> &mut Message {
+        let bytes = unsafe { std::slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) };
+        self.attr(ty, bytes)
+    }
+
+    pub fn attr_u8(&mut self, ty: u16, value: u8) -> &mut Message {
+        self.attr(ty, &[value])
+    }
+
+    pub fn attr_u16(&mut self, ty: u16, value: u16) -> &mut Message {
+        self.attr(ty, &value.to_ne_bytes())
+    }
+
+    /// Append a 16 bit attribute in network byte order, such as a port
+    pub fn attr_be16(&mut self, ty: u16, value: u16) -> &mut Message {
+        self.attr(ty, &value.to_be_bytes())
+    }
+
+    /// Append a 32 bit attribute in network byte order, such as a GRE key
+    pub fn attr_be32(&mut self, ty: u16, value: u32) -> &mut Message {
+        self.attr(ty, &value.to_be_bytes())
+    }
+
+    pub fn attr_u32(&mut self, ty: u16, value: u32) -> &mut Message {
+        self.attr(ty, &value.to_ne_bytes())
+    }
+
+    /// Append a NUL terminated string attribute
+    pub fn attr_str(&mut self, ty: u16, value: &str) -> &mut Message {
+        let mut data = value.as_bytes().to_vec();
+        data.push(0);
+        self.attr(ty, &data)
+    }
+
+    /// Append an IPv4 or IPv6 address attribute, 4 or 16 bytes
+    pub fn attr_ip(&mut self, ty: u16, addr: &IpAddr) -> &mut Message {
+        match addr {
+            IpAddr::V4(addr) => self.attr(ty, &addr.octets()),
+            IpAddr::V6(addr) => self.attr(ty, &addr.octets()),
+        }
+    }
+
+    /// Start a nested attribute, closed by `nest_end` with the returned offset
+    pub fn nest_begin(&mut self, ty: u16) -> usize {
+        let start = self.buf.len();
+        self.attr(ty | NLA_F_NESTED, &[]);
+        start
+    }
+
+    /// Close the nested attribute started at `start`
+    pub fn nest_end(&mut self, start: usize) -> &mut Message {
+        let len = (self.buf.len() - start) as u16;
+        self.buf[start..start + 2].copy_from_slice(&len.to_ne_bytes());
+        self
+    }
+
+    /// Start a nested header and attributes, as used by veth peers
+    pub fn nest_header<T: Copy>(&mut self, ty: u16, header: &T) -> usize {
+        let start = self.nest_begin(ty);
+        self.push(header);
+        start
+    }
+
+    fn finish(mut self, seq: u32) -> Vec<u8> {
+        let len = self.buf.len() as u32;
+        self.buf[0..4].copy_from_slice(&len.to_ne_bytes());
+        self.buf[8..12].copy_from_slice(&seq.to_ne_bytes());
+        self.buf
+    }
+
+    fn add_flags(&mut self, flags: u16) {
+        let current = u16::from_ne_bytes([self.buf[6], self.buf[7]]);
+        self.buf[6..8].copy_from_slice(&(current | flags).to_ne_bytes());
+    }
+}
+
+/// Iterator over the attributes of a message, yielding their type and payload
+pub struct Attrs<'a> {
+    data: &'a [u8],
+}
+
+/// Attributes found in `data`
+pub fn attrs(data: &[u8]) -> Attrs<'_> {
+    Attrs { data }
+}
+
+impl<'a> Iterator for Attrs<'a> {
+    type Item = (u16, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.len() < 4 {
+            return None;
+        }
+        let len = u16::from_ne_bytes([self.data[0], self.data[1]]) as usize;
+        let ty = u16::from_ne_bytes([self.data[2], self.data[3]]) & NLA_TYPE_MASK;
+        if len < 4 || len > self.data.len() {
+            return None;
+        }
+        let payload = &self.data[4..len];
+        self.data = &self.data[align(len).min(self.data.len())..];
+        Some((ty, payload))
+    }
+}
+
+pub fn parse_u8(data: &[u8]) -> Result<u8> {
+    data.first().copied().ok_or_else(|| anyhow!("Netlink attribute is too short"))
+}
+
+pub fn parse_u16(data: &[u8]) -> Result<u16> {
+    Ok(u16::from_ne_bytes(parse_header::<[u8; 2]>(data)?.0))
+}
+
+pub fn parse_u32(data: &[u8]) -> Result<u32> {
+    Ok(u32::from_ne_bytes(parse_header::<[u8; 4]>(data)?.0))
+}
+
+pub fn parse_u64(data: &[u8]) -> Result<u64> {
+    Ok(u64::from_ne_bytes(parse_header::<[u8; 8]>(data)?.0))
+}
+
+/// Parse a string attribute, NUL terminated or not
+pub fn parse_str(data: &[u8]) -> String {
+    let len = data.iter().position(|byte| *byte == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..len]).into_owned()
+}
+
+/// Parse an IPv4 or IPv6 address attribute
+pub fn parse_ip(data: &[u8]) -> Result<IpAddr> {
+    match data.len() {
+        4 => Ok(IpAddr::from(<[u8; 4]>::try_from(data)?)),
+        16 => Ok(IpAddr::from(<[u8; 16]>::try_from(data)?)),
+        len => bail!("Unexpected address length {}", len),
+    }
+}
+
+/// Socket speaking a netlink protocol with the kernel
+#[derive(Debug)]
+pub struct NetlinkSocket {
+    fd: OwnedFd,
+    seq: AtomicU32,
+}
+
+impl NetlinkSocket {
+    /// Open a netlink socket of `protocol`, closed on exec
+    pub fn open(protocol: libc::c_int) -> Result<NetlinkSocket> {
+        let fd = unsafe { libc::socket(libc::AF_NETLINK, libc::SOCK_RAW | libc::SOCK_CLOEXEC, protocol) };
+        if fd < 0 {
+            bail!("Failed to open netlink socket: {}", Errno::last());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        let res = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if res < 0 {
+            bail!("Failed to bind netlink socket: {}", Errno::last());
+        }
+
+        // Best effort, older kernels don't explain their errors
+        let enable: libc::c_int = 1;
+        unsafe {
+            libc::setsockopt(
+                fd.as_raw_fd(),
+                SOL_NETLINK,
+                NETLINK_EXT_ACK,
+                &enable as *const libc::c_int as *const libc::c_void,
+                mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+
+        Ok(NetlinkSocket {
+            fd,
+            seq: AtomicU32::new(1),
+        })
+    }
+
+    fn send(&self, msg: Message) -> Result<u32> {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        let buf = msg.finish(seq);
+        let res = unsafe { libc::send(self.fd.as_raw_fd(), buf.as_ptr() as *const libc::c_void, buf.len(), 0) };
+        if res < 0 {
+            bail!("Failed to send netlink message: {}", Errno::last());
+        }
+        Ok(seq)
+    }
+
+    /// Receive the replies to `seq` until it is acknowledged or, for dumps, done
+    fn recv(&self, seq: u32) -> Result<Vec<(u16, Vec<u8>)>> {
+        let mut replies = Vec::new();
+        let mut buf = vec![0u8; RECV_BUFFER_SIZE];
+        loop {
+            let len = unsafe { libc::recv(self.fd.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+            if len < 0 {
+                match Errno::last() {
+                    Errno::EINTR => continue,
+                    errno => bail!("Failed to receive netlink message: {}", errno),
+                }
+            }
+
+            let mut data = &buf[..len as usize];
+            while data.len() >= NLMSG_HDRLEN {
+                let (nlh, _) = parse_header::<nlmsghdr>(data)?;
+                let msg_len = nlh.nlmsg_len as usize;
+                if msg_len < NLMSG_HDRLEN || msg_len > data.len() {
+                    bail!("Received a truncated netlink message");
+                }
+                let payload = &data[NLMSG_HDRLEN..msg_len];
+                data = &data[align(msg_len).min(data.len())..];
+
+                // Left over replies to an earlier request
+                if nlh.nlmsg_seq != seq {
+                    continue;
+                }
+                match nlh.nlmsg_type {
+                    NLMSG_ERROR => {
+                        check_error(&nlh, payload)?;
+                        return Ok(replies);
+                    }
+                    NLMSG_DONE => return Ok(replies),
+                    ty => replies.push((ty, payload.to_vec())),
+                }
+                if nlh.nlmsg_flags & NLM_F_MULTI == 0 && nlh.nlmsg_type != NLMSG_ERROR {
+                    return Ok(replies);
+                }
+            }
+        }
+    }
+
+    /// Send a request and wait for the kernel to acknowledge it
+    pub fn request(&self, mut msg: Message) -> Result<()> {
+        msg.add_flags(NLM_F_REQUEST | NLM_F_ACK);
+        let seq = self.send(msg)?;
+        self.recv(seq)?;
+        Ok(())
+    }
+
+    /// Send a request expecting a single reply, returning its type and payload
+    pub fn get(&self, mut msg: Message) -> Result<(u16, Vec<u8>)> {
+        msg.add_flags(NLM_F_REQUEST);
+        let seq = self.send(msg)?;
+        self.recv(seq)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Kernel sent no reply"))
+    }
+
+    /// Send a dump request, returning the payload of every message received
+    pub fn dump(&self, mut msg: Message) -> Result<Vec<Vec<u8>>> {
+        msg.add_flags(NLM_F_REQUEST | NLM_F_DUMP);
+        let seq = self.send(msg)?;
+        Ok(self.recv(seq)?.into_iter().map(|(_, payload)| payload).collect())
+    }
+}
+
+/// Turn an `nlmsgerr` into an error, a zero error code being an acknowledgment
+fn check_error(nlh: &nlmsghdr, payload: &[u8]) -> Result<()> {
+    let (error, rest) = parse_header::<i32>(payload)?;
+    if error == 0 {
+        return Ok(());
+    }
+
+    let errno = Errno::from_i32(-error);
+    if nlh.nlmsg_flags & NLM_F_ACK_TLVS != 0 {
+        // The original request follows, whole unless capped to its header
+        let (request, _) = parse_header::<nlmsghdr>(rest)?;
+        let request_len = if nlh.nlmsg_flags & NLM_F_CAPPED != 0 {
+            NLMSG_HDRLEN
+        } else {
+            request.nlmsg_len as usize
+        };
+        let tlvs = &rest[align(request_len).min(rest.len())..];
+        if let Some((_, msg)) = attrs(tlvs).find(|(ty, _)| *ty == NLMSGERR_ATTR_MSG) {
+            return Err(anyhow!(errno).context(parse_str(msg)));
+        }
+    }
+    Err(anyhow!(errno))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IFLA_IFNAME: u16 = 3;
+    const IFLA_LINKINFO: u16 = 18;
+    const IFLA_INFO_KIND: u16 = 1;
+
+    /// Attributes of a message built by `build`, after its nlmsghdr and `header`
+    fn attrs_of<T: Copy>(header: &T, build: impl FnOnce(&mut Message)) -> Vec<u8> {
+        let mut msg = Message::new(16, 0, header);
+        build(&mut msg);
+        msg.buf[align(NLMSG_HDRLEN + mem::size_of::<T>())..].to_vec()
+    }
+
+    #[test]
+    fn message_header() {
+        let mut msg = Message::new(16, NLM_F_CREATE, &[1u8, 2, 3]);
+        msg.add_flags(NLM_F_REQUEST | NLM_F_ACK);
+        let buf = msg.finish(42);
+        // The 3 byte header is padded to 4
+        assert_eq!(buf.len(), NLMSG_HDRLEN + 4);
+        let (nlh, rest) = parse_header::<nlmsghdr>(&buf).unwrap();
+        assert_eq!(nlh.nlmsg_len as usize, buf.len());
+        assert_eq!(nlh.nlmsg_type, 16);
+        assert_eq!(nlh.nlmsg_flags, NLM_F_CREATE | NLM_F_REQUEST | NLM_F_ACK);
+        assert_eq!(nlh.nlmsg_seq, 42);
+        assert_eq!(rest, [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn attributes() {
+        let data = attrs_of(&0u32, |msg| {
+            msg.attr_str(IFLA_IFNAME, "eth0");
+            msg.attr_u32(4, 1500);
+            msg.attr_ip(1, &IpAddr::from([10, 0, 0, 1]));
+            msg.attr_ip(2, &"2001:db8::1".parse().unwrap());
+        });
+        // "eth0" and its NUL take 9 bytes, padded to 12
+        assert_eq!(data.len(), 12 + 8 + 8 + 20);
+        assert_eq!(&data[..4], [9, 0, IFLA_IFNAME as u8, 0]);
+
+        let parsed: Vec<(u16, &[u8])> = attrs(&data).collect();
+        assert_eq!(parsed.len(), 4);
+        assert_eq!(parsed[0].0, IFLA_IFNAME);
+        assert_eq!(parse_str(parsed[0].1), "eth0");
+        assert_eq!(parse_u32(parsed[1].1).unwrap(), 1500);
+        assert_eq!(parse_ip(parsed[2].1).unwrap(), IpAddr::from([10, 0, 0, 1]));
+        assert_eq!(parse_ip(parsed[3].1).unwrap(), "2001:db8::1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn nested_attributes() {
+        let kind = attrs_of(&(), |msg| {
+            msg.attr_str(IFLA_INFO_KIND, "veth");
+        });
+        let data = attrs_of(&0u32, |msg| {
+            msg.attr(IFLA_LINKINFO | NLA_F_NESTED, &kind);
+            msg.attr_str(IFLA_IFNAME, "veth0");
+        });
+
+        let mut parsed = attrs(&data);
+        let (ty, linkinfo) = parsed.next().unwrap();
+        // The nested flag is masked out
+        assert_eq!(ty, IFLA_LINKINFO);
+        let inner: Vec<(u16, &[u8])> = attrs(linkinfo).collect();
+        assert_eq!(inner.len(), 1);
+        assert_eq!(inner[0].0, IFLA_INFO_KIND);
+        assert_eq!(parse_str(inner[0].1), "veth");
+        let (ty, name) = parsed.next().unwrap();
+        assert_eq!((ty, parse_str(name).as_str()), (IFLA_IFNAME, "veth0"));
+        assert!(parsed.next().is_none());
+    }
+
+    #[test]
+    fn truncated_attributes() {
+        // Length past the end of the data
+        assert!(attrs(&[12, 0, 1, 0, 0, 0, 0, 0]).next().is_none());
+        // Length shorter than the attribute header
+        assert!(attrs(&[2, 0, 1, 0]).next().is_none());
+        assert!(parse_u32(&[1, 2]).is_err());
+        assert!(parse_ip(&[1, 2, 3]).is_err());
+        assert!(parse_header::<nlmsghdr>(&[0; 8]).is_err());
+    }
+
+    /// NLMSG_ERROR message carrying `error`, the header of the request, and the attributes added by `build`
+    fn error_message(error: i32, flags: u16, build: impl FnOnce(&mut Message)) -> Vec<u8> {
+        let mut msg = Message::new(NLMSG_ERROR, flags, &error);
+        msg.push(&nlmsghdr {
+            nlmsg_len: if flags & NLM_F_CAPPED != 0 { 64 } else { NLMSG_HDRLEN as u32 },
+            nlmsg_type: 20,
+            ..Default::default()
+        });
+        build(&mut msg);
+        msg.finish(1)
+    }
+
+    fn check(buf: &[u8]) -> Result<()> {
+        let (nlh, payload) = parse_header::<nlmsghdr>(buf).unwrap();
+        check_error(&nlh, payload)
+    }
+
+    #[test]
+    fn ack() {
+        assert!(check(&error_message(0, 0, |_| {})).is_ok());
+    }
+
+    #[test]
+    fn error() {
+        let e = check(&error_message(-libc::EEXIST, 0, |_| {})).unwrap_err();
+        assert_eq!(e.downcast_ref::<Errno>(), Some(&Errno::EEXIST));
+    }
+
+    #[test]
+    fn extended_error() {
+        for flags in [NLM_F_ACK_TLVS, NLM_F_ACK_TLVS | NLM_F_CAPPED] {
+            let buf = error_message(-libc::EINVAL, flags, |msg| {
+                msg.attr_str(NLMSGERR_ATTR_MSG, "Invalid prefix for given prefix length");
+            });
+            let e = check(&buf).unwrap_err();
+            assert_eq!(e.to_string(), "Invalid prefix for given prefix length");
+            assert_eq!(e.downcast_ref::<Errno>(), Some(&Errno::EINVAL));
+        }
+    }
+
+    #[test]
+    fn nest() {
+        let data = attrs_of(&0u32, |msg| {
+            let linkinfo = msg.nest_begin(IFLA_LINKINFO);
+            msg.attr_str(IFLA_INFO_KIND, "vlan");
+            let data = msg.nest_begin(2);
+            msg.attr_u16(1, 5);
+            msg.nest_end(data);
+            msg.nest_end(linkinfo);
+            msg.attr_str(IFLA_IFNAME, "eth0.5");
+        });
+        // Header, "vlan" and the nested VLAN id
+        assert_eq!(&data[..4], [4 + 12 + 4 + 8, 0, IFLA_LINKINFO as u8, (NLA_F_NESTED >> 8) as u8]);
+
+        let mut parsed = attrs(&data);
+        let (ty, linkinfo) = parsed.next().unwrap();
+        assert_eq!(ty, IFLA_LINKINFO);
+        let inner: Vec<(u16, &[u8])> = attrs(linkinfo).collect();
+        assert_eq!(inner.len(), 2);
+        assert_eq!((inner[0].0, parse_str(inner[0].1).as_str()), (IFLA_INFO_KIND, "vlan"));
+        assert_eq!(inner[1].0, 2);
+        let (ty, id) = attrs(inner[1].1).next().unwrap();
+        assert_eq!((ty, id), (1, &5u16.to_ne_bytes()[..]));
+        let (ty, name) = parsed.next().unwrap();
+        assert_eq!((ty, parse_str(name).as_str()), (IFLA_IFNAME, "eth0.5"));
+        assert!(parsed.next().is_none());
+    }
+}
@@ -0,0 +1,180 @@
+//! RTM_NEWNEIGH, RTM_DELNEIGH and RTM_GETNEIGH, see rtnetlink(7)
+//!
+//! Forwarding database entries are neighbours of the AF_BRIDGE family.
+use super::*;
+use crate::bridge::FdbEntry;
+use crate::interface::Interface;
+use crate::mac::MacAddr;
+use crate::neigh::{Neighbour, NeighbourState};
+
+const RTM_NEWNEIGH: u16 = 28;
+const RTM_DELNEIGH: u16 = 29;
+const RTM_GETNEIGH: u16 = 30;
+
+const NDA_DST: u16 = 1;
+const NDA_LLADDR: u16 = 2;
+const NDA_VLAN: u16 = 5;
+const NDA_MASTER: u16 = 9;
+
+// ndm_flags
+const NTF_SELF: u8 = 0x02;
+const NTF_MASTER: u8 = 0x04;
+const NTF_ROUTER: u8 = 0x80;
+
+/// Ethernet address held by a NDA_LLADDR, other link layer addresses being left out
+fn parse_mac(data: &[u8]) -> Option<MacAddr> {
+    <[u8; 6]>::try_from(data).ok().map(MacAddr::from)
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct ndmsg {
+    ndm_family: u8,
+    ndm_pad1: u8,
+    ndm_pad2: u16,
+    ndm_ifindex: i32,
+    ndm_state: u16,
+    ndm_flags: u8,
+    ndm_type: u8,
+}
+
+/// Build the message adding or removing `neighbour`
+fn neighbour_message(ty: u16, flags: u16, neighbour: &Neighbour) -> Result<Message> {
+    let header = ndmsg {
+        ndm_family: match neighbour.address {
+            IpAddr::V4(_) => libc::AF_INET as u8,
+            IpAddr::V6(_) => libc::AF_INET6 as u8,
+        },
+        ndm_ifindex: Interface::new(&neighbour.interface)?.index()?,
+        ndm_state: u16::from(neighbour.state),
+        ndm_flags: if neighbour.router { NTF_ROUTER } else { 0 },
+        ..Default::default()
+    };
+    let mut msg = Message::new(ty, flags, &header);
+    msg.attr_ip(NDA_DST, &neighbour.address);
+    if let Some(mac) = &neighbour.mac {
+        msg.attr(NDA_LLADDR, &mac.octets());
+    }
+    Ok(msg)
+}
+
+/// Add `neighbour`, replacing an existing entry when `replace` is set
+pub fn add(sock: &NetlinkSocket, neighbour: &Neighbour, replace: bool) -> Result<()> {
+    let flags = NLM_F_CREATE | if replace { NLM_F_REPLACE } else { NLM_F_EXCL };
+    sock.request(neighbour_message(RTM_NEWNEIGH, flags, neighbour)?)
+        .map_err(|e| e.context(format!("Failed to add neighbour '{}'", neighbour.address)))
+}
+
+/// Remove `neighbour`
+pub fn del(sock: &NetlinkSocket, neighbour: &Neighbour) -> Result<()> {
+    sock.request(neighbour_message(RTM_DELNEIGH, 0, neighbour)?)
+        .map_err(|e| e.context(format!("Failed to remove neighbour '{}'", neighbour.address)))
+}
+
+/// IPv4 and IPv6 neighbours of every interface
+pub fn list(sock: &NetlinkSocket) -> Result<Vec<Neighbour>> {
+    let header = ndmsg::default();
+    let mut neighbours = Vec::new();
+    for payload in sock.dump(Message::new(RTM_GETNEIGH, 0, &header))? {
+        let (header, data) = parse_header::<ndmsg>(&payload)?;
+        // Forwarding database entries are dumped as well
+        if header.ndm_family != libc::AF_INET as u8 && header.ndm_family != libc::AF_INET6 as u8 {
+            continue;
+        }
+        let mut address = None;
+        let mut mac = None;
+        for (ty, data) in attrs(data) {
+            match ty {
+                NDA_DST => address = Some(parse_ip(data)?),
+                NDA_LLADDR => mac = parse_mac(data),
+                _ => {}
+            }
+        }
+        let address = match address {
+            Some(address) => address,
+            None => continue,
+        };
+
+        neighbours.push(Neighbour {
+            address,
+            interface: Interface::from_index(header.ndm_ifindex as u32)?.name().to_string(),
+            mac,
+            state: NeighbourState::from(header.ndm_state),
+            router: header.ndm_flags & NTF_ROUTER != 0,
+        });
+    }
+    Ok(neighbours)
+}
+
+/// Build the AF_BRIDGE message adding or removing `entry`
+fn fdb_message(ty: u16, flags: u16, entry: &FdbEntry) -> Result<Message> {
+    let header = ndmsg {
+        ndm_family: libc::AF_BRIDGE as u8,
+        ndm_ifindex: Interface::new(&entry.interface)?.index()?,
+        ndm_state: u16::from(entry.state),
+        // The database of the bridge of the port, or the port's own one
+        ndm_flags: if entry.master.is_some() { NTF_MASTER } else { NTF_SELF },
+        ..Default::default()
+    };
+    let mut msg = Message::new(ty, flags, &header);
+    msg.attr(NDA_LLADDR, &entry.mac.octets());
+    if let Some(vlan) = entry.vlan {
+        msg.attr_u16(NDA_VLAN, vlan);
+    }
+    if let Some(destination) = &entry.destination {
+        msg.attr_ip(NDA_DST, destination);
+    }
+    Ok(msg)
+}
+
+/// Add `entry`, or another destination to the existing entry of its MAC address when `append` is set
+pub fn add_fdb(sock: &NetlinkSocket, entry: &FdbEntry, append: bool) -> Result<()> {
+    let flags = NLM_F_CREATE | if append { NLM_F_APPEND } else { NLM_F_EXCL };
+    sock.request(fdb_message(RTM_NEWNEIGH, flags, entry)?)
+        .map_err(|e| e.context(format!("Failed to add forwarding database entry '{}'", entry)))
+}
+
+/// Remove `entry`, only its destination when the MAC address has several
+pub fn del_fdb(sock: &NetlinkSocket, entry: &FdbEntry) -> Result<()> {
+    sock.request(fdb_message(RTM_DELNEIGH, 0, entry)?)
+        .map_err(|e| e.context(format!("Failed to remove forwarding database entry '{}'", entry)))
+}
+
+/// Entries of the forwarding databases of every bridge and port
+pub fn fdb(sock: &NetlinkSocket) -> Result<Vec<FdbEntry>> {
+    let header = ndmsg {
+        ndm_family: libc::AF_BRIDGE as u8,
+        ..Default::default()
+    };
+    let mut entries = Vec::new();
+    for payload in sock.dump(Message::new(RTM_GETNEIGH, 0, &header))? {
+        let (header, data) = parse_header::<ndmsg>(&payload)?;
+        let mut mac = None;
+        let mut vlan = None;
+        let mut destination = None;
+        let mut master = None;
+        for (ty, data) in attrs(data) {
+            match ty {
+                NDA_LLADDR => mac = parse_mac(data),
+                NDA_DST => destination = Some(parse_ip(data)?),
+                NDA_VLAN => vlan = Some(parse_u16(data)?),
+                NDA_MASTER => master = Some(Interface::from_index(parse_u32(data)?)?.name().to_string()),
+                _ => {}
+            }
+        }
+        let mac = match mac {
+            Some(mac) => mac,
+            None => continue,
+        };
+
+        entries.push(FdbEntry {
+            mac,
+            interface: Interface::from_index(header.ndm_ifindex as u32)?.name().to_string(),
+            vlan,
+            destination,
+            master,
+            state: NeighbourState::from(header.ndm_state),
+        });
+    }
+    Ok(entries)
+}
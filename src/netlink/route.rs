@@ -0,0 +1,153 @@
+//! RTM_NEWROUTE, RTM_DELROUTE and RTM_GETROUTE, see rtnetlink(7)
+use super::*;
+use crate::addr::{Cidr, Scope};
+use crate::interface::Interface;
+use crate::route::{Route, RouteType, Table};
+
+const RTM_NEWROUTE: u16 = 24;
+const RTM_DELROUTE: u16 = 25;
+const RTM_GETROUTE: u16 = 26;
+
+const RTA_DST: u16 = 1;
+const RTA_OIF: u16 = 4;
+const RTA_GATEWAY: u16 = 5;
+const RTA_PRIORITY: u16 = 6;
+const RTA_PREFSRC: u16 = 7;
+const RTA_TABLE: u16 = 15;
+
+/// Routes added by an administrator, as iproute2 marks them
+const RTPROT_BOOT: u8 = 3;
+const RTNH_F_ONLINK: u32 = 4;
+const RTM_F_CLONED: u32 = 0x200;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct rtmsg {
+    rtm_family: u8,
+    rtm_dst_len: u8,
+    rtm_src_len: u8,
+    rtm_tos: u8,
+    rtm_table: u8,
+    rtm_protocol: u8,
+    rtm_scope: u8,
+    rtm_type: u8,
+    rtm_flags: u32,
+}
+
+fn family(addr: &IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => libc::AF_INET as u8,
+        IpAddr::V6(_) => libc::AF_INET6 as u8,
+    }
+}
+
+/// Build the message adding or removing `route`
+fn route_message(ty: u16, flags: u16, route: &Route) -> Result<Message> {
+    let del = ty == RTM_DELROUTE;
+    let header = rtmsg {
+        rtm_family: family(&route.destination.addr),
+        rtm_dst_len: route.prefix_len(),
+        // Tables above 255 only fit in RTA_TABLE
+        rtm_table: if route.table.0 < 256 { route.table.0 as u8 } else { 0 },
+        rtm_protocol: if del { 0 } else { RTPROT_BOOT },
+        // Deleting with the nowhere scope and no type matches any of them
+        rtm_scope: u8::from(if del { Scope::Nowhere } else { route.scope }),
+        rtm_type: if del && route.kind == RouteType::Unicast { 0 } else { u8::from(route.kind) },
+        rtm_flags: if route.onlink { RTNH_F_ONLINK } else { 0 },
+        ..Default::default()
+    };
+    let mut msg = Message::new(ty, flags, &header);
+
+    if route.prefix_len() > 0 {
+        msg.attr_ip(RTA_DST, &route.destination.addr);
+    }
+    if let Some(gateway) = &route.gateway {
+        msg.attr_ip(RTA_GATEWAY, gateway);
+    }
+    if let Some(interface) = &route.interface {
+        msg.attr_u32(RTA_OIF, Interface::new(interface)?.index()? as u32);
+    }
+    if let Some(source) = &route.source {
+        msg.attr_ip(RTA_PREFSRC, source);
+    }
+    if let Some(metric) = route.metric {
+        msg.attr_u32(RTA_PRIORITY, metric);
+    }
+    msg.attr_u32(RTA_TABLE, route.table.0);
+    Ok(msg)
+}
+
+/// Parse an RTM_NEWROUTE message, `None` for families other than IPv4 and IPv6
+fn parse_route(payload: &[u8]) -> Result<Option<Route>> {
+    let (header, data) = parse_header::<rtmsg>(payload)?;
+    let unspecified = match header.rtm_family as i32 {
+        libc::AF_INET => IpAddr::from([0u8; 4]),
+        libc::AF_INET6 => IpAddr::from([0u8; 16]),
+        _ => return Ok(None),
+    };
+    let mut route = Route::new(Cidr {
+        addr: unspecified,
+        prefix_len: Some(header.rtm_dst_len),
+    });
+    route.kind = RouteType::from(header.rtm_type);
+    route.scope = Scope::from(header.rtm_scope);
+    route.table = Table(header.rtm_table as u32);
+    route.onlink = header.rtm_flags & RTNH_F_ONLINK != 0;
+
+    for (ty, data) in attrs(data) {
+        match ty {
+            RTA_DST => route.destination.addr = parse_ip(data)?,
+            RTA_GATEWAY => route.gateway = Some(parse_ip(data)?),
+            RTA_OIF => route.interface = Some(Interface::from_index(parse_u32(data)?)?.name().to_string()),
+            RTA_PREFSRC => route.source = Some(parse_ip(data)?),
+            RTA_PRIORITY => route.metric = Some(parse_u32(data)?),
+            RTA_TABLE => route.table = Table(parse_u32(data)?),
+            _ => {}
+        }
+    }
+    Ok(Some(route))
+}
+
+/// Add `route`, replacing an existing one to the same destination when `replace` is set
+pub fn add(sock: &NetlinkSocket, route: &Route, replace: bool) -> Result<()> {
+    let flags = NLM_F_CREATE | if replace { NLM_F_REPLACE } else { NLM_F_EXCL };
+    sock.request(route_message(RTM_NEWROUTE, flags, route)?)
+        .map_err(|e| e.context(format!("Failed to add route '{}'", route)))
+}
+
+/// Remove `route`, the fields left unset matching any route
+pub fn del(sock: &NetlinkSocket, route: &Route) -> Result<()> {
+    sock.request(route_message(RTM_DELROUTE, 0, route)?)
+        .map_err(|e| e.context(format!("Failed to remove route '{}'", route)))
+}
+
+/// Routes of every table and family
+pub fn list(sock: &NetlinkSocket) -> Result<Vec<Route>> {
+    let header = rtmsg::default();
+    let mut routes = Vec::new();
+    for payload in sock.dump(Message::new(RTM_GETROUTE, 0, &header))? {
+        // Routes cached by the kernel rather than configured
+        if parse_header::<rtmsg>(&payload)?.0.rtm_flags & RTM_F_CLONED != 0 {
+            continue;
+        }
+        if let Some(route) = parse_route(&payload)? {
+            routes.push(route);
+        }
+    }
+    Ok(routes)
+}
+
+/// Route the kernel picks to reach `destination`
+pub fn get(sock: &NetlinkSocket, destination: &IpAddr) -> Result<Route> {
+    let header = rtmsg {
+        rtm_family: family(destination),
+        rtm_dst_len: if destination.is_ipv4() { 32 } else { 128 },
+        ..Default::default()
+    };
+    let mut msg = Message::new(RTM_GETROUTE, 0, &header);
+    msg.attr_ip(RTA_DST, destination);
+    let (_, payload) = sock
+        .get(msg)
+        .map_err(|e| e.context(format!("Failed to get route to '{}'", destination)))?;
+    parse_route(&payload)?.ok_or_else(|| anyhow!("Kernel sent an unexpected route to '{}'", destination))
+}
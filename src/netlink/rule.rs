@@ -0,0 +1,155 @@
+//! RTM_NEWRULE, RTM_DELRULE and RTM_GETRULE, see rtnetlink(7)
+use super::*;
+use crate::addr::{Cidr, Family};
+use crate::route::Table;
+use crate::rule::{Rule, RuleAction};
+
+const RTM_NEWRULE: u16 = 32;
+const RTM_DELRULE: u16 = 33;
+const RTM_GETRULE: u16 = 34;
+
+const FRA_DST: u16 = 1;
+const FRA_SRC: u16 = 2;
+const FRA_IIFNAME: u16 = 3;
+const FRA_GOTO: u16 = 4;
+const FRA_PRIORITY: u16 = 6;
+const FRA_FWMARK: u16 = 10;
+const FRA_TABLE: u16 = 15;
+const FRA_FWMASK: u16 = 16;
+const FRA_OIFNAME: u16 = 17;
+
+const FIB_RULE_INVERT: u32 = 2;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct fib_rule_hdr {
+    family: u8,
+    dst_len: u8,
+    src_len: u8,
+    tos: u8,
+    table: u8,
+    res1: u8,
+    res2: u8,
+    action: u8,
+    flags: u32,
+}
+
+/// Build the message adding or removing `rule`
+fn rule_message(ty: u16, flags: u16, rule: &Rule) -> Message {
+    let del = ty == RTM_DELRULE;
+    let table = rule.table.unwrap_or(Table::UNSPEC);
+    let header = fib_rule_hdr {
+        family: match rule.family {
+            Family::Inet => libc::AF_INET as u8,
+            Family::Inet6 => libc::AF_INET6 as u8,
+        },
+        dst_len: rule.to.map_or(0, |to| to.prefix_len.unwrap_or(if to.addr.is_ipv4() { 32 } else { 128 })),
+        src_len: rule.from.map_or(0, |from| from.prefix_len.unwrap_or(if from.addr.is_ipv4() { 32 } else { 128 })),
+        // Tables above 255 only fit in FRA_TABLE
+        table: if table.0 < 256 { table.0 as u8 } else { 0 },
+        // Deleting without an action or table matches any of them
+        action: if del && rule.action == RuleAction::Lookup && rule.table.is_none() {
+            0
+        } else {
+            u8::from(rule.action)
+        },
+        flags: if rule.invert { FIB_RULE_INVERT } else { 0 },
+        ..Default::default()
+    };
+    let mut msg = Message::new(ty, flags, &header);
+
+    if let Some(from) = &rule.from {
+        msg.attr_ip(FRA_SRC, &from.addr);
+    }
+    if let Some(to) = &rule.to {
+        msg.attr_ip(FRA_DST, &to.addr);
+    }
+    if let Some(priority) = rule.priority {
+        msg.attr_u32(FRA_PRIORITY, priority);
+    }
+    if let Some(fwmark) = rule.fwmark {
+        msg.attr_u32(FRA_FWMARK, fwmark);
+    }
+    if let Some(fwmask) = rule.fwmask {
+        msg.attr_u32(FRA_FWMASK, fwmask);
+    }
+    if let Some(iif) = &rule.iif {
+        msg.attr_str(FRA_IIFNAME, iif);
+    }
+    if let Some(oif) = &rule.oif {
+        msg.attr_str(FRA_OIFNAME, oif);
+    }
+    if let Some(goto) = rule.goto {
+        msg.attr_u32(FRA_GOTO, goto);
+    }
+    if table != Table::UNSPEC {
+        msg.attr_u32(FRA_TABLE, table.0);
+    }
+    msg
+}
+
+/// Add `rule`
+pub fn add(sock: &NetlinkSocket, rule: &Rule) -> Result<()> {
+    sock.request(rule_message(RTM_NEWRULE, NLM_F_CREATE | NLM_F_EXCL, rule))
+        .map_err(|e| e.context(format!("Failed to add rule '{}'", rule)))
+}
+
+/// Remove `rule`, the fields left unset matching any rule
+pub fn del(sock: &NetlinkSocket, rule: &Rule) -> Result<()> {
+    sock.request(rule_message(RTM_DELRULE, 0, rule))
+        .map_err(|e| e.context(format!("Failed to remove rule '{}'", rule)))
+}
+
+/// IPv4 and IPv6 rules
+pub fn list(sock: &NetlinkSocket) -> Result<Vec<Rule>> {
+    let header = fib_rule_hdr::default();
+    let mut rules = Vec::new();
+    for payload in sock.dump(Message::new(RTM_GETRULE, 0, &header))? {
+        let (header, data) = parse_header::<fib_rule_hdr>(&payload)?;
+        // Multicast routing rules are dumped as well
+        let family = match header.family as i32 {
+            libc::AF_INET => Family::Inet,
+            libc::AF_INET6 => Family::Inet6,
+            _ => continue,
+        };
+
+        let mut rule = Rule::new(family, Table(header.table as u32));
+        rule.action = RuleAction::from(header.action);
+        rule.invert = header.flags & FIB_RULE_INVERT != 0;
+        for (ty, data) in attrs(data) {
+            match ty {
+                FRA_SRC => {
+                    rule.from = Some(Cidr {
+                        addr: parse_ip(data)?,
+                        prefix_len: Some(header.src_len),
+                    })
+                }
+                FRA_DST => {
+                    rule.to = Some(Cidr {
+                        addr: parse_ip(data)?,
+                        prefix_len: Some(header.dst_len),
+                    })
+                }
+                FRA_PRIORITY => rule.priority = Some(parse_u32(data)?),
+                FRA_FWMARK => rule.fwmark = Some(parse_u32(data)?),
+                FRA_FWMASK => rule.fwmask = Some(parse_u32(data)?),
+                FRA_IIFNAME => rule.iif = Some(parse_str(data)),
+                FRA_OIFNAME => rule.oif = Some(parse_str(data)),
+                FRA_GOTO => rule.goto = Some(parse_u32(data)?),
+                FRA_TABLE => rule.table = Some(Table(parse_u32(data)?)),
+                _ => {}
+            }
+        }
+        if rule.action != RuleAction::Lookup {
+            rule.table = None;
+        }
+        // A mask of all ones is the default the kernel reports along with a mark
+        if rule.fwmask == Some(u32::MAX) {
+            rule.fwmask = None;
+        }
+        // Priority 0 is left out of the dump
+        rule.priority = Some(rule.priority.unwrap_or(0));
+        rules.push(rule);
+    }
+    Ok(rules)
+}
@@ -0,0 +1,256 @@
+//! The wireguard generic netlink family, see include/uapi/linux/wireguard.h
+//!
+//! Devices are dumped rather than got: a device with many peers is split
+//! across messages, and so is a peer with many allowed IPs, its public key
+//! being repeated at the start of the next message.
+use super::genl::{self, genlmsghdr};
+use super::*;
+use crate::addr::Cidr;
+use crate::wireguard::{WireguardConfig, WireguardDevice, WireguardKey, WireguardPeer, WireguardPeerConfig};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+const WG_GENL_NAME: &str = "wireguard";
+const WG_GENL_VERSION: u8 = 1;
+
+const WG_CMD_GET_DEVICE: u8 = 0;
+const WG_CMD_SET_DEVICE: u8 = 1;
+
+const WGDEVICE_A_IFNAME: u16 = 2;
+const WGDEVICE_A_PRIVATE_KEY: u16 = 3;
+const WGDEVICE_A_PUBLIC_KEY: u16 = 4;
+const WGDEVICE_A_FLAGS: u16 = 5;
+const WGDEVICE_A_LISTEN_PORT: u16 = 6;
+const WGDEVICE_A_FWMARK: u16 = 7;
+const WGDEVICE_A_PEERS: u16 = 8;
+
+const WGDEVICE_F_REPLACE_PEERS: u32 = 1;
+
+const WGPEER_A_PUBLIC_KEY: u16 = 1;
+const WGPEER_A_PRESHARED_KEY: u16 = 2;
+const WGPEER_A_FLAGS: u16 = 3;
+const WGPEER_A_ENDPOINT: u16 = 4;
+const WGPEER_A_PERSISTENT_KEEPALIVE_INTERVAL: u16 = 5;
+const WGPEER_A_LAST_HANDSHAKE_TIME: u16 = 6;
+const WGPEER_A_RX_BYTES: u16 = 7;
+const WGPEER_A_TX_BYTES: u16 = 8;
+const WGPEER_A_ALLOWEDIPS: u16 = 9;
+
+const WGPEER_F_REMOVE_ME: u32 = 1;
+const WGPEER_F_REPLACE_ALLOWEDIPS: u32 = 2;
+
+const WGALLOWEDIP_A_FAMILY: u16 = 1;
+const WGALLOWEDIP_A_IPADDR: u16 = 2;
+const WGALLOWEDIP_A_CIDR_MASK: u16 = 3;
+
+fn parse_key(data: &[u8]) -> Result<WireguardKey> {
+    Ok(WireguardKey::from(<[u8; 32]>::try_from(data)?))
+}
+
+/// Parse the `sockaddr_in` or `sockaddr_in6` of an endpoint
+fn parse_endpoint(data: &[u8]) -> Result<Option<SocketAddr>> {
+    let endpoint = match parse_u16(data)? as libc::c_int {
+        libc::AF_INET => {
+            let (sin, _) = parse_header::<libc::sockaddr_in>(data)?;
+            let ip = Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr));
+            SocketAddr::V4(SocketAddrV4::new(ip, u16::from_be(sin.sin_port)))
+        }
+        libc::AF_INET6 => {
+            let (sin6, _) = parse_header::<libc::sockaddr_in6>(data)?;
+            let ip = Ipv6Addr::from(sin6.sin6_addr.s6_addr);
+            let port = u16::from_be(sin6.sin6_port);
+            SocketAddr::V6(SocketAddrV6::new(ip, port, u32::from_be(sin6.sin6_flowinfo), sin6.sin6_scope_id))
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(endpoint))
+}
+
+/// Append the endpoint attribute of a peer, a `sockaddr_in` or `sockaddr_in6`
+fn attr_endpoint(msg: &mut Message, endpoint: &SocketAddr) {
+    match endpoint {
+        SocketAddr::V4(addr) => {
+            let mut sin: libc::sockaddr_in = unsafe { mem::zeroed() };
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_port = addr.port().to_be();
+            sin.sin_addr.s_addr = u32::from(*addr.ip()).to_be();
+            msg.attr_struct(WGPEER_A_ENDPOINT, &sin);
+        }
+        SocketAddr::V6(addr) => {
+            let mut sin6: libc::sockaddr_in6 = unsafe { mem::zeroed() };
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_port = addr.port().to_be();
+            sin6.sin6_flowinfo = addr.flowinfo().to_be();
+            sin6.sin6_addr.s6_addr = addr.ip().octets();
+            sin6.sin6_scope_id = addr.scope_id();
+            msg.attr_struct(WGPEER_A_ENDPOINT, &sin6);
+        }
+    }
+}
+
+/// Parse a WGPEER_A_ALLOWEDIPS, a list of nested family, address and prefix length
+fn parse_allowed_ips(data: &[u8]) -> Result<Vec<Cidr>> {
+    let mut allowed_ips = Vec::new();
+    for (_, data) in attrs(data) {
+        let mut addr = None;
+        let mut prefix_len = None;
+        for (ty, data) in attrs(data) {
+            match ty {
+                WGALLOWEDIP_A_IPADDR => addr = Some(parse_ip(data)?),
+                WGALLOWEDIP_A_CIDR_MASK => prefix_len = Some(parse_u8(data)?),
+                _ => {}
+            }
+        }
+        if let Some(addr) = addr {
+            allowed_ips.push(Cidr { addr, prefix_len });
+        }
+    }
+    Ok(allowed_ips)
+}
+
+fn parse_peer(data: &[u8]) -> Result<WireguardPeer> {
+    let mut public_key = None;
+    let mut peer = WireguardPeer {
+        public_key: WireguardKey::from([0; 32]),
+        endpoint: None,
+        allowed_ips: Vec::new(),
+        persistent_keepalive: None,
+        last_handshake: None,
+        rx_bytes: 0,
+        tx_bytes: 0,
+    };
+    for (ty, data) in attrs(data) {
+        match ty {
+            WGPEER_A_PUBLIC_KEY => public_key = Some(parse_key(data)?),
+            WGPEER_A_ENDPOINT => peer.endpoint = parse_endpoint(data)?,
+            WGPEER_A_PERSISTENT_KEEPALIVE_INTERVAL => {
+                peer.persistent_keepalive = Some(parse_u16(data)?).filter(|interval| *interval != 0)
+            }
+            WGPEER_A_LAST_HANDSHAKE_TIME => {
+                // A struct __kernel_timespec, zero until the first handshake
+                let (seconds, _) = parse_header::<i64>(data)?;
+                peer.last_handshake = Some(seconds as u64).filter(|seconds| *seconds != 0);
+            }
+            WGPEER_A_RX_BYTES => peer.rx_bytes = parse_u64(data)?,
+            WGPEER_A_TX_BYTES => peer.tx_bytes = parse_u64(data)?,
+            WGPEER_A_ALLOWEDIPS => peer.allowed_ips = parse_allowed_ips(data)?,
+            _ => {}
+        }
+    }
+    peer.public_key = public_key.ok_or_else(|| anyhow!("Kernel sent a WireGuard peer without a public key"))?;
+    Ok(peer)
+}
+
+/// Turn the error of a request about `interface` into a readable one
+fn device_error(e: anyhow::Error, interface: &str, action: &str) -> anyhow::Error {
+    match e.downcast_ref::<Errno>() {
+        Some(Errno::EOPNOTSUPP) => anyhow!("Interface '{}' is not a WireGuard device", interface),
+        _ => e.context(format!("Failed to {} WireGuard device '{}'", action, interface)),
+    }
+}
+
+/// Keys, port and peers of the WireGuard device `interface`
+pub fn get(sock: &NetlinkSocket, interface: &str) -> Result<WireguardDevice> {
+    let family = genl::family(sock, WG_GENL_NAME)?;
+    let mut msg = Message::new(family, 0, &genlmsghdr::new(WG_CMD_GET_DEVICE, WG_GENL_VERSION));
+    msg.attr_str(WGDEVICE_A_IFNAME, interface);
+
+    let mut device = WireguardDevice {
+        interface: interface.to_string(),
+        public_key: None,
+        listen_port: 0,
+        fwmark: None,
+        peers: Vec::new(),
+    };
+    for payload in sock.dump(msg).map_err(|e| device_error(e, interface, "query"))? {
+        let (_, data) = parse_header::<genlmsghdr>(&payload)?;
+        for (ty, data) in attrs(data) {
+            match ty {
+                WGDEVICE_A_PUBLIC_KEY => device.public_key = Some(parse_key(data)?),
+                WGDEVICE_A_LISTEN_PORT => device.listen_port = parse_u16(data)?,
+                WGDEVICE_A_FWMARK => device.fwmark = Some(parse_u32(data)?).filter(|fwmark| *fwmark != 0),
+                WGDEVICE_A_PEERS => {
+                    for (_, data) in attrs(data) {
+                        let peer = parse_peer(data)?;
+                        match device.peers.last_mut() {
+                            // The rest of the allowed IPs of the last peer of the previous message
+                            Some(last) if last.public_key == peer.public_key => {
+                                last.allowed_ips.extend(peer.allowed_ips)
+                            }
+                            _ => device.peers.push(peer),
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(device)
+}
+
+/// Append the nested attributes of `peer`
+fn peer_attrs(msg: &mut Message, peer: &WireguardPeerConfig) {
+    let nest = msg.nest_begin(0);
+    msg.attr(WGPEER_A_PUBLIC_KEY, &peer.public_key.octets());
+    let mut flags = 0;
+    if peer.remove {
+        flags |= WGPEER_F_REMOVE_ME;
+    }
+    if peer.replace_allowed_ips {
+        flags |= WGPEER_F_REPLACE_ALLOWEDIPS;
+    }
+    if flags != 0 {
+        msg.attr_u32(WGPEER_A_FLAGS, flags);
+    }
+    if let Some(preshared_key) = &peer.preshared_key {
+        msg.attr(WGPEER_A_PRESHARED_KEY, &preshared_key.octets());
+    }
+    if let Some(endpoint) = &peer.endpoint {
+        attr_endpoint(msg, endpoint);
+    }
+    if let Some(interval) = peer.persistent_keepalive {
+        msg.attr_u16(WGPEER_A_PERSISTENT_KEEPALIVE_INTERVAL, interval);
+    }
+    if !peer.allowed_ips.is_empty() {
+        let allowed_ips = msg.nest_begin(WGPEER_A_ALLOWEDIPS);
+        for cidr in &peer.allowed_ips {
+            let (family, max_len) = match cidr.addr {
+                IpAddr::V4(_) => (libc::AF_INET, 32),
+                IpAddr::V6(_) => (libc::AF_INET6, 128),
+            };
+            let allowed_ip = msg.nest_begin(0);
+            msg.attr_u16(WGALLOWEDIP_A_FAMILY, family as u16);
+            msg.attr_ip(WGALLOWEDIP_A_IPADDR, &cidr.addr);
+            msg.attr_u8(WGALLOWEDIP_A_CIDR_MASK, cidr.prefix_len.unwrap_or(max_len));
+            msg.nest_end(allowed_ip);
+        }
+        msg.nest_end(allowed_ips);
+    }
+    msg.nest_end(nest);
+}
+
+/// Apply `config` to the WireGuard device `interface`
+pub fn set(sock: &NetlinkSocket, interface: &str, config: &WireguardConfig) -> Result<()> {
+    let family = genl::family(sock, WG_GENL_NAME)?;
+    let mut msg = Message::new(family, 0, &genlmsghdr::new(WG_CMD_SET_DEVICE, WG_GENL_VERSION));
+    msg.attr_str(WGDEVICE_A_IFNAME, interface);
+    if let Some(private_key) = &config.private_key {
+        msg.attr(WGDEVICE_A_PRIVATE_KEY, &private_key.octets());
+    }
+    if let Some(listen_port) = config.listen_port {
+        msg.attr_u16(WGDEVICE_A_LISTEN_PORT, listen_port);
+    }
+    if let Some(fwmark) = config.fwmark {
+        msg.attr_u32(WGDEVICE_A_FWMARK, fwmark);
+    }
+    if config.replace_peers {
+        msg.attr_u32(WGDEVICE_A_FLAGS, WGDEVICE_F_REPLACE_PEERS);
+    }
+    if !config.peers.is_empty() {
+        let peers = msg.nest_begin(WGDEVICE_A_PEERS);
+        for peer in &config.peers {
+            peer_attrs(&mut msg, peer);
+        }
+        msg.nest_end(peers);
+    }
+    sock.request(msg).map_err(|e| device_error(e, interface, "configure"))
+}
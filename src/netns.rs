@@ -0,0 +1,156 @@
+//! Network namespaces named as iproute2 does, see ip-netns(8)
+//!
+//! A namespace outlives the processes in it once its /proc/<tid>/ns/net is
+//! bind mounted onto a file of /run/netns, which is made a shared mount point
+//! for the namespaces to show up in every mount namespace.
+use crate::socket::ControlSocket;
+use anyhow::{anyhow, bail, Result};
+use nix::errno::Errno;
+use nix::mount::{mount, umount2, MntFlags, MsFlags};
+use nix::sched::{setns, unshare, CloneFlags};
+use std::fs::{self, File, OpenOptions};
+use std::io::ErrorKind;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+/// Directory the named namespaces are bind mounted in
+pub const NETNS_RUN_DIR: &str = "/run/netns";
+
+const NONE: Option<&str> = None;
+
+/// Path of the namespace `name`, which must be a plain file name
+fn path(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        bail!("Invalid network namespace name '{}'", name);
+    }
+    Ok(Path::new(NETNS_RUN_DIR).join(name))
+}
+
+/// Make the run directory a shared mount point, bind mounting it onto itself first when it isn't one
+fn share_run_dir() -> Result<()> {
+    fs::create_dir_all(NETNS_RUN_DIR).map_err(|e| anyhow!("Failed to create {}: {}", NETNS_RUN_DIR, e))?;
+    let remount = |source, flags| mount(source, NETNS_RUN_DIR, Some("none"), flags | MsFlags::MS_REC, NONE);
+    let res = match remount(Some("none"), MsFlags::MS_SHARED) {
+        // Not a mount point
+        Err(Errno::EINVAL) => remount(Some(NETNS_RUN_DIR), MsFlags::MS_BIND)
+            .and_then(|_| remount(Some("none"), MsFlags::MS_SHARED)),
+        res => res,
+    };
+    res.map_err(|e| anyhow!("Failed to make {} a shared mount point: {}", NETNS_RUN_DIR, e))
+}
+
+/// Handle to a named network namespace, holding it open
+#[derive(Debug)]
+pub struct Netns {
+    name: String,
+    file: File,
+}
+
+impl Netns {
+    /// Open the namespace `name`
+    pub fn open(name: &str) -> Result<Netns> {
+        let path = path(name)?;
+        let file = File::open(&path).map_err(|e| match e.kind() {
+            ErrorKind::NotFound => anyhow!("Network namespace '{}' not found", name),
+            _ => anyhow!("Failed to open {}: {}", path.display(), e),
+        })?;
+        Ok(Netns {
+            name: name.to_string(),
+            file,
+        })
+    }
+
+    /// Create the namespace `name`, with only a loopback interface which is down
+    ///
+    /// The namespace is unshared by a thread of its own, leaving the caller where it is.
+    pub fn add(name: &str) -> Result<Netns> {
+        let path = path(name)?;
+        share_run_dir()?;
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o444)
+            .open(&path)
+            .map_err(|e| match e.kind() {
+                ErrorKind::AlreadyExists => anyhow!("Network namespace '{}' already exists", name),
+                _ => anyhow!("Failed to create {}: {}", path.display(), e),
+            })?;
+
+        let target = path.clone();
+        let res = thread::spawn(move || {
+            unshare(CloneFlags::CLONE_NEWNET)?;
+            mount(Some("/proc/thread-self/ns/net"), &target, NONE, MsFlags::MS_BIND, NONE)
+        })
+        .join()
+        .map_err(|_| anyhow!("Thread creating network namespace '{}' panicked", name))?;
+        if let Err(e) = res {
+            // Don't leave a file which isn't a namespace behind
+            let _ = fs::remove_file(&path);
+            bail!("Failed to create network namespace '{}': {}", name, e);
+        }
+        Netns::open(name)
+    }
+
+    /// Names of the namespaces, sorted
+    pub fn list() -> Result<Vec<String>> {
+        let entries = match fs::read_dir(NETNS_RUN_DIR) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => bail!("Failed to read {}: {}", NETNS_RUN_DIR, e),
+        };
+        let mut names = Vec::new();
+        for entry in entries {
+            names.push(entry?.file_name().to_string_lossy().into_owned());
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Remove the name of the namespace
+    ///
+    /// The namespace itself goes away along with its virtual interfaces once
+    /// no process is left in it, physical interfaces returning to the initial namespace.
+    pub fn delete(self) -> Result<()> {
+        let Netns { name, file } = self;
+        let path = path(&name)?;
+        drop(file);
+        umount2(&path, MntFlags::MNT_DETACH)
+            .map_err(|e| anyhow!("Failed to unmount network namespace '{}': {}", name, e))?;
+        fs::remove_file(&path).map_err(|e| anyhow!("Failed to remove {}: {}", path.display(), e))
+    }
+
+    /// Move the calling thread into the namespace, the sockets opened from then on operating in it
+    ///
+    /// The thread also gets a mount namespace of its own with sysfs mounted
+    /// again, for /sys/class/net to list the interfaces of the namespace, as
+    /// `ip netns exec` does.
+    pub fn enter(&self) -> Result<()> {
+        setns(self.file.as_raw_fd(), CloneFlags::CLONE_NEWNET)
+            .map_err(|e| anyhow!("Failed to enter network namespace '{}': {}", self.name, e))?;
+        ControlSocket::reset_shared();
+
+        unshare(CloneFlags::CLONE_NEWNS).map_err(|e| anyhow!("Failed to unshare the mount namespace: {}", e))?;
+        // Keep the mounts below from propagating back
+        mount(NONE, "/", NONE, MsFlags::MS_SLAVE | MsFlags::MS_REC, NONE)
+            .map_err(|e| anyhow!("Failed to make / a slave mount point: {}", e))?;
+        match umount2("/sys", MntFlags::MNT_DETACH) {
+            // Not mounted
+            Ok(_) | Err(Errno::EINVAL) => {}
+            Err(e) => bail!("Failed to unmount /sys: {}", e),
+        }
+        mount(Some(self.name.as_str()), "/sys", Some("sysfs"), MsFlags::empty(), NONE)
+            .map_err(|e| anyhow!("Failed to mount sysfs of network namespace '{}': {}", self.name, e))
+    }
+}
+
+impl AsRawFd for Netns {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
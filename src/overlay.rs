@@ -0,0 +1,124 @@
+//! Overlay networks carrying Ethernet frames over UDP, VXLAN (RFC 7348) and GENEVE (RFC 8926)
+use std::fmt;
+use std::net::IpAddr;
+
+/// Options of a VXLAN link being created, the kernel defaults applying to those left unset
+///
+/// Frames to unknown MAC addresses go to `remote`, or to the multicast `group`,
+/// other remote VTEPs being reached through the forwarding database of the link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VxlanOptions {
+    /// VXLAN network identifier, 24 bits
+    pub vni: u32,
+    /// Source address of the encapsulated packets
+    pub local: Option<IpAddr>,
+    /// Unicast VTEP frames are sent to by default
+    pub remote: Option<IpAddr>,
+    /// Multicast group joined to reach every VTEP, exclusive with `remote`
+    pub group: Option<IpAddr>,
+    /// Interface the encapsulated packets are sent through
+    pub parent: Option<String>,
+    /// UDP destination port, 8472 when unset, 4789 being the IANA one
+    pub port: Option<u16>,
+    /// Whether the source addresses of received frames are learned in the forwarding database
+    pub learning: Option<bool>,
+}
+
+impl VxlanOptions {
+    pub fn new(vni: u32) -> VxlanOptions {
+        VxlanOptions {
+            vni,
+            local: None,
+            remote: None,
+            group: None,
+            parent: None,
+            port: None,
+            learning: None,
+        }
+    }
+}
+
+/// Formats as iproute2 does, e.g. `id 42 remote 10.0.0.2 local 10.0.0.1 dev eth0 dstport 4789 nolearning`
+impl fmt::Display for VxlanOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "id {}", self.vni)?;
+        if let Some(remote) = self.remote {
+            write!(f, " remote {}", remote)?;
+        }
+        if let Some(group) = self.group {
+            write!(f, " group {}", group)?;
+        }
+        if let Some(local) = self.local {
+            write!(f, " local {}", local)?;
+        }
+        if let Some(parent) = &self.parent {
+            write!(f, " dev {}", parent)?;
+        }
+        if let Some(port) = self.port {
+            write!(f, " dstport {}", port)?;
+        }
+        match self.learning {
+            Some(true) => write!(f, " learning"),
+            Some(false) => write!(f, " nolearning"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Options of a GENEVE link being created, the kernel defaults applying to those left unset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneveOptions {
+    /// Virtual network identifier, 24 bits
+    pub vni: u32,
+    /// Tunnel endpoint every frame is sent to
+    pub remote: IpAddr,
+    /// UDP destination port, 6081 when unset
+    pub port: Option<u16>,
+}
+
+impl GeneveOptions {
+    pub fn new(vni: u32, remote: IpAddr) -> GeneveOptions {
+        GeneveOptions { vni, remote, port: None }
+    }
+}
+
+/// Formats as iproute2 does, e.g. `id 42 remote 10.0.0.2 dstport 6081`
+impl fmt::Display for GeneveOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "id {} remote {}", self.vni, self.remote)?;
+        if let Some(port) = self.port {
+            write!(f, " dstport {}", port)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vxlan() {
+        let mut options = VxlanOptions::new(42);
+        assert_eq!(options.to_string(), "id 42");
+        options.remote = Some(IpAddr::from([10, 0, 0, 2]));
+        options.local = Some(IpAddr::from([10, 0, 0, 1]));
+        options.parent = Some("eth0".to_string());
+        options.port = Some(4789);
+        options.learning = Some(false);
+        assert_eq!(options.to_string(), "id 42 remote 10.0.0.2 local 10.0.0.1 dev eth0 dstport 4789 nolearning");
+
+        let mut options = VxlanOptions::new(7);
+        options.group = Some("ff05::100".parse().unwrap());
+        options.learning = Some(true);
+        assert_eq!(options.to_string(), "id 7 group ff05::100 learning");
+    }
+
+    #[test]
+    fn geneve() {
+        let mut options = GeneveOptions::new(42, "2001:db8::2".parse().unwrap());
+        assert_eq!(options.to_string(), "id 42 remote 2001:db8::2");
+        options.port = Some(6081);
+        assert_eq!(options.to_string(), "id 42 remote 2001:db8::2 dstport 6081");
+    }
+}
@@ -0,0 +1,542 @@
+//! Routes of the kernel's routing tables
+use crate::addr::{Cidr, Scope};
+use anyhow::{bail, Result};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+// RTF_* flags of /proc/net/route and /proc/net/ipv6_route
+const RTF_REJECT: u32 = 0x0200;
+const RTF_CACHE: u32 = 0x0100_0000;
+const RTF_LOCAL: u32 = 0x8000_0000;
+
+/// Routing table, named as in /etc/iproute2/rt_tables when it is a well known one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Table(pub u32);
+
+impl Table {
+    /// Matches every table when listing routes
+    pub const UNSPEC: Table = Table(0);
+    pub const DEFAULT: Table = Table(253);
+    pub const MAIN: Table = Table(254);
+    pub const LOCAL: Table = Table(255);
+}
+
+impl Default for Table {
+    fn default() -> Table {
+        Table::MAIN
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Table::UNSPEC => write!(f, "unspec"),
+            Table::DEFAULT => write!(f, "default"),
+            Table::MAIN => write!(f, "main"),
+            Table::LOCAL => write!(f, "local"),
+            Table(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+impl FromStr for Table {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "all" | "unspec" => Ok(Table::UNSPEC),
+            "default" => Ok(Table::DEFAULT),
+            "main" => Ok(Table::MAIN),
+            "local" => Ok(Table::LOCAL),
+            _ => match s.parse::<u32>() {
+                Ok(id) => Ok(Table(id)),
+                Err(_) => bail!("Unknown routing table '{}'", s),
+            },
+        }
+    }
+}
+
+/// Serializes as its name, e.g. `"main"`, or its number as a string
+impl Serialize for Table {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// What the kernel does with packets matching a route (RTN_*)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteType {
+    Unicast,
+    Local,
+    Broadcast,
+    Anycast,
+    Multicast,
+    /// Silently discard
+    Blackhole,
+    /// Discard with ICMP host unreachable
+    Unreachable,
+    /// Discard with ICMP administratively prohibited
+    Prohibit,
+    /// Continue the lookup in the next table
+    Throw,
+    Other(u8),
+}
+
+impl From<u8> for RouteType {
+    fn from(ty: u8) -> RouteType {
+        match ty {
+            1 => RouteType::Unicast,
+            2 => RouteType::Local,
+            3 => RouteType::Broadcast,
+            4 => RouteType::Anycast,
+            5 => RouteType::Multicast,
+            6 => RouteType::Blackhole,
+            7 => RouteType::Unreachable,
+            8 => RouteType::Prohibit,
+            9 => RouteType::Throw,
+            _ => RouteType::Other(ty),
+        }
+    }
+}
+
+impl From<RouteType> for u8 {
+    fn from(ty: RouteType) -> u8 {
+        match ty {
+            RouteType::Unicast => 1,
+            RouteType::Local => 2,
+            RouteType::Broadcast => 3,
+            RouteType::Anycast => 4,
+            RouteType::Multicast => 5,
+            RouteType::Blackhole => 6,
+            RouteType::Unreachable => 7,
+            RouteType::Prohibit => 8,
+            RouteType::Throw => 9,
+            RouteType::Other(ty) => ty,
+        }
+    }
+}
+
+impl fmt::Display for RouteType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteType::Unicast => write!(f, "unicast"),
+            RouteType::Local => write!(f, "local"),
+            RouteType::Broadcast => write!(f, "broadcast"),
+            RouteType::Anycast => write!(f, "anycast"),
+            RouteType::Multicast => write!(f, "multicast"),
+            RouteType::Blackhole => write!(f, "blackhole"),
+            RouteType::Unreachable => write!(f, "unreachable"),
+            RouteType::Prohibit => write!(f, "prohibit"),
+            RouteType::Throw => write!(f, "throw"),
+            RouteType::Other(ty) => write!(f, "{}", ty),
+        }
+    }
+}
+
+impl FromStr for RouteType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "unicast" => Ok(RouteType::Unicast),
+            "local" => Ok(RouteType::Local),
+            "broadcast" => Ok(RouteType::Broadcast),
+            "anycast" => Ok(RouteType::Anycast),
+            "multicast" => Ok(RouteType::Multicast),
+            "blackhole" => Ok(RouteType::Blackhole),
+            "unreachable" => Ok(RouteType::Unreachable),
+            "prohibit" => Ok(RouteType::Prohibit),
+            "throw" => Ok(RouteType::Throw),
+            _ => bail!("Unknown route type '{}'", s),
+        }
+    }
+}
+
+/// Serializes as its iproute2 name, e.g. `"blackhole"`
+impl Serialize for RouteType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Entry of a routing table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Route {
+    /// Destination prefix, `0.0.0.0/0` or `::/0` for default routes
+    pub destination: Cidr,
+    #[serde(rename = "type")]
+    pub kind: RouteType,
+    pub gateway: Option<IpAddr>,
+    /// Name of the outgoing interface
+    pub interface: Option<String>,
+    /// Preferred source address of packets sent along the route
+    pub source: Option<IpAddr>,
+    pub metric: Option<u32>,
+    pub table: Table,
+    pub scope: Scope,
+    /// Whether the gateway is assumed to be reachable on the interface without a matching prefix
+    pub onlink: bool,
+}
+
+impl Route {
+    /// Unicast route of global scope to `destination` in the main table
+    pub fn new(destination: Cidr) -> Route {
+        Route {
+            destination,
+            kind: RouteType::Unicast,
+            gateway: None,
+            interface: None,
+            source: None,
+            metric: None,
+            table: Table::MAIN,
+            scope: Scope::Universe,
+            onlink: false,
+        }
+    }
+
+    /// Prefix length of the destination, /32 or /128 when unset
+    pub fn prefix_len(&self) -> u8 {
+        match (self.destination.prefix_len, self.destination.addr) {
+            (Some(len), _) => len,
+            (None, IpAddr::V4(_)) => 32,
+            (None, IpAddr::V6(_)) => 128,
+        }
+    }
+
+    /// Whether this is a default route
+    pub fn is_default(&self) -> bool {
+        self.prefix_len() == 0
+    }
+
+    /// Scope iproute2 gives routes by default: host for local routes, link
+    /// for unicast routes without a gateway and global otherwise
+    pub fn default_scope(&self) -> Scope {
+        match self.kind {
+            RouteType::Local => Scope::Host,
+            RouteType::Unicast | RouteType::Anycast if self.gateway.is_none() => Scope::Link,
+            _ => Scope::Universe,
+        }
+    }
+
+    /// Whether `addr` falls in the destination prefix
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        let len = self.prefix_len() as u32;
+        match (self.destination.addr, addr) {
+            (IpAddr::V4(dst), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - len).unwrap_or(0);
+                u32::from(dst) & mask == u32::from(*addr) & mask
+            }
+            (IpAddr::V6(dst), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - len).unwrap_or(0);
+                u128::from(dst) & mask == u128::from(*addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Formats as iproute2 does, e.g. `default via 10.0.0.1 dev eth0 metric 100`
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.kind != RouteType::Unicast {
+            write!(f, "{} ", self.kind)?;
+        }
+        let host_len = if self.destination.addr.is_ipv4() { 32 } else { 128 };
+        match self.prefix_len() {
+            0 => write!(f, "default")?,
+            len if len == host_len => write!(f, "{}", self.destination.addr)?,
+            len => write!(f, "{}/{}", self.destination.addr, len)?,
+        }
+        if self.table != Table::MAIN {
+            write!(f, " table {}", self.table)?;
+        }
+        if let Some(gateway) = self.gateway {
+            write!(f, " via {}", gateway)?;
+        }
+        if let Some(interface) = &self.interface {
+            write!(f, " dev {}", interface)?;
+        }
+        if self.scope != Scope::Universe {
+            write!(f, " scope {}", self.scope)?;
+        }
+        if let Some(source) = self.source {
+            write!(f, " src {}", source)?;
+        }
+        if let Some(metric) = self.metric {
+            write!(f, " metric {}", metric)?;
+        }
+        if self.onlink {
+            write!(f, " onlink")?;
+        }
+        Ok(())
+    }
+}
+
+/// Route of the main table to `addr` with the longest prefix and then the lowest metric
+pub fn lookup<'a>(routes: &'a [Route], addr: &IpAddr) -> Option<&'a Route> {
+    routes
+        .iter()
+        .filter(|route| route.table == Table::MAIN && route.contains(addr))
+        .max_by_key(|route| (route.prefix_len(), std::cmp::Reverse(route.metric.unwrap_or(0))))
+}
+
+/// Read a procfs table, which is missing when its family is disabled
+fn read_proc(path: &str) -> Result<String> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => bail!("Failed to read {}: {}", path, e),
+    }
+}
+
+/// IPv4 routes of the main table, from procfs
+pub(crate) fn ipv4_routes() -> Result<Vec<Route>> {
+    parse_ipv4_routes(&read_proc("/proc/net/route")?)
+}
+
+/// Parse the content of /proc/net/route
+///
+/// Each line holds the interface, destination, gateway, flags, reference
+/// count, use count, metric and mask. Addresses are in network byte order
+/// printed as host integers.
+fn parse_ipv4_routes(content: &str) -> Result<Vec<Route>> {
+    let mut routes = Vec::new();
+    for line in content.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 8 {
+            bail!("Unexpected line in /proc/net/route: '{}'", line);
+        }
+        let parse_addr =
+            |field: &str| -> Result<Ipv4Addr> { Ok(Ipv4Addr::from(u32::from_str_radix(field, 16)?.to_ne_bytes())) };
+        let destination = parse_addr(fields[1])?;
+        let gateway = parse_addr(fields[2])?;
+        let flags = u32::from_str_radix(fields[3], 16)?;
+        let mask = u32::from(parse_addr(fields[7])?);
+
+        let mut route = Route::new(Cidr {
+            addr: IpAddr::from(destination),
+            prefix_len: Some(mask.count_ones() as u8),
+        });
+        route.gateway = Some(IpAddr::from(gateway)).filter(|gateway| !gateway.is_unspecified());
+        route.interface = Some(fields[0].to_string()).filter(|name| name != "*");
+        // Zero is the default metric, which netlink leaves out as well
+        route.metric = Some(fields[6].parse()?).filter(|metric| *metric != 0);
+        if flags & RTF_REJECT != 0 {
+            route.kind = RouteType::Unreachable;
+        }
+        route.scope = route.default_scope();
+        routes.push(route);
+    }
+    Ok(routes)
+}
+
+/// IPv6 routes, from procfs
+pub(crate) fn ipv6_routes() -> Result<Vec<Route>> {
+    parse_ipv6_routes(&read_proc("/proc/net/ipv6_route")?)
+}
+
+/// Parse the content of /proc/net/ipv6_route
+///
+/// Each line holds the destination and its prefix length, the source and its
+/// prefix length, the next hop, metric, reference count, use count, flags and
+/// interface, all but the interface in hex.
+fn parse_ipv6_routes(content: &str) -> Result<Vec<Route>> {
+    let mut routes = Vec::new();
+    for line in content.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 10 || fields[0].len() != 32 {
+            bail!("Unexpected line in /proc/net/ipv6_route: '{}'", line);
+        }
+        let parse_addr = |field: &str| -> Result<Ipv6Addr> { Ok(Ipv6Addr::from(u128::from_str_radix(field, 16)?)) };
+        let metric = u32::from_str_radix(fields[5], 16)?;
+        let flags = u32::from_str_radix(fields[8], 16)?;
+        // Cached clones and the catch-all entry terminating lookups
+        if flags & RTF_CACHE != 0 || (flags & RTF_REJECT != 0 && metric == u32::MAX) {
+            continue;
+        }
+
+        let mut route = Route::new(Cidr {
+            addr: IpAddr::from(parse_addr(fields[0])?),
+            prefix_len: Some(u8::from_str_radix(fields[1], 16)?),
+        });
+        route.gateway = Some(IpAddr::from(parse_addr(fields[4])?)).filter(|gateway| !gateway.is_unspecified());
+        route.interface = Some(fields[9].to_string());
+        route.metric = Some(metric);
+        if flags & RTF_LOCAL != 0 {
+            route.kind = RouteType::Local;
+            route.table = Table::LOCAL;
+        } else if route.destination.addr.is_multicast() {
+            route.kind = RouteType::Multicast;
+            route.table = Table::LOCAL;
+        } else if flags & RTF_REJECT != 0 {
+            route.kind = RouteType::Unreachable;
+        }
+        routes.push(route);
+    }
+    Ok(routes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(destination: &str) -> Route {
+        Route::new(destination.parse().unwrap())
+    }
+
+    #[test]
+    fn table() {
+        let tables = [("main", Table::MAIN), ("local", Table::LOCAL), ("default", Table::DEFAULT), ("100", Table(100))];
+        for (name, table) in tables {
+            assert_eq!(name.parse::<Table>().unwrap(), table);
+            assert_eq!(table.to_string(), name);
+        }
+        assert_eq!("all".parse::<Table>().unwrap(), Table::UNSPEC);
+        assert_eq!(Table::UNSPEC.to_string(), "unspec");
+        assert!("mian".parse::<Table>().is_err());
+        assert!("-1".parse::<Table>().is_err());
+    }
+
+    #[test]
+    fn route_type() {
+        for ty in 1..=9 {
+            let kind = RouteType::from(ty);
+            assert_eq!(u8::from(kind), ty);
+            assert_eq!(kind.to_string().parse::<RouteType>().unwrap(), kind);
+        }
+        assert_eq!(RouteType::from(42), RouteType::Other(42));
+        assert_eq!(RouteType::Other(42).to_string(), "42");
+        assert!("42".parse::<RouteType>().is_err());
+        assert!("nat".parse::<RouteType>().is_err());
+    }
+
+    #[test]
+    fn contains() {
+        let subnet = route("10.1.0.0/16");
+        assert!(subnet.contains(&IpAddr::from([10, 1, 255, 3])));
+        assert!(!subnet.contains(&IpAddr::from([10, 2, 0, 1])));
+        assert!(!subnet.contains(&"::a01:1".parse().unwrap()));
+        assert!(route("0.0.0.0/0").contains(&IpAddr::from([192, 0, 2, 1])));
+        assert!(route("10.1.2.3").contains(&IpAddr::from([10, 1, 2, 3])));
+        assert!(!route("10.1.2.3").contains(&IpAddr::from([10, 1, 2, 4])));
+
+        let subnet = route("2001:db8::/32");
+        assert!(subnet.contains(&"2001:db8:ffff::1".parse().unwrap()));
+        assert!(!subnet.contains(&"2001:db9::1".parse().unwrap()));
+        assert!(route("::/0").contains(&"2001:db9::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn lookup_longest_prefix() {
+        let mut default = route("0.0.0.0/0");
+        default.gateway = Some(IpAddr::from([10, 0, 0, 1]));
+        let subnet = route("10.1.0.0/16");
+        let mut host = route("10.1.2.3/32");
+        host.table = Table::LOCAL;
+        let mut slow = route("10.1.2.0/24");
+        slow.metric = Some(200);
+        let mut fast = route("10.1.2.0/24");
+        fast.metric = Some(100);
+        let routes = [default.clone(), subnet.clone(), host, slow, fast.clone()];
+
+        assert_eq!(lookup(&routes, &IpAddr::from([192, 0, 2, 1])), Some(&default));
+        assert_eq!(lookup(&routes, &IpAddr::from([10, 1, 3, 1])), Some(&subnet));
+        // Longest prefix first, then lowest metric, in the main table only
+        assert_eq!(lookup(&routes, &IpAddr::from([10, 1, 2, 3])), Some(&fast));
+        assert_eq!(lookup(&routes, &"2001:db8::1".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn display() {
+        let mut default = route("0.0.0.0/0");
+        default.gateway = Some(IpAddr::from([10, 0, 0, 1]));
+        default.interface = Some("eth0".to_string());
+        default.metric = Some(100);
+        assert_eq!(default.to_string(), "default via 10.0.0.1 dev eth0 metric 100");
+
+        let mut local = route("10.0.0.5/32");
+        local.kind = RouteType::Local;
+        local.table = Table::LOCAL;
+        local.interface = Some("eth0".to_string());
+        local.scope = local.default_scope();
+        local.source = Some(IpAddr::from([10, 0, 0, 5]));
+        assert_eq!(local.to_string(), "local 10.0.0.5 table local dev eth0 scope host src 10.0.0.5");
+
+        let mut onlink = route("2001:db8::/64");
+        onlink.gateway = Some("2001:db8:1::1".parse().unwrap());
+        onlink.onlink = true;
+        assert_eq!(onlink.to_string(), "2001:db8::/64 via 2001:db8:1::1 onlink");
+    }
+
+    #[test]
+    fn proc_ipv4_routes() {
+        let content = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+eth0\t00000000\t0100000A\t0003\t0\t0\t100\t00000000\t0\t0\t0
+eth0\t0000000A\t00000000\t0001\t0\t0\t0\t00FFFFFF\t0\t0\t0
+*\t000200C0\t00000000\t0201\t0\t0\t0\t00FFFFFF\t0\t0\t0
+";
+        let mut default = route("0.0.0.0/0");
+        default.gateway = Some(IpAddr::from([10, 0, 0, 1]));
+        default.interface = Some("eth0".to_string());
+        default.metric = Some(100);
+        let mut subnet = route("10.0.0.0/24");
+        subnet.interface = Some("eth0".to_string());
+        subnet.scope = Scope::Link;
+        let mut unreachable = route("192.0.2.0/24");
+        unreachable.kind = RouteType::Unreachable;
+        assert_eq!(parse_ipv4_routes(content).unwrap(), [default, subnet, unreachable]);
+
+        assert!(parse_ipv4_routes("Iface\neth0\t00000000\t0100000A\n").is_err());
+        assert!(parse_ipv4_routes("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn proc_ipv6_routes() {
+        let zero = "00000000000000000000000000000000";
+        let line = |destination: &str, len: &str, gateway: &str, metric: &str, flags: &str, interface: &str| {
+            let fields = [destination, len, zero, "00", gateway, metric, "00000001", "00000000", flags];
+            format!("{} {:>8}\n", fields.join(" "), interface)
+        };
+        let content = [
+            line("20010db8000000000000000000000000", "40", zero, "00000100", "00000001", "eth0"),
+            line(zero, "00", "fe800000000000000000000000000001", "00000400", "00450003", "eth0"),
+            line("00000000000000000000000000000001", "80", zero, "00000000", "80200001", "lo"),
+            line("ff000000000000000000000000000000", "08", zero, "00000100", "00000001", "eth0"),
+            line("20010db8000100000000000000000000", "30", zero, "00000400", "00200200", "lo"),
+            // Cached clone and the catch-all entry, both skipped
+            line("20010db8000000000000000000000005", "80", zero, "00000000", "01000001", "eth0"),
+            line(zero, "00", zero, "ffffffff", "00200200", "lo"),
+        ]
+        .concat();
+
+        let mut subnet = route("2001:db8::/64");
+        subnet.interface = Some("eth0".to_string());
+        subnet.metric = Some(256);
+        let mut default = route("::/0");
+        default.gateway = Some("fe80::1".parse().unwrap());
+        default.interface = Some("eth0".to_string());
+        default.metric = Some(1024);
+        let mut local = route("::1/128");
+        local.kind = RouteType::Local;
+        local.table = Table::LOCAL;
+        local.interface = Some("lo".to_string());
+        local.metric = Some(0);
+        let mut multicast = route("ff00::/8");
+        multicast.kind = RouteType::Multicast;
+        multicast.table = Table::LOCAL;
+        multicast.interface = Some("eth0".to_string());
+        multicast.metric = Some(256);
+        let mut unreachable = route("2001:db8:1::/48");
+        unreachable.kind = RouteType::Unreachable;
+        unreachable.interface = Some("lo".to_string());
+        unreachable.metric = Some(1024);
+        assert_eq!(parse_ipv6_routes(&content).unwrap(), [subnet, default, local, multicast, unreachable]);
+
+        assert!(parse_ipv6_routes("20010db8 40 eth0\n").is_err());
+    }
+}
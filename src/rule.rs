@@ -0,0 +1,173 @@
+//! Policy routing rules, selecting the routing table packets are looked up in
+use crate::addr::{Cidr, Family};
+use crate::route::Table;
+use anyhow::{bail, Result};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// What is done with packets matching a rule (FR_ACT_*)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleAction {
+    /// Look the route up in the table of the rule
+    Lookup,
+    /// Continue with the rule of the given priority
+    Goto,
+    Nop,
+    /// Silently discard
+    Blackhole,
+    /// Discard with ICMP network unreachable
+    Unreachable,
+    /// Discard with ICMP administratively prohibited
+    Prohibit,
+    Other(u8),
+}
+
+impl From<u8> for RuleAction {
+    fn from(action: u8) -> RuleAction {
+        match action {
+            1 => RuleAction::Lookup,
+            2 => RuleAction::Goto,
+            3 => RuleAction::Nop,
+            6 => RuleAction::Blackhole,
+            7 => RuleAction::Unreachable,
+            8 => RuleAction::Prohibit,
+            _ => RuleAction::Other(action),
+        }
+    }
+}
+
+impl From<RuleAction> for u8 {
+    fn from(action: RuleAction) -> u8 {
+        match action {
+            RuleAction::Lookup => 1,
+            RuleAction::Goto => 2,
+            RuleAction::Nop => 3,
+            RuleAction::Blackhole => 6,
+            RuleAction::Unreachable => 7,
+            RuleAction::Prohibit => 8,
+            RuleAction::Other(action) => action,
+        }
+    }
+}
+
+impl fmt::Display for RuleAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleAction::Lookup => write!(f, "lookup"),
+            RuleAction::Goto => write!(f, "goto"),
+            RuleAction::Nop => write!(f, "nop"),
+            RuleAction::Blackhole => write!(f, "blackhole"),
+            RuleAction::Unreachable => write!(f, "unreachable"),
+            RuleAction::Prohibit => write!(f, "prohibit"),
+            RuleAction::Other(action) => write!(f, "{}", action),
+        }
+    }
+}
+
+impl FromStr for RuleAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "lookup" => Ok(RuleAction::Lookup),
+            "goto" => Ok(RuleAction::Goto),
+            "nop" => Ok(RuleAction::Nop),
+            "blackhole" => Ok(RuleAction::Blackhole),
+            "unreachable" => Ok(RuleAction::Unreachable),
+            "prohibit" => Ok(RuleAction::Prohibit),
+            _ => bail!("Unknown rule action '{}'", s),
+        }
+    }
+}
+
+/// Serializes as its iproute2 name, e.g. `"lookup"`
+impl Serialize for RuleAction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Policy routing rule, unset selectors matching every packet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Rule {
+    pub family: Family,
+    /// Rules are evaluated from the lowest priority up, the kernel picks one when unset
+    pub priority: Option<u32>,
+    /// Source prefix of packets
+    pub from: Option<Cidr>,
+    /// Destination prefix of packets
+    pub to: Option<Cidr>,
+    /// Firewall mark of packets
+    pub fwmark: Option<u32>,
+    /// Bits of the firewall mark compared, all of them when unset
+    pub fwmask: Option<u32>,
+    /// Interface packets are received on
+    pub iif: Option<String>,
+    /// Interface packets are sent from, for sockets bound to it
+    pub oif: Option<String>,
+    /// Match packets not matching the selectors
+    pub invert: bool,
+    pub action: RuleAction,
+    /// Table routes are looked up in when the action is `lookup`
+    pub table: Option<Table>,
+    /// Priority of the rule to continue with when the action is `goto`
+    pub goto: Option<u32>,
+}
+
+impl Rule {
+    /// Rule of `family` matching every packet and looking routes up in `table`
+    pub fn new(family: Family, table: Table) -> Rule {
+        Rule {
+            family,
+            priority: None,
+            from: None,
+            to: None,
+            fwmark: None,
+            fwmask: None,
+            iif: None,
+            oif: None,
+            invert: false,
+            action: RuleAction::Lookup,
+            table: Some(table),
+            goto: None,
+        }
+    }
+}
+
+/// Formats as iproute2 does, e.g. `100: from 10.0.0.0/24 lookup 100`
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(priority) = self.priority {
+            write!(f, "{}:\t", priority)?;
+        }
+        if self.invert {
+            write!(f, "not ")?;
+        }
+        match self.from {
+            Some(from) => write!(f, "from {}", from)?,
+            None => write!(f, "from all")?,
+        }
+        if let Some(to) = self.to {
+            write!(f, " to {}", to)?;
+        }
+        if let Some(fwmark) = self.fwmark {
+            write!(f, " fwmark {:#x}", fwmark)?;
+            if let Some(fwmask) = self.fwmask {
+                write!(f, "/{:#x}", fwmask)?;
+            }
+        }
+        if let Some(iif) = &self.iif {
+            write!(f, " iif {}", iif)?;
+        }
+        if let Some(oif) = &self.oif {
+            write!(f, " oif {}", oif)?;
+        }
+        match (self.action, self.table, self.goto) {
+            (RuleAction::Lookup, Some(table), _) => write!(f, " lookup {}", table),
+            (RuleAction::Lookup, None, _) => Ok(()),
+            (RuleAction::Goto, _, Some(goto)) => write!(f, " goto {}", goto),
+            (action, _, _) => write!(f, " {}", action),
+        }
+    }
+}
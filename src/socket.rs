@@ -0,0 +1,63 @@
+//! Sockets the netdevice ioctls are issued on
+use anyhow::{bail, Result};
+use nix::sys::socket::{socket, AddressFamily, SockFlag, SockType};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::{Arc, Mutex, PoisonError};
+
+static INET: Mutex<Option<Arc<ControlSocket>>> = Mutex::new(None);
+static INET6: Mutex<Option<Arc<ControlSocket>>> = Mutex::new(None);
+
+/// Datagram socket owning its file descriptor, closed when dropped
+#[derive(Debug)]
+pub struct ControlSocket {
+    fd: OwnedFd,
+    family: AddressFamily,
+}
+
+impl ControlSocket {
+    /// Open a new socket of `family`, closed on exec
+    pub fn open(family: AddressFamily) -> Result<ControlSocket> {
+        let fd = socket(family, SockType::Datagram, SockFlag::SOCK_CLOEXEC, None)?;
+        Ok(ControlSocket {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            family,
+        })
+    }
+
+    /// Socket of `family` shared by the whole process, opened on first use
+    pub fn shared(family: AddressFamily) -> Result<Arc<ControlSocket>> {
+        let slot = match family {
+            AddressFamily::Inet => &INET,
+            AddressFamily::Inet6 => &INET6,
+            _ => bail!("Control sockets are only opened for AF_INET and AF_INET6"),
+        };
+
+        let mut slot = slot.lock().unwrap_or_else(PoisonError::into_inner);
+        match &*slot {
+            Some(sock) => Ok(sock.clone()),
+            None => {
+                let sock = Arc::new(ControlSocket::open(family)?);
+                *slot = Some(sock.clone());
+                Ok(sock)
+            }
+        }
+    }
+
+    /// Forget the shared sockets, for the next ones to be opened in the current network namespace
+    pub(crate) fn reset_shared() {
+        for slot in [&INET, &INET6].iter() {
+            *slot.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        }
+    }
+
+    /// Address family of the socket
+    pub fn family(&self) -> AddressFamily {
+        self.family
+    }
+}
+
+impl AsRawFd for ControlSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
@@ -0,0 +1,136 @@
+//! TUN and TAP devices, handing the packets of an interface to a program, see the kernel's tuntap.rst
+//!
+//! They are created by a TUNSETIFF on /dev/net/tun and only outlive the file
+//! descriptor once made persistent, deleting them means attaching to them
+//! again to clear TUNSETPERSIST.
+use crate::interface::Interface;
+use crate::ioctl::*;
+use anyhow::{anyhow, Result};
+use ifstructs::ifreq;
+use nix::errno::Errno;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// Flags of a TUN or TAP device which must match to attach to it (TUN_FEATURES and the type)
+const TUN_ATTACH_FLAGS: libc::c_int =
+    libc::IFF_TUN | libc::IFF_TAP | libc::IFF_NO_PI | libc::IFF_MULTI_QUEUE | libc::IFF_VNET_HDR;
+
+/// Options of a TUN or TAP device being created
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TunOptions {
+    /// User allowed to attach to the device, besides CAP_NET_ADMIN holders
+    pub owner: Option<u32>,
+    /// Group allowed to attach to the device, besides CAP_NET_ADMIN holders
+    pub group: Option<u32>,
+    /// Whether several file descriptors can attach to the device, one queue each
+    pub multi_queue: bool,
+    /// Whether packets are preceded by a virtio_net_hdr, for offloads of virtual machines
+    pub vnet_hdr: bool,
+}
+
+/// Formats as iproute2 does, e.g. `user 1000 group 1000 multi_queue vnet_hdr`
+impl fmt::Display for TunOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut options = Vec::new();
+        if let Some(owner) = self.owner {
+            options.push(format!("user {}", owner));
+        }
+        if let Some(group) = self.group {
+            options.push(format!("group {}", group));
+        }
+        if self.multi_queue {
+            options.push("multi_queue".to_string());
+        }
+        if self.vnet_hdr {
+            options.push("vnet_hdr".to_string());
+        }
+        write!(f, "{}", options.join(" "))
+    }
+}
+
+fn open() -> Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/net/tun")
+        .map_err(|e| anyhow!("Failed to open /dev/net/tun: {}", e))
+}
+
+/// Create the persistent TAP device `name` when `tap` is set, TUN device otherwise
+pub(crate) fn create(name: &str, tap: bool, options: &TunOptions) -> Result<Interface> {
+    // Without IFF_TUN_EXCL an existing device of the same type would be attached to instead
+    let mut flags = libc::IFF_NO_PI | libc::IFF_TUN_EXCL | if tap { libc::IFF_TAP } else { libc::IFF_TUN };
+    if options.multi_queue {
+        flags |= libc::IFF_MULTI_QUEUE;
+    }
+    if options.vnet_hdr {
+        flags |= libc::IFF_VNET_HDR;
+    }
+    let mut ifr = ifreq::from_name(name)?;
+    set_tun_flags(&mut ifr, flags);
+
+    // The device goes away with the file until made persistent, which is done last
+    let file = open()?;
+    let fd = file.as_raw_fd();
+    let kind = if tap { "tap" } else { "tun" };
+    unsafe { set_tun_iff(fd, &ifr) }.map_err(|e| match e {
+        Errno::EBUSY => anyhow!("Link '{}' already exists", name),
+        e => anyhow!("Failed to create {} link '{}': {}", kind, name, e),
+    })?;
+    if let Some(owner) = options.owner {
+        unsafe { set_tun_owner(fd, owner as libc::c_int) }
+            .map_err(|e| anyhow!("Failed to set the owner of '{}': {}", name, e))?;
+    }
+    if let Some(group) = options.group {
+        unsafe { set_tun_group(fd, group as libc::c_int) }
+            .map_err(|e| anyhow!("Failed to set the group of '{}': {}", name, e))?;
+    }
+    unsafe { set_tun_persist(fd, 1) }.map_err(|e| anyhow!("Failed to make '{}' persistent: {}", name, e))?;
+    Interface::new(name)
+}
+
+/// Path of the TUN_* flags of `name` in sysfs, which only TUN and TAP devices have
+fn flags_path(name: &str) -> std::path::PathBuf {
+    Path::new("/sys/class/net").join(name).join("tun_flags")
+}
+
+/// Whether `name` is a TUN or TAP device
+pub(crate) fn is_tun(name: &str) -> bool {
+    flags_path(name).exists()
+}
+
+/// Delete the persistent TUN or TAP device `name`, failing while a program is attached to it
+pub(crate) fn delete(name: &str) -> Result<()> {
+    // tun_flags holds e.g. `0x1002`
+    let content = fs::read_to_string(flags_path(name))?;
+    let flags = libc::c_int::from_str_radix(content.trim().trim_start_matches("0x"), 16)
+        .map_err(|e| anyhow!("Unexpected tun_flags '{}' of '{}': {}", content.trim(), name, e))?;
+    let mut ifr = ifreq::from_name(name)?;
+    set_tun_flags(&mut ifr, flags & TUN_ATTACH_FLAGS);
+
+    let file = open()?;
+    let fd = file.as_raw_fd();
+    unsafe { set_tun_iff(fd, &ifr) }.map_err(|e| anyhow!("Failed to attach to '{}': {}", name, e))?;
+    unsafe { set_tun_persist(fd, 0) }.map_err(|e| anyhow!("Failed to delete link '{}': {}", name, e))?;
+    // Closing the file takes the device along
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let mut options = TunOptions::default();
+        assert_eq!(options.to_string(), "");
+        options.multi_queue = true;
+        assert_eq!(options.to_string(), "multi_queue");
+        options.owner = Some(1000);
+        options.group = Some(100);
+        options.vnet_hdr = true;
+        assert_eq!(options.to_string(), "user 1000 group 100 multi_queue vnet_hdr");
+    }
+}
@@ -0,0 +1,73 @@
+//! Point-to-point IP tunnels: GRE (RFC 2784), IPIP (RFC 2003), SIT (RFC 4213) and ip6tnl (RFC 2473)
+use std::fmt;
+use std::net::IpAddr;
+
+/// Options of a tunnel, IPv6 endpoints being those of ip6tnl tunnels and IPv4 ones of the others
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TunnelOptions {
+    /// Source address of the encapsulated packets, any when unset
+    pub local: Option<IpAddr>,
+    /// Other end of the tunnel, packets from any address being accepted when unset
+    pub remote: Option<IpAddr>,
+    /// Interface the encapsulated packets are sent through
+    pub parent: Option<String>,
+    /// TTL or hop limit of the encapsulated packets, 0 or unset inheriting the one of the inner packet
+    pub ttl: Option<u8>,
+    /// Key of the packets sent and expected, only for GRE tunnels
+    pub key: Option<u32>,
+    /// Whether the encapsulated packets are sent with DF set, on when unset, only for IPv4 tunnels
+    pub pmtudisc: Option<bool>,
+}
+
+/// Formats as iproute2 does, e.g. `remote 10.0.0.2 local 10.0.0.1 dev eth0 ttl 64 key 42 nopmtudisc`
+impl fmt::Display for TunnelOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut options = Vec::new();
+        if let Some(remote) = self.remote {
+            options.push(format!("remote {}", remote));
+        }
+        if let Some(local) = self.local {
+            options.push(format!("local {}", local));
+        }
+        if let Some(parent) = &self.parent {
+            options.push(format!("dev {}", parent));
+        }
+        if let Some(ttl) = self.ttl {
+            options.push(format!("ttl {}", ttl));
+        }
+        if let Some(key) = self.key {
+            options.push(format!("key {}", key));
+        }
+        match self.pmtudisc {
+            Some(true) => options.push("pmtudisc".to_string()),
+            Some(false) => options.push("nopmtudisc".to_string()),
+            None => {}
+        }
+        write!(f, "{}", options.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let mut options = TunnelOptions::default();
+        assert_eq!(options.to_string(), "");
+        options.remote = Some(IpAddr::from([10, 0, 0, 2]));
+        options.local = Some(IpAddr::from([10, 0, 0, 1]));
+        options.parent = Some("eth0".to_string());
+        options.ttl = Some(64);
+        options.key = Some(42);
+        options.pmtudisc = Some(false);
+        assert_eq!(options.to_string(), "remote 10.0.0.2 local 10.0.0.1 dev eth0 ttl 64 key 42 nopmtudisc");
+
+        let options = TunnelOptions {
+            remote: Some("2001:db8::2".parse().unwrap()),
+            pmtudisc: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(options.to_string(), "remote 2001:db8::2 pmtudisc");
+    }
+}
@@ -0,0 +1,263 @@
+//! WireGuard tunnels, configured through the `wireguard` generic netlink family
+//!
+//! Keys are the Curve25519 ones of wg(8), written in base64. The kernel never
+//! gives the private key of a device back, only the public key derived from it.
+use crate::addr::Cidr;
+use anyhow::{anyhow, Result};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+const KEY_LEN: usize = 32;
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Public, private or preshared key of WireGuard, e.g. `yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct WireguardKey([u8; KEY_LEN]);
+
+impl WireguardKey {
+    pub fn octets(&self) -> [u8; KEY_LEN] {
+        self.0
+    }
+}
+
+impl From<[u8; KEY_LEN]> for WireguardKey {
+    fn from(key: [u8; KEY_LEN]) -> Self {
+        WireguardKey(key)
+    }
+}
+
+/// Parses the 44 characters of base64 of wg(8), the unused bits of the last one being zero
+impl FromStr for WireguardKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("Invalid WireGuard key '{}', expected 32 bytes in base64", s);
+        let digits = s.strip_suffix('=').filter(|digits| digits.len() == 43).ok_or_else(invalid)?;
+        let mut key = [0u8; KEY_LEN];
+        let mut len = 0;
+        let (mut bits, mut bit_count) = (0u32, 0);
+        for c in digits.bytes() {
+            let value = BASE64.iter().position(|digit| *digit == c).ok_or_else(invalid)?;
+            bits = bits << 6 | value as u32;
+            bit_count += 6;
+            if bit_count >= 8 {
+                bit_count -= 8;
+                key[len] = (bits >> bit_count) as u8;
+                len += 1;
+                bits &= (1 << bit_count) - 1;
+            }
+        }
+        if bits != 0 {
+            return Err(invalid());
+        }
+        Ok(WireguardKey(key))
+    }
+}
+
+impl fmt::Display for WireguardKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = String::with_capacity(44);
+        for chunk in self.0.chunks(3) {
+            let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| bits | u32::from(*byte) << (16 - 8 * i));
+            // n bytes take n + 1 digits, padded to 4
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    s.push(BASE64[(bits >> (18 - 6 * i)) as usize & 0x3f] as char);
+                } else {
+                    s.push('=');
+                }
+            }
+        }
+        f.write_str(&s)
+    }
+}
+
+/// Keeps keys out of logs, private and preshared ones being secrets
+impl fmt::Debug for WireguardKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WireguardKey(..)")
+    }
+}
+
+/// Serializes as its base64 string
+impl Serialize for WireguardKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Peer of a WireGuard device, as reported by the kernel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WireguardPeer {
+    pub public_key: WireguardKey,
+    /// Address packets are sent to, the last one the peer was heard from
+    pub endpoint: Option<SocketAddr>,
+    /// Destinations routed to the peer, and sources accepted from it
+    pub allowed_ips: Vec<Cidr>,
+    /// Seconds between two keepalives, unset when they are off
+    pub persistent_keepalive: Option<u16>,
+    /// Seconds since the Unix epoch at the last handshake, unset if none happened
+    pub last_handshake: Option<u64>,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+/// WireGuard device and its peers, as reported by the kernel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WireguardDevice {
+    pub interface: String,
+    /// Unset until a private key is set
+    pub public_key: Option<WireguardKey>,
+    /// UDP port listened on, picked at random when left to 0 and the device is up
+    pub listen_port: u16,
+    /// Firewall mark of the encapsulated packets, unset for none
+    pub fwmark: Option<u32>,
+    pub peers: Vec<WireguardPeer>,
+}
+
+/// Peer to add or change, or to remove when `remove` is set, what is left unset being kept as it is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WireguardPeerConfig {
+    pub public_key: WireguardKey,
+    /// Key mixed into the handshakes for post-quantum resistance, all zeros removing it
+    pub preshared_key: Option<WireguardKey>,
+    /// Address to send packets to until the peer is heard from
+    pub endpoint: Option<SocketAddr>,
+    /// Seconds between two keepalives, 0 turning them off
+    pub persistent_keepalive: Option<u16>,
+    /// Added to the allowed IPs of the peer, or replacing them when `replace_allowed_ips` is set
+    pub allowed_ips: Vec<Cidr>,
+    pub replace_allowed_ips: bool,
+    pub remove: bool,
+}
+
+impl WireguardPeerConfig {
+    pub fn new(public_key: WireguardKey) -> WireguardPeerConfig {
+        WireguardPeerConfig {
+            public_key,
+            preshared_key: None,
+            endpoint: None,
+            persistent_keepalive: None,
+            allowed_ips: Vec::new(),
+            replace_allowed_ips: false,
+            remove: false,
+        }
+    }
+}
+
+/// Formats as wg(8) takes it, e.g. `peer yAnz...Bmk= endpoint 192.0.2.1:51820 allowed-ips 10.0.0.2/32`,
+/// leaving out the preshared key
+impl fmt::Display for WireguardPeerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "peer {}", self.public_key)?;
+        if self.remove {
+            return write!(f, " remove");
+        }
+        if let Some(endpoint) = self.endpoint {
+            write!(f, " endpoint {}", endpoint)?;
+        }
+        if let Some(keepalive) = self.persistent_keepalive {
+            write!(f, " persistent-keepalive {}", keepalive)?;
+        }
+        if !self.allowed_ips.is_empty() || self.replace_allowed_ips {
+            let allowed_ips: Vec<String> = self.allowed_ips.iter().map(Cidr::to_string).collect();
+            write!(f, " allowed-ips {}", allowed_ips.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Changes to a WireGuard device, what is left unset being kept as it is
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WireguardConfig {
+    pub private_key: Option<WireguardKey>,
+    /// UDP port to listen on, 0 for a random one
+    pub listen_port: Option<u16>,
+    /// Firewall mark of the encapsulated packets, 0 for none
+    pub fwmark: Option<u32>,
+    /// Whether the peers missing from `peers` are removed
+    pub replace_peers: bool,
+    /// Peers to add, change or remove
+    pub peers: Vec<WireguardPeerConfig>,
+}
+
+impl WireguardConfig {
+    /// Whether nothing would be changed
+    pub fn is_empty(&self) -> bool {
+        *self == WireguardConfig::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Private and public keys of the examples of wg(8)
+    const PRIVATE_KEY: &str = "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=";
+    const PRIVATE_OCTETS: [u8; KEY_LEN] = [
+        0xc8, 0x09, 0xf3, 0xe5, 0x31, 0x7e, 0x95, 0x75, 0xc9, 0xb5, 0xed, 0x78, 0xb6, 0x38, 0xb7, 0xce, 0x53, 0x0d,
+        0xab, 0xe8, 0x5d, 0xda, 0xb6, 0x14, 0x22, 0x02, 0x41, 0x80, 0x1d, 0xdf, 0x06, 0x69,
+    ];
+    const PUBLIC_KEY: &str = "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=";
+    const PUBLIC_OCTETS: [u8; KEY_LEN] = [
+        0xc5, 0x32, 0x01, 0x03, 0x9a, 0xdb, 0xa1, 0x4b, 0xe7, 0x1f, 0x88, 0x6d, 0xa1, 0xd8, 0xdb, 0xe9, 0xee, 0xbd,
+        0xed, 0x08, 0xcb, 0x11, 0x1b, 0x75, 0x34, 0x00, 0x78, 0x99, 0x9a, 0xa9, 0xf0, 0x38,
+    ];
+
+    #[test]
+    fn parse_key() {
+        assert_eq!(PRIVATE_KEY.parse::<WireguardKey>().unwrap().octets(), PRIVATE_OCTETS);
+        assert_eq!(PUBLIC_KEY.parse::<WireguardKey>().unwrap().octets(), PUBLIC_OCTETS);
+    }
+
+    #[test]
+    fn format_key() {
+        assert_eq!(WireguardKey::from(PRIVATE_OCTETS).to_string(), PRIVATE_KEY);
+        assert_eq!(WireguardKey::from(PUBLIC_OCTETS).to_string(), PUBLIC_KEY);
+        assert_eq!(WireguardKey::from([0; KEY_LEN]).to_string(), format!("{}=", "A".repeat(43)));
+        assert_eq!(WireguardKey::from([0xff; KEY_LEN]).to_string(), format!("{}8=", "/".repeat(42)));
+    }
+
+    #[test]
+    fn round_trip() {
+        for octets in [PRIVATE_OCTETS, PUBLIC_OCTETS, [0; KEY_LEN], [0xff; KEY_LEN]] {
+            let key = WireguardKey::from(octets);
+            assert_eq!(key.to_string().parse::<WireguardKey>().unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn bad_padding() {
+        let digits = &PRIVATE_KEY[..43];
+        for s in [
+            digits.to_string(),
+            format!("{}==", digits),
+            format!("{}A", digits),
+            format!("{}=", &digits[..42]),
+            format!("{}=A", &digits[..42]),
+            format!("{}=", PRIVATE_KEY),
+            String::new(),
+        ] {
+            assert!(s.parse::<WireguardKey>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn trailing_bits() {
+        // The 43rd digit carries 4 bits of the key and 2 unused ones, set here
+        for last in ["F", "G", "H"] {
+            let s = format!("{}{}=", "A".repeat(42), last);
+            assert!(s.parse::<WireguardKey>().is_err(), "{}", s);
+        }
+        assert!(format!("{}E=", "A".repeat(42)).parse::<WireguardKey>().is_ok());
+    }
+
+    #[test]
+    fn bad_digits() {
+        for s in [PRIVATE_KEY.replace('+', "-"), PRIVATE_KEY.replace('+', "_"), PUBLIC_KEY.replace('x', " ")] {
+            assert!(s.parse::<WireguardKey>().is_err(), "{}", s);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Running network_config in named network namespaces of the tests' own
+//!
+//! Creating namespaces and links takes root, the tests pass without checking anything otherwise.
+#![allow(dead_code)]
+use nix::unistd::geteuid;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub fn network_config(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_network_config"))
+        .args(args)
+        .env("RUST_BACKTRACE", "0")
+        .output()
+        .expect("Failed to run network_config")
+}
+
+/// Named network namespace deleted on drop
+pub struct TestNetns(String);
+
+impl TestNetns {
+    /// Create a namespace of a unique name, none when not privileged enough to
+    pub fn new() -> Option<TestNetns> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        if !geteuid().is_root() {
+            eprintln!("Skipped, creating links takes root");
+            return None;
+        }
+        let name = format!("network-config-test-{}-{}", std::process::id(), COUNT.fetch_add(1, Ordering::Relaxed));
+        let output = network_config(&["netns", "add", &name]);
+        if !output.status.success() {
+            eprintln!("Skipped, failed to create a namespace: {}", String::from_utf8_lossy(&output.stderr));
+            return None;
+        }
+        Some(TestNetns(name))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    /// Run network_config in the namespace
+    pub fn run(&self, args: &[&str]) -> Output {
+        let mut all = vec!["--netns", &self.0];
+        all.extend(args);
+        network_config(&all)
+    }
+
+    /// Run network_config in the namespace, asserting it succeeds, and return what it printed
+    pub fn check(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(output.status.success(), "{:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    pub fn exists(&self, interface: &str) -> bool {
+        self.run(&["mtu", interface]).status.success()
+    }
+}
+
+impl Drop for TestNetns {
+    fn drop(&mut self) {
+        network_config(&["netns", "del", &self.0]);
+    }
+}
@@ -0,0 +1,70 @@
+//! `link add` and `link del` run against the kernel, in a network namespace of their own
+mod common;
+
+use common::TestNetns;
+
+#[test]
+fn dummy() {
+    let netns = match TestNetns::new() {
+        Some(netns) => netns,
+        None => return,
+    };
+    let output = netns.run(&["link", "add", "dummy0", "dummy"]);
+    if String::from_utf8_lossy(&output.stderr).contains("Unknown device type") {
+        eprintln!("Skipped, the kernel has no dummy links");
+        return;
+    }
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(netns.exists("dummy0"));
+
+    netns.check(&["link", "del", "dummy0"]);
+    assert!(!netns.exists("dummy0"));
+}
+
+#[test]
+fn veth() {
+    let netns = match TestNetns::new() {
+        Some(netns) => netns,
+        None => return,
+    };
+    netns.check(&["link", "add", "veth0", "veth", "--peer", "veth1"]);
+    assert!(netns.exists("veth0"));
+    assert!(netns.exists("veth1"));
+    assert!(!netns.run(&["link", "add", "veth0", "veth", "--peer", "veth2"]).status.success());
+    assert!(!netns.exists("veth2"));
+
+    // Deleting either end takes the peer along
+    netns.check(&["link", "del", "veth1"]);
+    assert!(!netns.exists("veth0"));
+    assert!(!netns.exists("veth1"));
+}
+
+#[test]
+fn existing_tap() {
+    let netns = match TestNetns::new() {
+        Some(netns) => netns,
+        None => return,
+    };
+    netns.check(&["link", "add", "tap0", "tap"]);
+    // Failing to add it again must leave the existing link alone
+    assert!(!netns.run(&["link", "add", "tap0", "--ip", "10.7.7.7/24", "tap"]).status.success());
+    assert!(netns.exists("tap0"));
+    assert!(!netns.check(&["list"]).contains("10.7.7.7"));
+
+    netns.check(&["link", "del", "tap0"]);
+    assert!(!netns.exists("tap0"));
+}
+
+#[test]
+fn missing() {
+    let netns = match TestNetns::new() {
+        Some(netns) => netns,
+        None => return,
+    };
+    for backend in ["netlink", "ioctl"] {
+        let output = netns.run(&["--backend", backend, "link", "del", "missing0"]);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("No such device") && !stderr.contains("hardware address"), "{}: {}", backend, stderr);
+    }
+}
@@ -0,0 +1,81 @@
+//! WireGuard handshake between two namespaces joined by a veth pair
+mod common;
+
+use common::TestNetns;
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Private keys, any 32 bytes being one
+const KEY_A: &str = "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=";
+const KEY_B: &str = "zO2GodnQA5GdMGwymfngK6iuCrwX/KGWtwjCct1GwAk=";
+
+/// `wg show` of the device wg0 of `netns`
+fn show(netns: &TestNetns) -> Value {
+    let devices: Value = serde_json::from_str(&netns.check(&["--output", "json", "wg", "show", "wg0"])).unwrap();
+    devices[0].clone()
+}
+
+/// Create the device wg0 with the private key `key`, listening on port 51820, and return its public key
+fn add_device(netns: &TestNetns, key: &str) -> String {
+    let path: PathBuf = std::env::temp_dir().join(format!("{}.key", netns.name()));
+    fs::write(&path, key).unwrap();
+    let output = netns.run(&["wg", "set", "wg0", "--private-key", path.to_str().unwrap(), "--listen-port", "51820"]);
+    fs::remove_file(&path).unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    netns.check(&["up", "wg0"]);
+    show(netns)["public_key"].as_str().unwrap().to_string()
+}
+
+#[test]
+fn handshake() {
+    let (a, b) = match (TestNetns::new(), TestNetns::new()) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return,
+    };
+    let output = a.run(&["link", "add", "wg0", "wireguard"]);
+    if !output.status.success() {
+        eprintln!("Skipped, failed to create a WireGuard device: {}", String::from_utf8_lossy(&output.stderr));
+        return;
+    }
+    b.check(&["link", "add", "wg0", "wireguard"]);
+
+    a.check(&["link", "add", "--ip", "192.0.2.1/24", "--up", "veth0", "veth", "--peer", "veth1"]);
+    a.check(&["link", "set-netns", "veth1", b.name()]);
+    b.check(&["addr", "add", "veth1", "192.0.2.2/24"]);
+    b.check(&["up", "veth1"]);
+
+    let public_a = add_device(&a, KEY_A);
+    let public_b = add_device(&b, KEY_B);
+    assert_ne!(public_a, public_b);
+    b.check(&["wg", "peer", "add", "wg0", &public_a, "--allowed-ip", "10.0.0.1/32"]);
+    // Keepalives start with a handshake as soon as the peer is set
+    a.check(&[
+        "wg",
+        "peer",
+        "add",
+        "wg0",
+        &public_b,
+        "--endpoint",
+        "192.0.2.2:51820",
+        "--allowed-ip",
+        "10.0.0.2/32",
+        "--persistent-keepalive",
+        "1",
+    ]);
+
+    let start = Instant::now();
+    loop {
+        let device = show(&b);
+        let peer = &device["peers"][0];
+        assert_eq!(peer["public_key"], public_a.as_str());
+        if !peer["last_handshake"].is_null() {
+            assert_eq!(peer["endpoint"], "192.0.2.1:51820");
+            break;
+        }
+        assert!(start.elapsed() < Duration::from_secs(10), "No handshake: {}", device);
+        thread::sleep(Duration::from_millis(100));
+    }
+}